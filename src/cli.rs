@@ -0,0 +1,448 @@
+//! Headless mode, for scripting:
+//!
+//! ```txt
+//! uad-ng --apply <file> [--device <serial>] [--user <id>] [--quiet]
+//! ```
+//!
+//! `<file>` has the format of a selection export: 1 package name per line.
+//! With `-` as `<file>`, the selection is read from stdin.
+//! Every listed package that's enabled for the target user gets uninstalled
+//! (or disabled, if "disable mode" is on in the device settings).
+//! With "dry run" on in the settings, the commands are only printed.
+//! The hooks of the settings (see [`crate::core::hooks`]) run before and after it:
+//! a failing pre-apply hook stops before any package is touched.
+//! A device that's still booting is waited for, up to [`WAIT_FOR_DEVICE_TIMEOUT`].
+//!
+//! A [`Summary`] is printed at the end, even with `--quiet` (which hides the per-package lines).
+//! The exit code is the number of failed packages, with codes reserved for
+//! [`EXIT_ERROR`] and [`EXIT_NO_DEVICE`], see [`USAGE`].
+//!
+//! Without `--apply`, the GUI starts as usual.
+
+use crate::core::{
+    adb::{self, WaitError},
+    backend::backend,
+    config::Config,
+    hooks::{self, Hook},
+    sync::{
+        CorePackage, Phone, User, apply_pkg_state_commands, format_device_name, get_android_sdk,
+        get_device_brand, get_device_model, list_users_idx_prot,
+    },
+    uad_lists::{Opposite, PackageHashMap, PackageState},
+    utils::{fetch_packages, parse_selection},
+};
+use std::{collections::HashSet, fs, io, path::PathBuf, time::Duration};
+
+/// How long to wait for the device to be listed, e.g. when it's still booting
+pub const WAIT_FOR_DEVICE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Nothing was applied: invalid arguments, unreadable file, ADB failure...
+pub const EXIT_ERROR: u8 = 254;
+/// No (matching) authorized device, even after [`WAIT_FOR_DEVICE_TIMEOUT`].
+/// Unlike [`EXIT_ERROR`], worth retrying.
+pub const EXIT_NO_DEVICE: u8 = 255;
+
+/// Printed by `--help`
+pub const USAGE: &str = "\
+Usage: uad-ng [--apply <file> [--device <serial>] [--user <id>] [--quiet]]
+
+Without --apply, the GUI starts.
+
+  --apply <file>      Uninstall (or disable) the packages listed in <file>, 1 per line.
+                      With - as <file>, they're read from stdin
+  --device <serial>   Target device, defaults to the first authorized one
+  --user <id>         Target user, defaults to the first user of the device
+  --quiet             Only print the summary
+
+Exit codes of --apply:
+  0       every action was applied
+  1-253   number of failed packages (capped)
+  254     error, nothing was applied
+  255     no device found, even after waiting 30s
+";
+
+/// Returns `true` if `--help` (or `-h`) was passed
+pub fn help_requested(mut args: impl Iterator<Item = String>) -> bool {
+    args.any(|arg| arg == "--help" || arg == "-h")
+}
+
+/// Parsed headless-mode arguments
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Args {
+    /// Serial of the target device. Defaults to the first authorized one.
+    pub device: Option<String>,
+    /// Target user ID. Defaults to the first user of the device.
+    pub user: Option<u16>,
+    /// Selection file to apply, `-` for stdin
+    pub apply: PathBuf,
+    /// Only print the summary, not 1 line per package
+    pub quiet: bool,
+}
+
+/// Outcome of [`run`]
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Summary {
+    /// Packages that had to change state
+    pub actions: usize,
+    pub failed: usize,
+    pub dry_run: bool,
+}
+
+impl Summary {
+    /// The number of failures, for CI to gate on
+    pub fn exit_code(&self) -> u8 {
+        u8::try_from(self.failed).map_or(EXIT_ERROR - 1, |n| n.min(EXIT_ERROR - 1))
+    }
+}
+
+/// Why [`run`] stopped before applying anything
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    NoDevice(String),
+    Failed(String),
+}
+
+impl Error {
+    pub const fn exit_code(&self) -> u8 {
+        match self {
+            Self::NoDevice(_) => EXIT_NO_DEVICE,
+            Self::Failed(_) => EXIT_ERROR,
+        }
+    }
+}
+
+impl From<String> for Error {
+    fn from(err: String) -> Self {
+        Self::Failed(err)
+    }
+}
+
+impl From<WaitError> for Error {
+    fn from(err: WaitError) -> Self {
+        match err {
+            WaitError::Timeout(_) => Self::NoDevice(err.to_string()),
+            WaitError::Adb(e) => Self::Failed(e),
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NoDevice(e) | Self::Failed(e) => write!(f, "{e}"),
+        }
+    }
+}
+
+impl std::fmt::Display for Summary {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let applied = self.actions - self.failed;
+        write!(f, "Applied {applied}/{} actions", self.actions)?;
+        if self.failed > 0 {
+            write!(f, "; {} failed (see above)", self.failed)?;
+        }
+        if self.dry_run {
+            write!(f, " (dry run)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns `Ok(None)` when headless mode wasn't requested (no `--apply`).
+///
+/// Unknown flags are ignored, as they may be meant for the GUI.
+pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Option<Args>, String> {
+    let mut device = None;
+    let mut user = None;
+    let mut apply = None;
+    let mut quiet = false;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or(format!("Missing value for `{arg}`"));
+        match arg.as_str() {
+            "--device" => device = Some(value()?),
+            "--user" => {
+                let v = value()?;
+                user = Some(
+                    v.parse()
+                        .map_err(|_| format!("Invalid user ID `{v}`: expected a number"))?,
+                );
+            }
+            "--apply" => apply = Some(PathBuf::from(value()?)),
+            "--quiet" => quiet = true,
+            _ => {}
+        }
+    }
+
+    Ok(apply.map(|apply| Args {
+        device,
+        user,
+        apply,
+        quiet,
+    }))
+}
+
+/// Finds the user with ID `user_id`, or the first one if `None`.
+///
+/// Protected users are refused, as their packages can't be managed.
+pub fn resolve_user(users: &[User], user_id: Option<u16>) -> Result<User, String> {
+    let user = match user_id {
+        Some(id) => users
+            .iter()
+            .find(|u| u.id == id)
+            .ok_or(format!("User {id} doesn't exist on this device"))?,
+        None => users.first().ok_or("This device has no users")?,
+    };
+    if user.protected {
+        return Err(format!(
+            "User {} is protected and can't be managed: {}",
+            user.id,
+            user.protected_reason.as_deref().unwrap_or("unknown reason")
+        ));
+    }
+    Ok(user.clone())
+}
+
+fn select_device(serial: Option<&str>) -> Result<Phone, Error> {
+    let devices = backend().devices_long()?;
+    let device = devices
+        .into_iter()
+        .filter(|d| d.status == "device")
+        .find(|d| serial.is_none_or(|serial| d.serial == serial))
+        .ok_or_else(|| {
+            Error::NoDevice(match serial {
+                Some(s) => format!("Device `{s}` not found or unauthorized"),
+                None => "No authorized device found".to_string(),
+            })
+        })?;
+    let adb_id = device.serial;
+    Ok(Phone {
+        model: format_device_name(
+            &get_device_brand(&adb_id),
+            &get_device_model(&adb_id),
+            &adb_id,
+        ),
+        android_sdk: get_android_sdk(&adb_id),
+        user_list: list_users_idx_prot(&adb_id),
+        adb_id,
+        transport: device.transport,
+        backup: None,
+        favorite: false,
+    })
+}
+
+/// Applies the selection file, printing 1 line per package unless [`Args::quiet`]
+pub fn run(args: &Args) -> Result<Summary, Error> {
+    let selection = if args.apply.as_os_str() == "-" {
+        io::read_to_string(io::stdin()).map_err(|e| format!("Cannot read stdin: {e}"))?
+    } else {
+        fs::read_to_string(&args.apply)
+            .map_err(|e| format!("Cannot read `{}`: {e}", args.apply.display()))?
+    };
+    let selection = parse_selection(&selection);
+    if selection.is_empty() {
+        println!("Nothing to do: the selection is empty");
+        return Ok(Summary::default());
+    }
+
+    let config = Config::load_configuration_file();
+    adb::set_server(config.general.parsed_adb_server());
+
+    backend().wait_for_device(
+        args.device.as_deref().unwrap_or_default(),
+        WAIT_FOR_DEVICE_TIMEOUT,
+    )?;
+    let phone = select_device(args.device.as_deref())?;
+    let user = resolve_user(&phone.user_list, args.user)?;
+    println!("{} ({}), {user}", phone.model, phone.adb_id);
+
+    let device_settings = config.devices.iter().find(|d| d.device_id == phone.adb_id);
+    let disable_mode = device_settings.is_some_and(|d| d.disable_mode);
+    let keep_data = device_settings.is_some_and(|d| d.keep_data);
+    let dry_run = config.general.dry_run;
+
+    // which are disabled until used doesn't matter here
+    let packages = fetch_packages(
+        &PackageHashMap::new(),
+        &phone.adb_id,
+        Some(user.id),
+        &HashSet::new(),
+    );
+
+    let mut summary = Summary {
+        dry_run,
+        ..Summary::default()
+    };
+    let report = |line: String| {
+        if !args.quiet {
+            println!("{line}");
+        }
+    };
+    let run_hook = |hook: Hook| -> Result<(), String> {
+        let Some(script) = hook.script(&config.general) else {
+            return Ok(());
+        };
+        if dry_run {
+            report(format!(
+                "dry run hook: {} {}",
+                script.display(),
+                phone.adb_id
+            ));
+            return Ok(());
+        }
+        hooks::run(script, &phone.adb_id).map_err(|e| e.message().to_string())
+    };
+    run_hook(Hook::PreApply)?;
+    for name in selection.iter().map(String::as_str) {
+        let Some(package) = packages.iter().find(|p| p.name == name) else {
+            report(format!("skipped {name}: not found for {user}"));
+            continue;
+        };
+        if package.state != PackageState::Enabled {
+            report(format!("skipped {name}: already {}", package.state));
+            continue;
+        }
+        let wanted_state = package.state.opposite(disable_mode);
+        let commands = apply_pkg_state_commands(
+            &CorePackage::from(package),
+            wanted_state,
+            Some(user.clone()),
+            &phone,
+            keep_data,
+        );
+        summary.actions += 1;
+        if dry_run {
+            for cmd in &commands {
+                report(format!(
+                    "dry run {name}: adb -s {} shell {cmd}",
+                    phone.adb_id
+                ));
+            }
+            continue;
+        }
+        match commands
+            .iter()
+            .try_for_each(|cmd| backend().shell(&phone.adb_id, cmd).map(|_| ()))
+        {
+            Ok(()) => report(format!("{wanted_state} {name}")),
+            Err(err) => {
+                summary.failed += 1;
+                report(format!("failed {name}: {err}"));
+            }
+        }
+    }
+    if let Err(err) = run_hook(Hook::PostApply) {
+        eprintln!("ERROR: {err}");
+    }
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn users() -> Vec<User> {
+        vec![
+            User {
+                id: 0,
+                index: 0,
+                protected: false,
+                ..User::default()
+            },
+            User {
+                id: 10,
+                index: 1,
+                protected: false,
+                ..User::default()
+            },
+            User {
+                id: 150,
+                index: 2,
+                protected: true,
+                ..User::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn parse_args() {
+        let args = |s: &str| parse(s.split_whitespace().map(String::from));
+        assert_eq!(args(""), Ok(None));
+        assert_eq!(args("--mock"), Ok(None));
+        assert_eq!(
+            args("--device X --user 10 --apply sel.txt"),
+            Ok(Some(Args {
+                device: Some("X".to_string()),
+                user: Some(10),
+                apply: PathBuf::from("sel.txt"),
+                quiet: false,
+            }))
+        );
+        assert_eq!(
+            args("--apply sel.txt --quiet").map(|a| a.map(|a| a.quiet)),
+            Ok(Some(true))
+        );
+        assert_eq!(
+            args("--apply -").map(|a| a.map(|a| a.apply)),
+            Ok(Some(PathBuf::from("-")))
+        );
+        assert!(args("--apply sel.txt --user ten").is_err());
+        assert!(args("--apply").is_err());
+    }
+
+    #[test]
+    fn resolve_users() {
+        let users = users();
+        assert_eq!(resolve_user(&users, None), Ok(users[0].clone()));
+        assert_eq!(resolve_user(&users, Some(10)), Ok(users[1].clone()));
+        assert!(resolve_user(&users, Some(150)).is_err());
+        assert!(resolve_user(&users, Some(11)).is_err());
+        assert!(resolve_user(&[], None).is_err());
+    }
+
+    #[test]
+    fn summary() {
+        let summary = Summary {
+            actions: 45,
+            failed: 3,
+            dry_run: false,
+        };
+        assert_eq!(
+            summary.to_string(),
+            "Applied 42/45 actions; 3 failed (see above)"
+        );
+        assert_eq!(summary.exit_code(), 3);
+        let dry = Summary {
+            actions: 2,
+            failed: 0,
+            dry_run: true,
+        };
+        assert_eq!(dry.to_string(), "Applied 2/2 actions (dry run)");
+        assert_eq!(dry.exit_code(), 0);
+        let many = Summary {
+            actions: 300,
+            failed: 300,
+            dry_run: false,
+        };
+        assert_eq!(many.exit_code(), 253);
+    }
+
+    #[test]
+    fn error_exit_codes() {
+        let timeout = Error::from(WaitError::Timeout(WAIT_FOR_DEVICE_TIMEOUT));
+        assert_eq!(timeout.exit_code(), EXIT_NO_DEVICE);
+        let adb = Error::from(WaitError::Adb("Cannot run ADB".to_string()));
+        assert_eq!(adb.exit_code(), EXIT_ERROR);
+        assert_eq!(adb.to_string(), "Cannot run ADB");
+    }
+
+    #[test]
+    fn help_flag() {
+        let help = |s: &str| help_requested(s.split_whitespace().map(String::from));
+        assert!(help("--help"));
+        assert!(help("--apply sel.txt -h"));
+        assert!(!help("--apply sel.txt"));
+    }
+}
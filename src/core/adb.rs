@@ -39,6 +39,10 @@
 //! [see this](https://android.googlesource.com/platform/packages/modules/adb/+/refs/heads/master/docs/)
 
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
 
 #[cfg(target_os = "windows")]
 use std::os::windows::process::CommandExt;
@@ -52,6 +56,18 @@ pub fn to_trimmed_utf8(v: Vec<u8>) -> String {
         .to_string()
 }
 
+/// Whether `out` of [`ACommand::version`] comes from the real Platform Tools,
+/// not from a stub printing its own usage or an ad (like the Windows Store one).
+///
+/// Only the first line is checked, older versions print a different rest.
+#[must_use]
+pub fn is_genuine_version(out: &str) -> bool {
+    out.lines()
+        .next()
+        .and_then(|ln| ln.strip_prefix("Android Debug Bridge version "))
+        .is_some_and(|v| is_version_triple(v.trim()))
+}
+
 #[must_use]
 fn is_version_triple(s: &str) -> bool {
     let mut components = s.split('.');
@@ -69,6 +85,47 @@ fn is_version_triple(s: &str) -> bool {
     true
 }
 
+/// An ADB server other than the default one, on `localhost:5037`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AdbServer {
+    /// On `localhost`, e.g. in a container. Passed as `ANDROID_ADB_SERVER_PORT`.
+    Port(u16),
+    /// On another host. Passed as `ADB_SERVER_SOCKET=tcp:<host>:<port>`.
+    Remote(String, u16),
+}
+
+impl AdbServer {
+    /// `port`, `host:port` or `tcp:host:port`
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let s = s.trim();
+        let port = |p: &str| match p.parse::<u16>() {
+            Ok(port) if port > 0 => Ok(port),
+            _ => Err(format!("`{p}` is not a port")),
+        };
+        match s.strip_prefix("tcp:").unwrap_or(s).rsplit_once(':') {
+            None => port(s).map(Self::Port),
+            Some((host, p)) if !host.is_empty() && !host.contains(char::is_whitespace) => {
+                Ok(Self::Remote(host.to_string(), port(p)?))
+            }
+            Some(_) => Err(format!("`{s}` is not a `host:port`")),
+        }
+    }
+}
+
+/// ADB server the commands talk to, `None` for what the environment says
+static SERVER: RwLock<Option<AdbServer>> = RwLock::new(None);
+
+/// Make every next [`ACommand`] use `server`.
+///
+/// Without one, `adb` follows the environment UAD-ng was started with,
+/// such as `ADB_SERVER_SOCKET`, or uses the default server.
+pub fn set_server(server: Option<AdbServer>) {
+    match SERVER.write() {
+        Ok(mut s) => *s = server,
+        Err(e) => error!("Couldn't set the ADB server: {e}"),
+    }
+}
+
 /// Builder object for an Android Debug Bridge CLI command,
 /// using the type-state and new-type patterns.
 ///
@@ -82,7 +139,19 @@ impl ACommand {
     /// `adb` command builder
     #[must_use]
     pub fn new() -> Self {
-        Self(std::process::Command::new("adb"))
+        let mut cmd = std::process::Command::new("adb");
+        match SERVER.read().ok().and_then(|s| s.clone()) {
+            None => (),
+            Some(AdbServer::Port(port)) => {
+                // it would take precedence over the port
+                cmd.env_remove("ADB_SERVER_SOCKET")
+                    .env("ANDROID_ADB_SERVER_PORT", port.to_string());
+            }
+            Some(AdbServer::Remote(host, port)) => {
+                cmd.env("ADB_SERVER_SOCKET", format!("tcp:{host}:{port}"));
+            }
+        }
+        Self(cmd)
     }
 
     /// `shell` sub-command builder.
@@ -106,27 +175,11 @@ impl ACommand {
     /// Status can be (but not limited to):
     /// - "unauthorized"
     /// - "device"
-    pub fn devices(mut self) -> Result<Vec<(String, String)>, String> {
-        self.0.arg("devices");
-        Ok(self
-            .run()?
-            .lines()
-            .skip(1) // header
-            .map(|dev_stat| {
-                let tab_idx = dev_stat
-                    // OS-specific?
-                    .find('\t')
-                    // True on Linux,
-                    // no matter if ADB is piped or connected to terminal
-                    .expect("There must be 1 tab after serial");
-                (
-                    // serial
-                    dev_stat[..tab_idx].to_string(),
-                    // status
-                    dev_stat[(tab_idx + 1)..].to_string(),
-                )
-            })
-            .collect())
+    ///
+    /// With the transport of each device (`devices -l`).
+    pub fn devices_long(mut self) -> Result<Vec<DeviceEntry>, String> {
+        self.0.args(["devices", "-l"]);
+        Ok(parse_devices_long(&self.run()?))
     }
 
     /// `version` sub-command
@@ -148,39 +201,50 @@ impl ACommand {
     /// Installed as <ANDROID_SDK_HOME>/platform-tools/adb[.exe]
     /// Running on <OS/kernel version> (<CPU arch>)
     /// ```
-    #[expect(clippy::panic_in_result_fn, reason = "Assertions are fine")]
+    ///
+    /// See [`is_genuine_version`] to tell if it's the real one.
     pub fn version(mut self) -> Result<String, String> {
         self.0.arg("version");
-        let out = self.run()?;
+        self.run()
+    }
 
-        #[cfg(debug_assertions)]
-        {
-            const ADBV: &str = "Android Debug Bridge version ";
-            const V: &str = "Version ";
-
-            let mut lns = out.lines();
-
-            assert!(
-                lns.next()
-                    .is_some_and(|ln| ln.starts_with(ADBV) && is_version_triple(&ln[ADBV.len()..]))
-            );
-            assert!(lns.next().is_some_and(|ln| ln.starts_with(V)
-                && is_version_triple(&ln[V.len()..ln.find('-').unwrap_or(ln.len())])));
-            // missing test for valid path
-            assert!(lns.next().is_some_and(|ln| ln.starts_with("Installed as ")
-                && (ln.ends_with("adb") || ln.ends_with("adb.exe"))));
-            // missing test for x86/ARM (both 64b)
-            assert!(lns.next().is_some_and(|ln| ln.starts_with("Running on ")));
-            if lns.next().is_some() {
-                unreachable!("Expected < 5 lines")
-            }
+    /// `install -r` sub-command, for an APK on the host.
+    ///
+    /// If `device_serial` is empty, it lets ADB choose the default device.
+    pub fn install<S: AsRef<str>>(
+        mut self,
+        device_serial: S,
+        apk: &Path,
+        user_id: Option<u16>,
+    ) -> Result<AdbOutcome, String> {
+        let serial = device_serial.as_ref();
+        if !serial.is_empty() {
+            self.0.args(["-s", serial]);
         }
-
-        Ok(out)
+        self.0.args(["install", "-r"]);
+        if let Some(u) = user_id {
+            self.0.args(["--user", &u.to_string()]);
+        }
+        // `run` logs the args as UTF-8
+        self.0.arg(&*apk.to_string_lossy());
+        self.run_outcome()
     }
 
-    /// General executor
-    fn run(self) -> Result<String, String> {
+    /// `wait-for-device` sub-command, killed after `timeout`.
+    ///
+    /// If `device_serial` is empty, it waits for any device.
+    pub fn wait_for_device<S: AsRef<str>>(
+        mut self,
+        device_serial: S,
+        timeout: Duration,
+    ) -> Result<(), WaitError> {
+        const POLL: Duration = Duration::from_millis(100);
+
+        let serial = device_serial.as_ref();
+        if !serial.is_empty() {
+            self.0.args(["-s", serial]);
+        }
+        self.0.arg("wait-for-device");
         let mut cmd = self.0;
         #[cfg(target_os = "windows")]
         let cmd = cmd.creation_flags(0x0800_0000); // do not open a cmd window
@@ -192,23 +256,198 @@ impl ACommand {
                 .collect::<Vec<_>>()
                 .join(" ")
         );
+        let mut child = cmd
+            .stdout(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .spawn()
+            .map_err(|e| {
+                error!("ADB: {e}");
+                WaitError::Adb("Cannot run ADB, likely not found".to_string())
+            })?;
+        let start = Instant::now();
+        loop {
+            match child.try_wait() {
+                Ok(Some(status)) if status.success() => return Ok(()),
+                Ok(Some(status)) => {
+                    return Err(WaitError::Adb(format!("adb exited with {status}")));
+                }
+                Ok(None) if start.elapsed() >= timeout => {
+                    // best-effort: the process may have just exited
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(WaitError::Timeout(timeout));
+                }
+                Ok(None) => std::thread::sleep(POLL),
+                Err(e) => return Err(WaitError::Adb(e.to_string())),
+            }
+        }
+    }
+
+    /// General executor
+    fn run(self) -> Result<String, String> {
+        self.run_outcome().and_then(AdbOutcome::into_result)
+    }
+
+    /// Like [`Self::run`], but keeps everything ADB reported.
+    ///
+    /// `Err` if ADB couldn't run at all.
+    fn run_outcome(self) -> Result<AdbOutcome, String> {
+        let mut cmd = self.0;
+        #[cfg(target_os = "windows")]
+        let cmd = cmd.creation_flags(0x0800_0000); // do not open a cmd window
+
+        let command = std::iter::once("adb")
+            .chain(
+                cmd.get_args()
+                    .map(|s| s.to_str().unwrap_or_else(|| unreachable!())),
+            )
+            .collect::<Vec<_>>()
+            .join(" ");
+        info!("Ran command: {command}");
         match cmd.output() {
             Err(e) => {
                 error!("ADB: {e}");
                 Err("Cannot run ADB, likely not found".to_string())
             }
-            Ok(o) => {
-                let stdout = to_trimmed_utf8(o.stdout);
-                if o.status.success() {
-                    Ok(stdout)
-                } else {
-                    let stderr = to_trimmed_utf8(o.stderr);
-                    // ADB does really weird things:
-                    // Some errors are not redirected to `stderr`
-                    let err = if stdout.is_empty() { stderr } else { stdout };
-                    Err(err)
-                }
-            }
+            Ok(o) => Ok(AdbOutcome {
+                command,
+                stdout: to_trimmed_utf8(o.stdout),
+                stderr: to_trimmed_utf8(o.stderr),
+                exit_code: o.status.code(),
+            }),
+        }
+    }
+}
+
+/// Everything an `adb` invocation reported, whether it succeeded or not
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AdbOutcome {
+    /// The full `adb` invocation, so it can be re-run manually
+    pub command: String,
+    pub stdout: String,
+    pub stderr: String,
+    /// `None` if ADB was killed by a signal
+    pub exit_code: Option<i32>,
+}
+
+impl AdbOutcome {
+    #[must_use]
+    pub const fn success(&self) -> bool {
+        matches!(self.exit_code, Some(0))
+    }
+
+    /// `stdout` on success, otherwise the error message
+    pub fn into_result(self) -> Result<String, String> {
+        if self.success() {
+            Ok(self.stdout)
+        } else if self.stdout.is_empty() {
+            Err(self.stderr)
+        } else {
+            // ADB does really weird things:
+            // Some errors are not redirected to `stderr`
+            Err(self.stdout)
+        }
+    }
+}
+
+/// How a device is connected to ADB
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Transport {
+    Usb,
+    /// Wireless debugging, or `adb connect`
+    Tcp,
+    Emulator,
+    #[default]
+    Unknown,
+}
+
+impl Transport {
+    /// `usb` is whether `devices -l` listed a `usb:` field
+    pub fn guess(serial: &str, usb: bool) -> Self {
+        if usb {
+            Self::Usb
+        } else if serial.starts_with("emulator-") {
+            Self::Emulator
+        } else if serial.contains("._adb-tls-connect._tcp")
+            || serial
+                .rsplit_once(':')
+                .is_some_and(|(_, port)| port.parse::<u16>().is_ok())
+        {
+            Self::Tcp
+        } else {
+            Self::Unknown
+        }
+    }
+}
+
+impl std::fmt::Display for Transport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Usb => "USB",
+            Self::Tcp => "Wi-Fi",
+            Self::Emulator => "Emulator",
+            Self::Unknown => "?",
+        })
+    }
+}
+
+/// A line of `adb devices -l`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceEntry {
+    pub serial: String,
+    /// See [`ACommand::devices_long`]
+    pub status: String,
+    pub transport: Transport,
+    /// Changes on every reconnection, unlike the serial
+    pub transport_id: Option<u32>,
+}
+
+/// Parses the output of `adb devices -l`:
+/// ```txt
+/// List of devices attached
+/// 0123456789ABCDEF       device usb:1-1 product:x model:Pixel_7 device:panther transport_id:3
+/// 192.168.1.2:5555       device product:x model:Pixel_7 device:panther transport_id:4
+/// ```
+/// The status may contain spaces (`no permissions (...)`), so it ends at the first known field.
+pub fn parse_devices_long(output: &str) -> Vec<DeviceEntry> {
+    const FIELDS: [&str; 5] = ["usb:", "product:", "model:", "device:", "transport_id:"];
+    output
+        .lines()
+        .skip(1) // header
+        .filter_map(|line| {
+            let (serial, rest) = line.trim().split_once(char::is_whitespace)?;
+            let rest = rest.trim_start();
+            let fields_start = rest
+                .match_indices(' ')
+                .map(|(i, _)| i + 1)
+                .find(|&i| FIELDS.iter().any(|f| rest[i..].starts_with(f)))
+                .unwrap_or(rest.len());
+            let (status, fields) = rest.split_at(fields_start);
+            let field = |name: &str| fields.split_whitespace().find_map(|f| f.strip_prefix(name));
+            Some(DeviceEntry {
+                serial: serial.to_string(),
+                status: status.trim().to_string(),
+                transport: Transport::guess(serial, field("usb:").is_some()),
+                transport_id: field("transport_id:").and_then(|id| id.parse().ok()),
+            })
+        })
+        .collect()
+}
+
+/// Failure of [`ACommand::wait_for_device`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WaitError {
+    /// No device showed up in time
+    Timeout(Duration),
+    /// ADB couldn't run, or failed
+    Adb(String),
+}
+
+impl std::fmt::Display for WaitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Timeout(t) => write!(f, "No device after waiting {}s", t.as_secs()),
+            Self::Adb(e) => write!(f, "{e}"),
         }
     }
 }
@@ -237,11 +476,69 @@ impl ShellCommand {
         self.0.0.args(["getprop", key]);
         self.0.run()
     }
+    /// An action (such as `pm disable-user --user 0 <pkg>`) built by [`crate::core::sync`].
+    ///
+    /// This is the only escape hatch of these APIs:
+    /// actions are joined into scripts (see [`crate::core::sync::batch_script`]),
+    /// which can't be modeled as sub-commands.
+    pub fn raw(mut self, action: &str) -> Result<AdbOutcome, String> {
+        // this works because `sh` splits spaces
+        self.0.0.arg(action);
+        self.0.run_outcome()
+    }
     /// Reboots device
     pub fn reboot(mut self) -> Result<String, String> {
         self.0.0.arg("reboot");
         self.0.run()
     }
+    /// `df /data`, parsed by [`crate::core::sync::parse_df`]
+    pub fn df_data(mut self) -> Result<String, String> {
+        self.0.0.args(["df", "/data"]);
+        self.0.run()
+    }
+    /// `dumpsys diskstats`, parsed by [`parse_diskstats_app_sizes`].
+    ///
+    /// Android 8.0+ only, older versions don't print per-app sizes
+    /// (the map is empty).
+    pub fn diskstats_app_sizes(mut self) -> Result<HashMap<String, u64>, String> {
+        self.0.0.args(["dumpsys", "diskstats"]);
+        self.0.run().map(|out| parse_diskstats_app_sizes(&out))
+    }
+}
+
+/// Sum of code, data, and cache sizes (bytes) by package name,
+/// from the output of `dumpsys diskstats`:
+/// ```txt
+/// Package Names: ["com.example.a","com.example.b"]
+/// App Sizes: [1000,2000]
+/// App Data Sizes: [10,20]
+/// Cache Sizes: [1,2]
+/// ```
+#[must_use]
+pub fn parse_diskstats_app_sizes(out: &str) -> HashMap<String, u64> {
+    let field = |key: &str| {
+        out.lines()
+            .find_map(|ln| ln.strip_prefix(key))
+            .map(|v| {
+                v.trim()
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default()
+    };
+    let names = field("Package Names:");
+    let mut sizes: HashMap<String, u64> = HashMap::new();
+    for key in ["App Sizes:", "App Data Sizes:", "Cache Sizes:"] {
+        for (name, size) in names.iter().zip(field(key)) {
+            *sizes.entry(name.trim_matches('"').to_string()).or_default() +=
+                size.parse::<u64>().unwrap_or_default();
+        }
+    }
+    sizes
 }
 
 #[must_use]
@@ -386,32 +683,46 @@ impl PmCommand {
                 // the format seems to be stable across Android versions:
                 // "\tUserInfo{<id>:<name>:<flags>}[ running]"
 
-                let mut comps = ln.split(':');
-
-                let id = comps
-                    .next()
-                    .expect("There must be at least 1 ':'-separated component")
-                    .parse()
-                    .expect("string assumed to be UID numeral");
-                //let name = comps
-                //    .next()
-                //    .expect("There must be at least 2 ':'-separated components. 2nd is user-name");
-                //let flags = u32::from_str_radix(
-                //    comps.next().expect(
-                //        "There must be at least 3 ':'-separated components. 3rd is user bit-flags",
-                //    ),
-                //    16,
-                //)
-                //.expect("string assumed to be hexadecimal bit-flags");
+                let (id, rest) = ln.split_once(':').unwrap_or((ln, ""));
+                let id = id.parse().expect("string assumed to be UID numeral");
+                // names can contain ':', but flags can't
+                let (name, flags) = rest.rsplit_once(':').unwrap_or((rest, ""));
                 UserInfo {
                     id,
-                    //name: name.into(),
-                    //flags,
+                    name: name.into(),
+                    flags: u32::from_str_radix(flags, 16).unwrap_or_default(),
                     running: run,
                 }
             })
             .collect())
     }
+
+    /// `create-user` sub-command. Prints the ID of the new user.
+    ///
+    /// Only letters, digits, spaces, `_` and `-` are allowed in `name`.
+    pub fn create_user(mut self, name: &str) -> Result<String, String> {
+        let name = quoted_user_name(name)?;
+        self.0.0.0.args(["create-user", &name]);
+        self.0.0.run()
+    }
+
+    /// `remove-user` sub-command, which deletes all the data of the user!
+    pub fn remove_user(mut self, user_id: u16) -> Result<String, String> {
+        self.0.0.0.args(["remove-user", &user_id.to_string()]);
+        self.0.0.run()
+    }
+}
+
+/// `name` as a single word for the device shell,
+/// which gets the args of `adb shell` joined into a command line
+fn quoted_user_name(name: &str) -> Result<String, String> {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || matches!(c, ' ' | '_' | '-');
+    if name.trim().is_empty() || !name.chars().all(allowed) {
+        return Err(format!(
+            "Invalid user name {name:?}: only letters, digits, spaces, `_` and `-` are allowed"
+        ));
+    }
+    Ok(format!("'{name}'"))
 }
 
 /// Mirror of AOSP `UserInfo` Java Class,
@@ -419,15 +730,45 @@ impl PmCommand {
 #[derive(Debug, Clone)]
 pub struct UserInfo {
     id: u16,
-    //name: Box<str>,
-    //flags: u32,
+    name: Box<str>,
+    flags: u32,
     running: bool,
 }
 impl UserInfo {
+    /// `UserInfo.FLAG_PRIMARY`
+    pub const FLAG_PRIMARY: u32 = 0x1;
+    /// `UserInfo.FLAG_GUEST`
+    pub const FLAG_GUEST: u32 = 0x4;
+    /// `UserInfo.FLAG_RESTRICTED`
+    pub const FLAG_RESTRICTED: u32 = 0x8;
+    /// `UserInfo.FLAG_MANAGED_PROFILE`, a work profile
+    pub const FLAG_MANAGED_PROFILE: u32 = 0x20;
+    /// `UserInfo.FLAG_PROFILE`, any profile (work, clone, private space, ...)
+    pub const FLAG_PROFILE: u32 = 0x1000;
+
+    #[must_use]
+    pub fn new(id: u16, name: &str, flags: u32, running: bool) -> Self {
+        Self {
+            id,
+            name: name.into(),
+            flags,
+            running,
+        }
+    }
     #[must_use]
     pub const fn get_id(&self) -> u16 {
         self.id
     }
+    /// Display name, possibly empty
+    #[must_use]
+    pub fn get_name(&self) -> &str {
+        &self.name
+    }
+    /// `UserInfo.FLAG_*` bits
+    #[must_use]
+    pub const fn get_flags(&self) -> u32 {
+        self.flags
+    }
     /// Check if the user was logged-in
     /// at the time `pm list users` was invoked
     #[must_use]
@@ -440,6 +781,104 @@ impl UserInfo {
 mod tests {
     use super::*;
 
+    #[test]
+    fn genuine_version() {
+        assert!(is_genuine_version(
+            "Android Debug Bridge version 1.0.41\nVersion 34.0.5-debian\nInstalled as /usr/lib/android-sdk/platform-tools/adb\nRunning on Linux 6.12.12-amd64 (x86_64)\n"
+        ));
+        assert!(is_genuine_version(
+            "Android Debug Bridge version 1.0.32\nRevision 09a0d98bebce-android\n"
+        ));
+        assert!(!is_genuine_version(""));
+        assert!(!is_genuine_version(
+            "Get ADB from the Microsoft Store!\nAndroid Debug Bridge version 1.0.41\n"
+        ));
+        assert!(!is_genuine_version("Android Debug Bridge version latest\n"));
+    }
+
+    #[test]
+    fn adb_servers() {
+        assert_eq!(AdbServer::parse("5038"), Ok(AdbServer::Port(5038)));
+        let remote = Ok(AdbServer::Remote("192.168.1.2".to_string(), 5037));
+        assert_eq!(AdbServer::parse(" 192.168.1.2:5037"), remote);
+        assert_eq!(AdbServer::parse("tcp:192.168.1.2:5037"), remote);
+        assert_eq!(
+            AdbServer::parse("[::1]:5037"),
+            Ok(AdbServer::Remote("[::1]".to_string(), 5037))
+        );
+        for server in [
+            "",
+            "0",
+            "host",
+            "host:",
+            ":5037",
+            "host:70000",
+            "my host:5037",
+        ] {
+            assert!(AdbServer::parse(server).is_err(), "{server:?}");
+        }
+    }
+
+    #[test]
+    fn user_names() {
+        assert_eq!(quoted_user_name("Work").as_deref(), Ok("'Work'"));
+        assert_eq!(
+            quoted_user_name("My kid_2-b").as_deref(),
+            Ok("'My kid_2-b'")
+        );
+        for name in [
+            "", " ", "a;reboot", "$(id)", "it's", "a\"b", "`id`", "a\nb", "é",
+        ] {
+            assert!(quoted_user_name(name).is_err(), "{name:?}");
+        }
+    }
+
+    #[test]
+    fn devices_long() {
+        let devices = parse_devices_long(
+            "List of devices attached\n\
+             0123456789ABCDEF       device usb:1-1 product:p model:Pixel_7 device:panther transport_id:3\n\
+             192.168.1.2:5555       device product:p model:Pixel_7 device:panther transport_id:4\n\
+             emulator-5554          unauthorized transport_id:5\n\
+             FEDCBA                 no permissions (missing udev rules?); see [http://d.android.com/r/tools/device.html] usb:1-2 transport_id:6\n\
+             \n",
+        );
+        let summary: Vec<_> = devices
+            .iter()
+            .map(|d| {
+                (
+                    d.serial.as_str(),
+                    d.status.as_str(),
+                    d.transport,
+                    d.transport_id,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("0123456789ABCDEF", "device", Transport::Usb, Some(3)),
+                ("192.168.1.2:5555", "device", Transport::Tcp, Some(4)),
+                (
+                    "emulator-5554",
+                    "unauthorized",
+                    Transport::Emulator,
+                    Some(5)
+                ),
+                (
+                    "FEDCBA",
+                    "no permissions (missing udev rules?); see [http://d.android.com/r/tools/device.html]",
+                    Transport::Usb,
+                    Some(6)
+                ),
+            ]
+        );
+        // plain `devices`
+        let plain = parse_devices_long("List of devices attached\nABC\tdevice\n");
+        assert_eq!(plain[0].status, "device");
+        assert_eq!(plain[0].transport, Transport::Unknown);
+    }
+
     #[test]
     fn invalid_pack_ids() {
         for p_id in [
@@ -458,6 +897,20 @@ mod tests {
         }
     }
 
+    #[test]
+    fn diskstats_app_sizes() {
+        let out = "Latency: 1ms [512B Data Write]\n\
+            Package Names: [\"com.example.a\",\"com.example.b\"]\n\
+            App Sizes: [1000,2000]\n\
+            App Data Sizes: [10,20]\n\
+            Cache Sizes: [1,2]\n";
+        let sizes = parse_diskstats_app_sizes(out);
+        assert_eq!(sizes.len(), 2);
+        assert_eq!(sizes.get("com.example.a"), Some(&1011));
+        assert_eq!(sizes.get("com.example.b"), Some(&2022));
+        assert!(parse_diskstats_app_sizes("Package Names: []").is_empty());
+    }
+
     #[test]
     fn valid_pack_ids() {
         for p_id in [
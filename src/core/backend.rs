@@ -0,0 +1,591 @@
+#![deny(clippy::unwrap_used)]
+
+//! Device access, abstracted behind [`AdbBackend`].
+//!
+//! The real implementation ([`AdbCli`]) is a thin layer over [`ACommand`].
+//! The fake one ([`FakeAdb`]) keeps scripted devices and package states in memory,
+//! so the GUI can be developed and tested without any hardware.
+//! It's selected with the `--mock` CLI flag or the `UADNG_MOCK` env var.
+
+use crate::core::adb::{
+    ACommand, AdbOutcome, DeviceEntry, PmListPacksFlag, Transport, UserInfo, WaitError,
+};
+use crate::core::sync::BATCH_MARKER;
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{LazyLock, Mutex},
+    time::Duration,
+};
+
+/// CLI flag that selects [`FakeAdb`]
+pub const MOCK_FLAG: &str = "--mock";
+/// Env var that selects [`FakeAdb`], when set to anything
+pub const MOCK_ENV: &str = "UADNG_MOCK";
+
+/// The subset of ADB that UAD-ng relies on.
+///
+/// `serial` can be empty, which lets the backend choose the default device.
+pub trait AdbBackend: Send + Sync {
+    /// See [`ACommand::version`]
+    fn version(&self) -> Result<String, String>;
+    /// See [`ACommand::devices_long`]
+    fn devices_long(&self) -> Result<Vec<DeviceEntry>, String>;
+    /// `(serial, status)` of [`Self::devices_long`]
+    fn devices(&self) -> Result<Vec<(String, String)>, String> {
+        Ok(self
+            .devices_long()?
+            .into_iter()
+            .map(|d| (d.serial, d.status))
+            .collect())
+    }
+    /// See [`crate::core::adb::ShellCommand::getprop`]
+    fn getprop(&self, serial: &str, key: &str) -> Result<String, String>;
+    /// See [`crate::core::adb::PmCommand::list_packages_sys`]
+    fn list_packages_sys(
+        &self,
+        serial: &str,
+        f: Option<PmListPacksFlag>,
+        user_id: Option<u16>,
+    ) -> Result<Vec<String>, String>;
+    /// See [`crate::core::adb::PmCommand::list_users`]
+    fn list_users(&self, serial: &str) -> Result<Box<[UserInfo]>, String>;
+    /// Runs an **arbitrary** `sh` command-line, such as `pm disable-user --user 0 <pkg>`.
+    ///
+    /// See [`crate::core::adb::ShellCommand::raw`]
+    fn shell_outcome(&self, serial: &str, action: &str) -> Result<AdbOutcome, String>;
+    /// Like [`Self::shell_outcome`], keeping either the output or the error
+    fn shell(&self, serial: &str, action: &str) -> Result<String, String> {
+        self.shell_outcome(serial, action)
+            .and_then(AdbOutcome::into_result)
+    }
+    /// See [`crate::core::adb::ShellCommand::reboot`]
+    fn reboot(&self, serial: &str) -> Result<String, String>;
+    /// See [`crate::core::adb::ShellCommand::diskstats_app_sizes`]
+    fn package_sizes(&self, serial: &str) -> Result<HashMap<String, u64>, String>;
+    /// See [`crate::core::adb::ShellCommand::df_data`]
+    fn df_data(&self, serial: &str) -> Result<String, String>;
+    /// See [`ACommand::install`]
+    fn install(&self, serial: &str, apk: &Path, user_id: Option<u16>)
+    -> Result<AdbOutcome, String>;
+    /// See [`ACommand::wait_for_device`]
+    fn wait_for_device(&self, serial: &str, timeout: Duration) -> Result<(), WaitError>;
+    /// See [`crate::core::adb::PmCommand::create_user`]
+    fn create_user(&self, serial: &str, name: &str) -> Result<String, String>;
+    /// See [`crate::core::adb::PmCommand::remove_user`]
+    fn remove_user(&self, serial: &str, user_id: u16) -> Result<String, String>;
+}
+
+/// Returns `true` if the fake backend was requested
+#[must_use]
+pub fn mock_requested() -> bool {
+    std::env::var_os(MOCK_ENV).is_some() || std::env::args().any(|arg| arg == MOCK_FLAG)
+}
+
+static BACKEND: LazyLock<Box<dyn AdbBackend>> = LazyLock::new(|| {
+    if mock_requested() {
+        warn!("Using the fake ADB backend, no real device will be touched");
+        Box::new(FakeAdb::default())
+    } else {
+        Box::new(AdbCli)
+    }
+});
+
+/// The backend selected at startup
+#[must_use]
+pub fn backend() -> &'static dyn AdbBackend {
+    BACKEND.as_ref()
+}
+
+/// Real devices, through the `adb` executable
+#[derive(Debug, Clone, Copy)]
+pub struct AdbCli;
+
+impl AdbBackend for AdbCli {
+    fn version(&self) -> Result<String, String> {
+        ACommand::new().version()
+    }
+
+    fn devices_long(&self) -> Result<Vec<DeviceEntry>, String> {
+        ACommand::new().devices_long()
+    }
+
+    fn getprop(&self, serial: &str, key: &str) -> Result<String, String> {
+        ACommand::new().shell(serial).getprop(key)
+    }
+
+    fn list_packages_sys(
+        &self,
+        serial: &str,
+        f: Option<PmListPacksFlag>,
+        user_id: Option<u16>,
+    ) -> Result<Vec<String>, String> {
+        ACommand::new()
+            .shell(serial)
+            .pm()
+            .list_packages_sys(f, user_id)
+    }
+
+    fn list_users(&self, serial: &str) -> Result<Box<[UserInfo]>, String> {
+        ACommand::new().shell(serial).pm().list_users()
+    }
+
+    fn shell_outcome(&self, serial: &str, action: &str) -> Result<AdbOutcome, String> {
+        ACommand::new().shell(serial).raw(action)
+    }
+
+    fn reboot(&self, serial: &str) -> Result<String, String> {
+        ACommand::new().shell(serial).reboot()
+    }
+
+    fn package_sizes(&self, serial: &str) -> Result<HashMap<String, u64>, String> {
+        ACommand::new().shell(serial).diskstats_app_sizes()
+    }
+
+    fn df_data(&self, serial: &str) -> Result<String, String> {
+        ACommand::new().shell(serial).df_data()
+    }
+
+    fn install(
+        &self,
+        serial: &str,
+        apk: &Path,
+        user_id: Option<u16>,
+    ) -> Result<AdbOutcome, String> {
+        ACommand::new().install(serial, apk, user_id)
+    }
+
+    fn wait_for_device(&self, serial: &str, timeout: Duration) -> Result<(), WaitError> {
+        ACommand::new().wait_for_device(serial, timeout)
+    }
+
+    fn create_user(&self, serial: &str, name: &str) -> Result<String, String> {
+        ACommand::new().shell(serial).pm().create_user(name)
+    }
+
+    fn remove_user(&self, serial: &str, user_id: u16) -> Result<String, String> {
+        ACommand::new().shell(serial).pm().remove_user(user_id)
+    }
+}
+
+/// State of a package in [`FakeAdb`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FakeState {
+    Enabled,
+    Disabled,
+    Uninstalled,
+}
+
+/// A scripted device
+#[derive(Debug)]
+struct FakeDevice {
+    serial: String,
+    props: HashMap<&'static str, &'static str>,
+    /// `(user ID, protected)`
+    users: Vec<(u16, bool)>,
+    /// `(user ID, package) -> state`
+    packages: HashMap<(u16, String), FakeState>,
+}
+
+/// In-memory devices, with scripted packages.
+///
+/// Shell actions mutate package states, so the GUI behaves like with a real device.
+#[derive(Debug)]
+pub struct FakeAdb {
+    devices: Mutex<Vec<FakeDevice>>,
+}
+
+impl Default for FakeAdb {
+    fn default() -> Self {
+        const PACKAGES: [(&str, FakeState); 8] = [
+            ("com.android.chrome", FakeState::Enabled),
+            ("com.android.systemui", FakeState::Enabled),
+            ("com.android.vending", FakeState::Enabled),
+            ("com.facebook.appmanager", FakeState::Disabled),
+            ("com.facebook.services", FakeState::Enabled),
+            ("com.google.android.youtube", FakeState::Enabled),
+            ("com.google.android.apps.maps", FakeState::Uninstalled),
+            ("com.example.unlisted", FakeState::Enabled),
+        ];
+        let users = vec![(0, false), (10, false), (150, true)];
+        let packages = users
+            .iter()
+            .filter(|(_, protected)| !protected)
+            .flat_map(|&(id, _)| {
+                PACKAGES
+                    .iter()
+                    .map(move |&(name, state)| ((id, name.to_string()), state))
+            })
+            .collect();
+        Self {
+            devices: Mutex::new(vec![FakeDevice {
+                serial: "mock-0001".to_string(),
+                props: HashMap::from([
+                    ("ro.product.brand", "UAD-ng"),
+                    ("ro.product.model", "Mock Device"),
+                    ("ro.build.version.sdk", "34"),
+                    ("ro.serialno", "mock-0001"),
+                ]),
+                users,
+                packages,
+            }]),
+        }
+    }
+}
+
+impl FakeAdb {
+    /// Runs `f` on the device matching `serial`, or the first one if `serial` is empty
+    fn with_device<T>(
+        &self,
+        serial: &str,
+        f: impl FnOnce(&mut FakeDevice) -> Result<T, String>,
+    ) -> Result<T, String> {
+        let mut devices = self.devices.lock().expect("fake ADB lock poisoned");
+        devices
+            .iter_mut()
+            .find(|d| serial.is_empty() || d.serial == serial)
+            .map_or_else(|| Err("adb: no devices/emulators found".to_string()), f)
+    }
+}
+
+/// Fake commands fail with the exit code `1`, as most real ones
+fn fake_outcome(command: String, result: Result<String, String>) -> AdbOutcome {
+    match result {
+        Ok(stdout) => AdbOutcome {
+            command,
+            stdout,
+            stderr: String::new(),
+            exit_code: Some(0),
+        },
+        Err(stderr) => AdbOutcome {
+            command,
+            stdout: String::new(),
+            stderr,
+            exit_code: Some(1),
+        },
+    }
+}
+
+impl AdbBackend for FakeAdb {
+    fn version(&self) -> Result<String, String> {
+        Ok("Android Debug Bridge version 1.0.41\nVersion 35.0.2-fake\n".to_string())
+    }
+
+    fn devices_long(&self) -> Result<Vec<DeviceEntry>, String> {
+        let devices = self.devices.lock().expect("fake ADB lock poisoned");
+        Ok(devices
+            .iter()
+            .zip(1..)
+            .map(|(d, transport_id)| DeviceEntry {
+                serial: d.serial.clone(),
+                status: "device".to_string(),
+                transport: Transport::Usb,
+                transport_id: Some(transport_id),
+            })
+            .collect())
+    }
+
+    fn getprop(&self, serial: &str, key: &str) -> Result<String, String> {
+        self.with_device(serial, |d| {
+            Ok(d.props.get(key).copied().unwrap_or_default().to_string())
+        })
+    }
+
+    fn list_packages_sys(
+        &self,
+        serial: &str,
+        f: Option<PmListPacksFlag>,
+        user_id: Option<u16>,
+    ) -> Result<Vec<String>, String> {
+        let user = user_id.unwrap_or_default();
+        self.with_device(serial, |d| {
+            if d.users.iter().any(|&(id, protected)| id == user && protected) {
+                return Err(format!(
+                    "java.lang.SecurityException: Shell does not have permission to access user {user}"
+                ));
+            }
+            Ok(d.packages
+                .iter()
+                .filter(|((u, _), _)| *u == user)
+                .filter(|(_, state)| match f {
+                    Some(PmListPacksFlag::IncludeUninstalled) => true,
+                    Some(PmListPacksFlag::OnlyEnabled) => **state == FakeState::Enabled,
+                    Some(PmListPacksFlag::OnlyDisabled) => **state == FakeState::Disabled,
+                    None => **state != FakeState::Uninstalled,
+                })
+                .map(|((_, name), _)| name.clone())
+                .collect())
+        })
+    }
+
+    fn list_users(&self, serial: &str) -> Result<Box<[UserInfo]>, String> {
+        self.with_device(serial, |d| {
+            Ok(d.users
+                .iter()
+                .map(|&(id, _)| match id {
+                    0 => UserInfo::new(id, "Owner", 0xc13, true),
+                    10 => UserInfo::new(id, "Work profile", 0x1030, false),
+                    150 => UserInfo::new(id, "Secure Folder", 0x1030, false),
+                    _ => UserInfo::new(id, "Secondary", 0x410, false),
+                })
+                .collect())
+        })
+    }
+
+    fn shell_outcome(&self, serial: &str, action: &str) -> Result<AdbOutcome, String> {
+        Ok(fake_outcome(
+            format!("adb -s {serial} shell {action}"),
+            self.shell(serial, action),
+        ))
+    }
+
+    fn shell(&self, serial: &str, action: &str) -> Result<String, String> {
+        let batch_end = format!(" 2>&1; echo {BATCH_MARKER} $?");
+        if action.contains(&batch_end) {
+            // see `batch_script`
+            return Ok(action
+                .split(&batch_end)
+                .map(|a| a.trim_start_matches("; "))
+                .filter(|a| !a.is_empty())
+                .map(|a| match self.shell(serial, a) {
+                    Ok(out) => format!("{out}\n{BATCH_MARKER} 0"),
+                    Err(err) => format!("{err}\n{BATCH_MARKER} 1"),
+                })
+                .collect::<Vec<_>>()
+                .join("\n"));
+        }
+        let words: Vec<&str> = action.split_whitespace().collect();
+        let Some((&package, cmd)) = words.split_last() else {
+            return Err("sh: empty command".to_string());
+        };
+        let user = words
+            .iter()
+            .position(|&w| w == "--user")
+            .and_then(|i| words.get(i + 1))
+            .map_or(Ok(0), |id| id.parse::<u16>())
+            .map_err(|e| e.to_string())?;
+        let cmd: Vec<&str> = cmd
+            .iter()
+            .take_while(|&&w| w != "--user")
+            .copied()
+            .collect();
+        let new_state = match cmd.as_slice() {
+            ["pm", "enable" | "unhide" | "unblock"] | ["cmd", "package", "install-existing"] => {
+                Some(FakeState::Enabled)
+            }
+            ["pm", "disable-user"] => Some(FakeState::Disabled),
+            ["pm", "uninstall" | "hide" | "block"] | ["pm", "uninstall", "-k"] => {
+                Some(FakeState::Uninstalled)
+            }
+            ["pm", "clear"] | ["am", "force-stop"] => None,
+            _ => return Err(format!("/system/bin/sh: {action}: not found")),
+        };
+        self.with_device(serial, |d| {
+            let Some(state) = d.packages.get_mut(&(user, package.to_string())) else {
+                return Err(format!("Failure [not installed for {user}]"));
+            };
+            if let Some(s) = new_state {
+                *state = s;
+            }
+            Ok("Success".to_string())
+        })
+    }
+
+    fn reboot(&self, serial: &str) -> Result<String, String> {
+        self.with_device(serial, |_| Ok(String::new()))
+    }
+
+    fn package_sizes(&self, serial: &str) -> Result<HashMap<String, u64>, String> {
+        self.with_device(serial, |d| {
+            // Arbitrary, but stable, sizes
+            Ok(d.packages
+                .keys()
+                .filter(|(user, _)| *user == 0)
+                .map(|(_, name)| (name.clone(), name.len() as u64 * 3_000_000))
+                .collect())
+        })
+    }
+
+    fn df_data(&self, serial: &str) -> Result<String, String> {
+        const TOTAL_KB: u64 = 64 * 1024 * 1024;
+        let sizes = self.package_sizes(serial)?;
+        self.with_device(serial, |d| {
+            // uninstalling frees space, like on a real device
+            let freed: u64 = d
+                .packages
+                .iter()
+                .filter(|&(&(user, _), &state)| user == 0 && state == FakeState::Uninstalled)
+                .filter_map(|((_, name), _)| sizes.get(name))
+                .sum();
+            let available = TOTAL_KB / 4 + freed / 1024;
+            Ok(format!(
+                "Filesystem     1K-blocks     Used Available Use% Mounted on\n\
+                /dev/block/dm-5 {TOTAL_KB} {} {available} 75% /data",
+                TOTAL_KB - available
+            ))
+        })
+    }
+
+    /// The package name is the file stem, e.g. `com.example.apk`
+    fn install(
+        &self,
+        serial: &str,
+        apk: &Path,
+        user_id: Option<u16>,
+    ) -> Result<AdbOutcome, String> {
+        let command = format!("adb -s {serial} install -r {}", apk.display());
+        let installed = apk
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("adb: failed to stat {}", apk.display()))
+            .and_then(|name| {
+                self.with_device(serial, |d| {
+                    d.packages
+                        .insert((user_id.unwrap_or_default(), name), FakeState::Enabled);
+                    Ok("Performing Streamed Install\nSuccess".to_string())
+                })
+            });
+        Ok(fake_outcome(command, installed))
+    }
+
+    /// Fake devices are always up, so an unknown one never shows up
+    fn wait_for_device(&self, serial: &str, timeout: Duration) -> Result<(), WaitError> {
+        self.with_device(serial, |_| Ok(()))
+            .map_err(|_| WaitError::Timeout(timeout))
+    }
+
+    /// New users start with the packages of user 0, all enabled
+    fn create_user(&self, serial: &str, name: &str) -> Result<String, String> {
+        self.with_device(serial, |d| {
+            let id = d.users.iter().map(|&(id, _)| id).max().unwrap_or_default() + 1;
+            d.users.push((id, false));
+            let packages: Vec<String> = d
+                .packages
+                .keys()
+                .filter(|(user, _)| *user == 0)
+                .map(|(_, package)| package.clone())
+                .collect();
+            for p in packages {
+                d.packages.insert((id, p), FakeState::Enabled);
+            }
+            Ok(format!("Success: created user id {id} ({name})"))
+        })
+    }
+
+    fn remove_user(&self, serial: &str, user_id: u16) -> Result<String, String> {
+        self.with_device(serial, |d| {
+            if user_id == 0 || !d.users.iter().any(|&(id, _)| id == user_id) {
+                return Ok(format!("Error: couldn't remove user id {user_id}"));
+            }
+            d.users.retain(|&(id, _)| id != user_id);
+            d.packages.retain(|&(user, _), _| user != user_id);
+            Ok("Success: removed user".to_string())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::sync::{batch_script, parse_batch_output};
+
+    #[test]
+    fn fake_shell_changes_state() {
+        let adb = FakeAdb::default();
+        let enabled =
+            |user| adb.list_packages_sys("", Some(PmListPacksFlag::OnlyEnabled), Some(user));
+        assert!(
+            enabled(10)
+                .expect("user 10 is not protected")
+                .contains(&"com.android.chrome".to_string())
+        );
+
+        adb.shell("mock-0001", "pm disable-user --user 10 com.android.chrome")
+            .expect("package exists");
+        assert!(
+            !enabled(10)
+                .expect("user 10 is not protected")
+                .contains(&"com.android.chrome".to_string())
+        );
+        // other users are untouched
+        assert!(
+            enabled(0)
+                .expect("user 0 is not protected")
+                .contains(&"com.android.chrome".to_string())
+        );
+    }
+
+    #[test]
+    fn fake_shell_batch() {
+        let adb = FakeAdb::default();
+        let script = batch_script(&[
+            "pm disable-user --user 0 com.android.chrome".to_string(),
+            "pm uninstall --user 0 com.example.missing".to_string(),
+        ]);
+        let out = adb.shell("mock-0001", &script).expect("device exists");
+        let results = parse_batch_output(&out, 2);
+        assert_eq!(results[0], Ok((0, "Success".to_string())));
+        assert!(matches!(&results[1], Ok((1, failure)) if failure.starts_with("Failure")));
+    }
+
+    #[test]
+    fn fake_shell_keep_data_round_trip() {
+        use crate::core::sync::{CorePackage, Phone, User, apply_pkg_state_commands};
+        use crate::core::uad_lists::PackageState;
+
+        let adb = FakeAdb::default();
+        let phone = Phone {
+            android_sdk: 34,
+            ..Phone::default()
+        };
+        let user = User {
+            id: 10,
+            ..User::default()
+        };
+        let mut package = CorePackage {
+            name: "com.android.chrome".to_string(),
+            state: PackageState::Enabled,
+        };
+        let enabled = || {
+            adb.list_packages_sys("", Some(PmListPacksFlag::OnlyEnabled), Some(10))
+                .expect("user 10 is not protected")
+                .contains(&package.name)
+        };
+
+        let uninstall = apply_pkg_state_commands(
+            &package,
+            PackageState::Uninstalled,
+            Some(user.clone()),
+            &phone,
+            true,
+        );
+        assert_eq!(uninstall, ["pm uninstall -k --user 10 com.android.chrome"]);
+        for (wanted, enabled_after) in [
+            (PackageState::Uninstalled, false),
+            (PackageState::Enabled, true),
+        ] {
+            for cmd in apply_pkg_state_commands(&package, wanted, Some(user.clone()), &phone, true)
+            {
+                adb.shell("mock-0001", &cmd)
+                    .expect("fake ADB knows the command");
+            }
+            package.state = wanted;
+            assert_eq!(enabled(), enabled_after);
+        }
+    }
+
+    #[test]
+    fn fake_protected_user_and_unknown_device() {
+        let adb = FakeAdb::default();
+        // not mistaken for a stub `adb.exe`
+        assert!(crate::core::adb::is_genuine_version(
+            &adb.version().expect("always runs")
+        ));
+        assert!(adb.list_packages_sys("", None, Some(150)).is_err());
+        assert!(adb.getprop("no-such-serial", "ro.product.model").is_err());
+        assert!(
+            adb.shell("", "pm uninstall --user 0 not.a.package")
+                .is_err()
+        );
+    }
+}
@@ -1,9 +1,17 @@
 use crate::CACHE_DIR;
 use crate::CONFIG_DIR;
 use crate::core::utils::DisplayablePath;
-use crate::core::{sync::User, theme::Theme};
+use crate::core::{
+    adb::AdbServer,
+    save::last_backup_time,
+    sync::User,
+    theme::{SemanticColors, Theme},
+    uad_lists::{Origin, PackageState, Removal, UadList},
+};
 use crate::gui::views::settings::Settings;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::LazyLock;
@@ -16,10 +24,102 @@ pub struct Config {
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "independent checkboxes of the settings view"
+)]
 pub struct GeneralSettings {
     pub theme: String,
     pub expert_mode: bool,
+    /// Otherwise `expert_mode` only lasts for the session it was enabled in
+    #[serde(default)]
+    pub keep_expert_mode: bool,
     pub backup_folder: PathBuf,
+    /// Backups are duplicated into these, on a best-effort basis
+    #[serde(skip_serializing_if = "Vec::is_empty", default = "Vec::new")]
+    pub secondary_backup_folders: Vec<PathBuf>,
+    /// Log the commands instead of running them
+    #[serde(default)]
+    pub dry_run: bool,
+    /// Where exports (selection, package lists) are written
+    #[serde(default = "default_export_folder")]
+    pub export_folder: PathBuf,
+    /// Periodically re-check the state of the visible packages
+    #[serde(default)]
+    pub live_state_sync: bool,
+    /// Select the first connected device when none is.
+    /// Otherwise the user picks it, e.g. to not be interrupted by a device plugged in meanwhile.
+    #[serde(default = "default_auto_select_device")]
+    pub auto_select_device: bool,
+    /// Ask before uninstalling (or disabling) a single package too,
+    /// not only a reviewed selection
+    #[serde(default)]
+    pub confirm_every_action: bool,
+    /// Smaller package rows, to see more of them
+    #[serde(default)]
+    pub dense_mode: bool,
+    /// Distinguishable colors with color vision deficiencies
+    #[serde(default)]
+    pub color_blind: bool,
+    /// Semantic colors overrides, by theme name
+    #[serde(skip_serializing_if = "HashMap::is_empty", default)]
+    pub theme_colors: HashMap<String, SemanticColors>,
+    /// Allow exporting the removed packages that aren't in the lists, to improve them
+    #[serde(default)]
+    pub uncategorized_report: bool,
+    /// Check for new releases and offer to update in-app.
+    /// Distro packages turn it off, as they manage updates themselves.
+    #[serde(default = "default_self_update_enabled")]
+    pub self_update_enabled: bool,
+    /// Mirror of the debloat lists, see [`crate::core::uad_lists::resolve_list_url`]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub list_url: Option<String>,
+    /// Run before a reviewed selection (or a CLI `--apply`) is applied, see [`crate::core::hooks`].
+    /// A failure aborts the batch.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub pre_apply_hook: Option<PathBuf>,
+    /// Run once the batch is done. A failure is only reported.
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub post_apply_hook: Option<PathBuf>,
+    /// ADB server to use instead of the default one, as a port or `host:port`,
+    /// see [`crate::core::adb::AdbServer::parse`]
+    #[serde(skip_serializing_if = "Option::is_none", default)]
+    pub adb_server: Option<String>,
+    /// Keep the selection on disk, to offer restoring it after a restart or a crash
+    #[serde(default = "default_persist_selection")]
+    pub persist_selection: bool,
+}
+
+/// The user's documents, as the folder of the executable may be read-only
+fn default_export_folder() -> PathBuf {
+    dirs::document_dir().unwrap_or_else(|| CONFIG_DIR.clone())
+}
+
+const fn default_self_update_enabled() -> bool {
+    true
+}
+
+const fn default_auto_select_device() -> bool {
+    true
+}
+
+const fn default_persist_selection() -> bool {
+    true
+}
+
+impl GeneralSettings {
+    /// The primary backup folder first, then the secondary ones
+    pub fn backup_folders(&self) -> impl Iterator<Item = &PathBuf> {
+        std::iter::once(&self.backup_folder).chain(&self.secondary_backup_folders)
+    }
+
+    /// The configured [`Self::adb_server`], if it's valid
+    pub fn parsed_adb_server(&self) -> Option<AdbServer> {
+        let server = self.adb_server.as_deref()?;
+        AdbServer::parse(server)
+            .inspect_err(|e| warn!("Ignoring the ADB server `{server}`: {e}"))
+            .ok()
+    }
 }
 
 #[derive(Default, Debug, Clone)]
@@ -29,24 +129,87 @@ pub struct BackupSettings {
     pub users: Vec<User>,
     pub selected_user: Option<User>,
     pub backup_state: String,
+    /// Newest of `backups`, see [`Self::set_backups`]
+    pub last_backup: Option<DateTime<Utc>>,
+}
+
+impl BackupSettings {
+    /// Replaces the known backups, so that the view doesn't stat them on every frame
+    pub fn set_backups(&mut self, backups: Vec<DisplayablePath>) {
+        self.last_backup = last_backup_time(&backups);
+        self.backups = backups;
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
+#[expect(
+    clippy::struct_excessive_bools,
+    reason = "independent checkboxes of the settings view"
+)]
 pub struct DeviceSettings {
     /// Unique serial identifier
     pub device_id: String,
     pub disable_mode: bool,
     pub multi_user_mode: bool,
+    /// Listed first in the device pick-list
+    #[serde(default)]
+    pub favorite: bool,
+    /// Uninstall with `pm uninstall -k`: the data and caches stay on the device,
+    /// so an app restored later comes back as it was
+    #[serde(default)]
+    pub keep_data: bool,
+    #[serde(default)]
+    pub view_prefs: ViewPrefs,
     #[serde(skip)]
     pub backup: BackupSettings,
 }
 
+/// Filters of the package list, restored when the device is selected again
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(default)]
+pub struct ViewPrefs {
+    pub uad_list: UadList,
+    pub package_state: PackageState,
+    pub removal: Removal,
+    pub origin: Origin,
+    pub selected_only: bool,
+}
+
+impl Default for ViewPrefs {
+    fn default() -> Self {
+        Self {
+            uad_list: UadList::All,
+            package_state: PackageState::Enabled,
+            removal: Removal::Recommended,
+            origin: Origin::All,
+            selected_only: false,
+        }
+    }
+}
+
 impl Default for GeneralSettings {
     fn default() -> Self {
         Self {
             theme: Theme::default().to_string(),
             expert_mode: false,
+            keep_expert_mode: false,
             backup_folder: CACHE_DIR.join("backups"),
+            secondary_backup_folders: vec![],
+            dry_run: false,
+            export_folder: default_export_folder(),
+            live_state_sync: false,
+            auto_select_device: default_auto_select_device(),
+            confirm_every_action: false,
+            dense_mode: false,
+            color_blind: false,
+            theme_colors: HashMap::new(),
+            uncategorized_report: false,
+            self_update_enabled: default_self_update_enabled(),
+            list_url: None,
+            pre_apply_hook: None,
+            post_apply_hook: None,
+            adb_server: None,
+            persist_selection: default_persist_selection(),
         }
     }
 }
@@ -71,6 +234,24 @@ impl Config {
         fs::write(&*CONFIG_FILE, toml).expect("Could not write config file to disk!");
     }
 
+    /// Drop the settings of `device_id`, so its defaults apply the next time they're loaded.
+    /// Returns whether there were any.
+    pub fn remove_device(&mut self, device_id: &str) -> bool {
+        let len = self.devices.len();
+        self.devices.retain(|d| d.device_id != device_id);
+        self.devices.len() != len
+    }
+
+    /// [`Self::remove_device`] in the config file
+    pub fn forget_device(device_id: &str) {
+        let mut config = Self::load_configuration_file();
+        if config.remove_device(device_id) {
+            debug!("config: settings of {device_id} removed");
+            let toml = toml::to_string(&config).unwrap();
+            fs::write(&*CONFIG_FILE, toml).expect("Could not write config file to disk!");
+        }
+    }
+
     pub fn load_configuration_file() -> Self {
         match fs::read_to_string(&*CONFIG_FILE) {
             Ok(s) => match toml::from_str(&s) {
@@ -99,6 +280,50 @@ mod tests {
         fs::write(&*CONFIG_FILE, toml).expect("Could not write config file to disk!");
     }
 
+    #[test]
+    fn view_prefs_round_trip() {
+        let device = DeviceSettings {
+            device_id: "serial".to_string(),
+            view_prefs: ViewPrefs {
+                uad_list: UadList::Google,
+                package_state: PackageState::All,
+                removal: Removal::Expert,
+                origin: Origin::Carrier,
+                selected_only: true,
+            },
+            ..DeviceSettings::default()
+        };
+        let config = Config {
+            general: GeneralSettings::default(),
+            devices: vec![device.clone()],
+        };
+        let toml = toml::to_string(&config).expect("serializable");
+        let parsed: Config = toml::from_str(&toml).expect("deserializable");
+        assert_eq!(parsed.devices[0].view_prefs, device.view_prefs);
+
+        // configs written before `view_prefs` existed
+        let old: DeviceSettings =
+            toml::from_str("device_id = \"serial\"\ndisable_mode = false\nmulti_user_mode = true")
+                .expect("deserializable");
+        assert_eq!(old.view_prefs, ViewPrefs::default());
+    }
+
+    #[test]
+    fn remove_device_settings() {
+        let device = |id: &str| DeviceSettings {
+            device_id: id.to_string(),
+            ..DeviceSettings::default()
+        };
+        let mut config = Config {
+            general: GeneralSettings::default(),
+            devices: vec![device("a"), device("b")],
+        };
+        assert!(config.remove_device("a"));
+        assert!(!config.remove_device("a"));
+        assert_eq!(config.devices.len(), 1);
+        assert_eq!(config.devices[0].device_id, "b");
+    }
+
     #[test]
     fn test_create_default_config_file() {
         create_default_config_file();
@@ -0,0 +1,129 @@
+//! Audit trail of the package actions, to remember what was changed weeks ago.
+//!
+//! Each device has its own file in `CONFIG_DIR/history`, with 1 JSON [`Entry`] per line.
+//! Lines that can't be parsed (e.g. truncated by a crash) are skipped.
+
+use crate::CONFIG_DIR;
+use crate::core::uad_lists::PackageState;
+use crate::core::utils::device_file_stem;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// Local time, `%Y-%m-%d %H:%M:%S`
+    pub timestamp: String,
+    pub user: u16,
+    pub package: String,
+    pub from: PackageState,
+    pub to: PackageState,
+    /// `None` if the action succeeded
+    pub error: Option<String>,
+}
+
+impl Entry {
+    /// Timestamped now
+    pub fn new(
+        user: u16,
+        package: &str,
+        from: PackageState,
+        to: PackageState,
+        error: Option<String>,
+    ) -> Self {
+        Self {
+            timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            user,
+            package: package.to_string(),
+            from,
+            to,
+            error,
+        }
+    }
+}
+
+fn history_file(device_id: &str) -> PathBuf {
+    CONFIG_DIR
+        .join("history")
+        .join(format!("{}.jsonl", device_file_stem(device_id)))
+}
+
+/// Appends `entry` to the history of the device
+pub fn record(device_id: &str, entry: &Entry) -> Result<(), String> {
+    append(&history_file(device_id), entry)
+}
+
+/// Every entry of the device, oldest first
+pub fn load(device_id: &str) -> Vec<Entry> {
+    read(&history_file(device_id))
+}
+
+/// Forgets the history of the device
+pub fn clear(device_id: &str) -> Result<(), String> {
+    match fs::remove_file(history_file(device_id)) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+        _ => Ok(()),
+    }
+}
+
+fn append(path: &Path, entry: &Entry) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .and_then(|mut file| writeln!(file, "{line}"))
+        .map_err(|e| e.to_string())
+}
+
+fn read(path: &Path) -> Vec<Entry> {
+    fs::read_to_string(path)
+        .map(|s| {
+            s.lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("uadng_history_{}", std::process::id()));
+        let path = dir.join("serial.jsonl");
+        let _ = fs::remove_file(&path);
+        assert!(read(&path).is_empty());
+
+        let removed = Entry::new(
+            0,
+            "com.example",
+            PackageState::Enabled,
+            PackageState::Uninstalled,
+            None,
+        );
+        let failed = Entry::new(
+            10,
+            "com.example",
+            PackageState::Enabled,
+            PackageState::Disabled,
+            Some("Failure".to_string()),
+        );
+        append(&path, &removed).expect("writable temp dir");
+        // a line of a crashed write
+        fs::OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut f| writeln!(f, "{{\"timestamp\":"))
+            .expect("writable temp dir");
+        append(&path, &failed).expect("writable temp dir");
+        assert_eq!(read(&path), vec![removed, failed]);
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,118 @@
+//! User scripts run around a batch of actions, see
+//! [`GeneralSettings::pre_apply_hook`] and [`GeneralSettings::post_apply_hook`].
+//!
+//! They're run as `<script> <device serial>`, with the rights of UAD-ng:
+//! they can do anything, so only set scripts you trust.
+//! On Windows, `.ps1` scripts are run with PowerShell, `.bat` and `.cmd` ones with `cmd`.
+
+use crate::core::config::GeneralSettings;
+use crate::core::sync::{AdbError, is_dry_run};
+use std::path::Path;
+use std::process::Command;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Hook {
+    PreApply,
+    PostApply,
+}
+
+impl Hook {
+    pub const ALL: [Self; 2] = [Self::PreApply, Self::PostApply];
+
+    /// The script set for this hook, if any
+    pub fn script(self, settings: &GeneralSettings) -> Option<&Path> {
+        match self {
+            Self::PreApply => settings.pre_apply_hook.as_deref(),
+            Self::PostApply => settings.post_apply_hook.as_deref(),
+        }
+    }
+}
+
+impl std::fmt::Display for Hook {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::PreApply => "Before applying",
+            Self::PostApply => "After applying",
+        })
+    }
+}
+
+/// What runs `script` on Windows, which only starts executables by itself.
+/// `None` for executables.
+fn windows_interpreter(script: &Path) -> Option<&'static [&'static str]> {
+    let ext = script.extension()?.to_string_lossy().to_ascii_lowercase();
+    match ext.as_str() {
+        "ps1" => Some(&[
+            "powershell",
+            "-NoProfile",
+            "-NonInteractive",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-File",
+        ]),
+        "bat" | "cmd" => Some(&["cmd", "/C"]),
+        _ => None,
+    }
+}
+
+/// Runs `script` with the device `serial` as argument.
+/// A non-zero exit is an error, with the script's stderr (or stdout) as message.
+pub fn run(script: &Path, serial: &str) -> Result<(), AdbError> {
+    let command = format!("{} {serial}", script.display());
+    if is_dry_run() {
+        info!("[DRY RUN] [hook] {command}");
+        return Ok(());
+    }
+    info!("Running hook: {command}");
+    let mut cmd = match windows_interpreter(script).filter(|_| cfg!(target_os = "windows")) {
+        Some([program, args @ ..]) => {
+            let mut cmd = Command::new(program);
+            cmd.args(args).arg(script);
+            cmd
+        }
+        _ => Command::new(script),
+    };
+    let output = cmd.arg(serial).output().map_err(|e| AdbError::Command {
+        error: format!("Cannot run the hook: {e}"),
+        command: command.clone(),
+        exit_code: None,
+    })?;
+    let stdout = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+    if output.status.success() {
+        debug!("Hook output: {stdout}");
+        return Ok(());
+    }
+    Err(AdbError::Command {
+        error: format!(
+            "The hook failed ({}): {}",
+            output.status,
+            if stderr.is_empty() { stdout } else { stderr }
+        ),
+        command,
+        exit_code: output.status.code(),
+    })
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exit_status() {
+        assert!(run(Path::new("true"), "serial").is_ok());
+        let err = run(Path::new("false"), "serial").expect_err("non-zero exit");
+        assert_eq!(err.command(), "false serial");
+        assert!(run(Path::new("/nonexistent/hook"), "serial").is_err());
+    }
+
+    #[test]
+    fn windows_interpreters() {
+        let program = |script: &str| windows_interpreter(Path::new(script)).map(|i| i[0]);
+        assert_eq!(program(r"C:\hooks\notify.PS1"), Some("powershell"));
+        assert_eq!(program("notify.bat"), Some("cmd"));
+        assert_eq!(program("notify.cmd"), Some("cmd"));
+        assert_eq!(program("notify.exe"), None);
+        assert_eq!(program("notify"), None);
+    }
+}
@@ -1,7 +1,11 @@
 pub mod adb;
+pub mod backend;
 pub mod config;
 pub mod helpers;
+pub mod history;
+pub mod hooks;
 pub mod save;
+pub mod snapshot;
 pub mod sync;
 pub mod theme;
 pub mod uad_lists;
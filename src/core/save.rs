@@ -1,13 +1,51 @@
-use crate::core::config::{Config, DeviceSettings};
+use crate::core::config::DeviceSettings;
 use crate::core::sync::{CorePackage, Phone, User, apply_pkg_state_commands};
-use crate::core::utils::DisplayablePath;
+use crate::core::uad_lists::{PackageHashMap, PackageState};
+use crate::core::utils::{DisplayablePath, check_writable, package_row};
 use crate::gui::widgets::package_row::PackageRow;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
 use serde::{Deserialize, Serialize};
 use std::{
     fs,
     path::{Path, PathBuf},
 };
 
+/// Stem of the backup files, e.g. `2024-01-31_18-30-00`
+const BACKUP_TIME_FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+
+/// File name of a backup made at `t`
+#[must_use]
+pub fn backup_file_name(t: NaiveDateTime) -> String {
+    format!("{}.json", t.format(BACKUP_TIME_FORMAT))
+}
+
+/// When `backup` was made, from its name (the inverse of [`backup_file_name`]).
+/// `None` for a renamed backup.
+#[must_use]
+pub fn backup_time(backup: &DisplayablePath) -> Option<NaiveDateTime> {
+    let stem = backup.path.file_stem()?.to_str()?;
+    NaiveDateTime::parse_from_str(stem, BACKUP_TIME_FORMAT).ok()
+}
+
+/// When the newest of `backups` was made.
+/// Renamed backups fall back to the last modification of their file.
+#[must_use]
+pub fn last_backup_time(backups: &[DisplayablePath]) -> Option<DateTime<Utc>> {
+    backups
+        .iter()
+        .filter_map(|b| match backup_time(b) {
+            Some(t) => Local
+                .from_local_datetime(&t)
+                .earliest()
+                .map(|t| t.with_timezone(&Utc)),
+            None => fs::metadata(&b.path)
+                .and_then(|m| m.modified())
+                .ok()
+                .map(DateTime::<Utc>::from),
+        })
+        .max()
+}
+
 #[derive(Default, Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
 pub struct PhoneBackup {
     pub device_id: String,
@@ -21,48 +59,73 @@ pub struct UserBackup {
 }
 
 /// Backup all `Uninstalled` and `Disabled` packages
-pub async fn backup_phone(
+/// into `backup_dir`/`device_id`,
+/// and a copy into every `secondary_dirs`/`device_id`.
+///
+/// Only a failure to write into `backup_dir` fails the backup,
+/// the copies are best-effort.
+///
+/// `on_progress` is called after each package with `(processed, total)`,
+/// so callers running this off the GUI thread can report how far along it is.
+pub fn backup_phone(
     users: Vec<User>,
     device_id: String,
-    phone_packages: Vec<Vec<PackageRow>>,
+    backup_dir: &Path,
+    secondary_dirs: &[PathBuf],
+    phone_packages: &[Vec<PackageRow>],
+    mut on_progress: impl FnMut(usize, usize),
 ) -> Result<bool, String> {
+    let backup_path = &*backup_dir.join(&device_id);
+    check_writable(backup_path)?;
+
     let mut backup = PhoneBackup {
-        device_id: device_id.clone(),
+        device_id,
         ..PhoneBackup::default()
     };
 
+    let total: usize = users
+        .iter()
+        .filter_map(|u| phone_packages.get(u.index))
+        .map(Vec::len)
+        .sum();
+    let mut processed = 0;
+
     for u in users {
         let mut user_backup = UserBackup {
             id: u.id,
             ..UserBackup::default()
         };
 
-        for p in phone_packages[u.index].clone() {
+        for p in phone_packages.get(u.index).into_iter().flatten() {
             user_backup.packages.push(CorePackage {
                 name: p.name.clone(),
                 state: p.state,
             });
+            processed += 1;
+            on_progress(processed, total);
         }
         backup.users.push(user_backup);
     }
 
     match serde_json::to_string_pretty(&backup) {
         Ok(json) => {
-            let backup_dir: PathBuf = Config::load_configuration_file().general.backup_folder;
-            let backup_path = &*backup_dir.join(device_id);
+            let backup_filename = backup_file_name(Local::now().naive_local());
 
-            if let Err(e) = fs::create_dir_all(backup_path) {
-                error!("BACKUP: could not create backup dir: {e}");
-                return Err(e.to_string());
+            if let Err(err) = fs::write(backup_path.join(&backup_filename), &json) {
+                return Err(err.to_string());
             }
-
-            let backup_filename =
-                format!("{}.json", chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"));
-
-            match fs::write(backup_path.join(backup_filename), json) {
-                Ok(()) => Ok(true),
-                Err(err) => Err(err.to_string()),
+            for dir in secondary_dirs {
+                let copy_path = dir.join(&backup.device_id);
+                if let Err(err) = check_writable(&copy_path).and_then(|()| {
+                    fs::write(copy_path.join(&backup_filename), &json).map_err(|e| e.to_string())
+                }) {
+                    warn!(
+                        "BACKUP: could not write a copy into {}: {err}",
+                        dir.display()
+                    );
+                }
             }
+            Ok(true)
         }
         Err(err) => Err(err.to_string()),
     }
@@ -78,6 +141,125 @@ pub fn list_available_backups(dir: &Path) -> Vec<DisplayablePath> {
     }
 }
 
+/// Backups of `device_id` across `dirs`.
+/// A backup found in several of them (same timestamp) is only listed once,
+/// from the first folder it's found in.
+pub fn list_available_backups_in<'a>(
+    dirs: impl IntoIterator<Item = &'a PathBuf>,
+    device_id: &str,
+) -> Vec<DisplayablePath> {
+    let mut backups: Vec<DisplayablePath> = vec![];
+    for dir in dirs {
+        for backup in list_available_backups(&dir.join(device_id)) {
+            if !backups
+                .iter()
+                .any(|b| b.path.file_name() == backup.path.file_name())
+            {
+                backups.push(backup);
+            }
+        }
+    }
+    backups
+}
+
+/// Make `name` usable as a file name on every OS:
+/// path separators and reserved characters become `_`.
+///
+/// Returns `None` if nothing usable is left.
+#[must_use]
+pub fn sanitize_backup_name(name: &str) -> Option<String> {
+    let name: String = name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_control() || matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') {
+                '_'
+            } else {
+                c
+            }
+        })
+        .collect();
+    let name = name.trim_matches(['.', ' ']);
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+/// Rename the backup file `old` to `new_name` (sanitized), in the same folder.
+/// Refuses to overwrite another backup.
+pub fn rename_backup(old: &DisplayablePath, new_name: &str) -> Result<DisplayablePath, String> {
+    let name = sanitize_backup_name(new_name).ok_or("Invalid backup name")?;
+    let mut path = old.path.with_file_name(name);
+    if let Some(ext) = old.path.extension() {
+        let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+        file_name.push(".");
+        file_name.push(ext);
+        path.set_file_name(file_name);
+    }
+    if path == old.path {
+        return Ok(old.clone());
+    }
+    if path.exists() {
+        return Err(format!(
+            "A backup named `{}` already exists",
+            new_name.trim()
+        ));
+    }
+    fs::rename(&old.path, &path).map_err(|e| e.to_string())?;
+    Ok(DisplayablePath { path })
+}
+
+/// Delete the backup file `backup`
+pub fn delete_backup(backup: &DisplayablePath) -> Result<(), String> {
+    fs::remove_file(&backup.path).map_err(|e| format!("Cannot delete {backup}: {e}"))
+}
+
+/// Parse the backup file at `path`
+pub fn read_backup(path: &Path) -> Result<PhoneBackup, String> {
+    let data = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&data).map_err(|e| format!("Invalid backup file: {e}"))
+}
+
+/// The device the backup at `path` was taken of, to view it offline:
+/// see [`Phone::backup`]. Nothing is read from the device itself.
+pub fn offline_phone(path: &Path) -> Result<Phone, String> {
+    let backup = read_backup(path)?;
+    Ok(Phone {
+        model: backup.device_id.clone(),
+        user_list: backup
+            .users
+            .iter()
+            .enumerate()
+            .map(|(index, u)| User {
+                id: u.id,
+                index,
+                ..User::default()
+            })
+            .collect(),
+        adb_id: backup.device_id,
+        backup: Some(path.to_path_buf()),
+        ..Phone::default()
+    })
+}
+
+/// The packages of every user of [`offline_phone`], in their backed-up state
+pub fn offline_packages(
+    path: &Path,
+    uad_lists: &PackageHashMap,
+) -> Result<Vec<Vec<PackageRow>>, String> {
+    Ok(read_backup(path)?
+        .users
+        .iter()
+        .map(|u| {
+            let mut rows: Vec<PackageRow> = u
+                .packages
+                .iter()
+                .map(|p| package_row(uad_lists, &p.name, p.state, ""))
+                .collect();
+            rows.sort_by_key(|p| p.name.to_lowercase());
+            rows
+        })
+        .collect())
+}
+
 pub fn list_available_backup_user(backup: DisplayablePath) -> Vec<User> {
     match fs::read_to_string(backup.path) {
         Ok(data) => serde_json::from_str::<PhoneBackup>(&data)
@@ -86,8 +268,7 @@ pub fn list_available_backup_user(backup: DisplayablePath) -> Vec<User> {
             .into_iter()
             .map(|u| User {
                 id: u.id,
-                index: 0,
-                protected: false,
+                ..User::default()
             })
             .collect(),
         Err(e) => {
@@ -97,10 +278,19 @@ pub fn list_available_backup_user(backup: DisplayablePath) -> Vec<User> {
     }
 }
 
+/// APKs pulled along a backup live in a folder named after it:
+/// `2024-01-01_00-00-00.json` -> `2024-01-01_00-00-00/<package>.apk`
+#[must_use]
+pub fn backup_apk(backup: &Path, package: &str) -> PathBuf {
+    backup.with_extension("").join(format!("{package}.apk"))
+}
+
 #[derive(Debug)]
 pub struct BackupPackage {
     pub index: usize,
     pub commands: Vec<String>,
+    /// Installed before running `commands`
+    pub apk: Option<PathBuf>,
 }
 
 pub fn restore_backup(
@@ -108,64 +298,207 @@ pub fn restore_backup(
     packages: &[Vec<PackageRow>],
     settings: &DeviceSettings,
 ) -> Result<Vec<BackupPackage>, String> {
-    match fs::read_to_string(
-        settings
-            .backup
-            .selected
-            .as_ref()
-            .ok_or("field should be Some type")?
-            .path
-            .clone(),
-    ) {
-        Ok(data) => {
-            let phone_backup: PhoneBackup =
-                serde_json::from_str(&data).expect("Unable to parse backup file");
-
-            let mut commands = vec![];
-            for u in phone_backup.users {
-                let index = match selected_device.user_list.iter().find(|x| x.id == u.id) {
-                    Some(i) => i.index,
-                    None => return Err(format!("user {} doesn't exist", u.id)),
-                };
-
-                for (i, backup_package) in u.packages.iter().enumerate() {
-                    let package: CorePackage = match packages[index]
-                        .iter()
-                        .find(|x| x.name == backup_package.name)
-                    {
-                        Some(p) => p.into(),
-                        None => {
-                            return Err(format!(
-                                "{} not found for user {}",
-                                backup_package.name, u.id
-                            ));
-                        }
-                    };
-                    let p_commands = apply_pkg_state_commands(
-                        &package,
-                        backup_package.state,
-                        settings
-                            .backup
-                            .selected_user
-                            .ok_or("field should be Some type")?,
-                        selected_device,
-                    );
-                    if !p_commands.is_empty() {
-                        commands.push(BackupPackage {
-                            index: i,
-                            commands: p_commands,
-                        });
-                    }
+    let backup_path = &settings
+        .backup
+        .selected
+        .as_ref()
+        .ok_or("field should be Some type")?
+        .path;
+    let phone_backup = read_backup(backup_path)?;
+
+    let mut commands = vec![];
+    for u in phone_backup.users {
+        let index = match selected_device.user_list.iter().find(|x| x.id == u.id) {
+            Some(i) => i.index,
+            None => return Err(format!("user {} doesn't exist", u.id)),
+        };
+
+        for (i, backup_package) in u.packages.iter().enumerate() {
+            let package: CorePackage = match packages[index]
+                .iter()
+                .find(|x| x.name == backup_package.name)
+            {
+                Some(p) => p.into(),
+                None => {
+                    return Err(format!(
+                        "{} not found for user {}",
+                        backup_package.name, u.id
+                    ));
                 }
-            }
-            if !commands.is_empty() {
+            };
+            let p_commands = apply_pkg_state_commands(
+                &package,
+                backup_package.state,
+                Some(
+                    settings
+                        .backup
+                        .selected_user
+                        .clone()
+                        .ok_or("field should be Some type")?,
+                ),
+                selected_device,
+                settings.keep_data,
+            );
+            if !p_commands.is_empty() {
+                // `install-existing` only works if the APK is still on the device
+                let apk = Some(backup_apk(backup_path, &backup_package.name)).filter(|apk| {
+                    backup_package.state != PackageState::Uninstalled
+                        && apk.is_file()
+                        && uninstalled_for_all_users(packages, &backup_package.name)
+                });
                 commands.push(BackupPackage {
-                    index: 0,
-                    commands: vec![],
+                    index: i,
+                    commands: p_commands,
+                    apk,
                 });
             }
-            Ok(commands)
         }
-        Err(e) => Err(e.to_string()),
+    }
+    if !commands.is_empty() {
+        commands.push(BackupPackage {
+            index: 0,
+            commands: vec![],
+            apk: None,
+        });
+    }
+    Ok(commands)
+}
+
+fn uninstalled_for_all_users(packages: &[Vec<PackageRow>], name: &str) -> bool {
+    packages
+        .iter()
+        .flatten()
+        .filter(|p| p.name == name)
+        .all(|p| p.state == PackageState::Uninstalled)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_times() {
+        let t = NaiveDateTime::parse_from_str("2024-01-31 18:30:05", "%Y-%m-%d %H:%M:%S")
+            .expect("valid date");
+        let name = backup_file_name(t);
+        assert_eq!(name, "2024-01-31_18-30-05.json");
+        let backup = |file: &str| DisplayablePath {
+            path: Path::new("backups/serial").join(file),
+        };
+        assert_eq!(backup_time(&backup(&name)), Some(t));
+        assert_eq!(backup_time(&backup("clean baseline.json")), None);
+
+        let older = backup_file_name(t - chrono::Duration::days(3));
+        let newest = last_backup_time(&[backup(&older), backup(&name)]).expect("parsed");
+        assert_eq!(newest.with_timezone(&Local).naive_local(), t);
+        assert_eq!(last_backup_time(&[]), None);
+    }
+
+    #[test]
+    fn apk_next_to_backup() {
+        assert_eq!(
+            backup_apk(
+                Path::new("backups/serial/2024-01-01_00-00-00.json"),
+                "com.example"
+            ),
+            Path::new("backups/serial/2024-01-01_00-00-00/com.example.apk")
+        );
+    }
+
+    #[test]
+    fn backup_names() {
+        assert_eq!(
+            sanitize_backup_name(" clean baseline "),
+            Some("clean baseline".to_string())
+        );
+        assert_eq!(
+            sanitize_backup_name("before: Knox/test?"),
+            Some("before_ Knox_test_".to_string())
+        );
+        assert_eq!(sanitize_backup_name("../x"), Some("_x".to_string()));
+        assert_eq!(sanitize_backup_name(".."), None);
+        assert_eq!(sanitize_backup_name("   "), None);
+    }
+
+    #[test]
+    fn rename_keeps_extension() {
+        let dir = std::env::temp_dir().join(format!("uadng_rename_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let old = DisplayablePath {
+            path: dir.join("2024-01-01_00-00-00.json"),
+        };
+        fs::write(&old.path, "{}").expect("write backup");
+        fs::write(dir.join("taken.json"), "{}").expect("write backup");
+
+        let new = rename_backup(&old, "clean baseline").expect("rename");
+        assert_eq!(new.path, dir.join("clean baseline.json"));
+        assert!(new.path.exists() && !old.path.exists());
+        assert!(rename_backup(&new, "taken").is_err());
+
+        delete_backup(&new).expect("delete");
+        assert!(!new.path.exists());
+        assert!(delete_backup(&new).is_err());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
+    }
+
+    #[test]
+    fn open_offline() {
+        let dir = std::env::temp_dir().join(format!("uadng_offline_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let path = dir.join("2024-01-01_00-00-00.json");
+        let backup = PhoneBackup {
+            device_id: "serial".to_string(),
+            users: vec![
+                UserBackup {
+                    id: 0,
+                    packages: vec![
+                        CorePackage {
+                            name: "com.b".to_string(),
+                            state: PackageState::Disabled,
+                        },
+                        CorePackage {
+                            name: "com.a".to_string(),
+                            state: PackageState::Uninstalled,
+                        },
+                    ],
+                },
+                UserBackup {
+                    id: 10,
+                    packages: vec![],
+                },
+            ],
+        };
+        fs::write(&path, serde_json::to_string(&backup).expect("serializable")).expect("write");
+
+        let phone = offline_phone(&path).expect("valid backup");
+        assert_eq!(phone.adb_id, "serial");
+        assert_eq!(phone.backup.as_deref(), Some(path.as_path()));
+        assert_eq!(
+            phone
+                .user_list
+                .iter()
+                .map(|u| (u.id, u.index))
+                .collect::<Vec<_>>(),
+            [(0, 0), (10, 1)]
+        );
+
+        let packages = offline_packages(&path, &PackageHashMap::new()).expect("valid backup");
+        assert_eq!(packages.len(), 2);
+        assert_eq!(
+            packages[0]
+                .iter()
+                .map(|p| (p.name.as_str(), p.state))
+                .collect::<Vec<_>>(),
+            [
+                ("com.a", PackageState::Uninstalled),
+                ("com.b", PackageState::Disabled)
+            ]
+        );
+
+        fs::write(&path, "not json").expect("write");
+        assert!(offline_phone(&path).is_err());
+
+        fs::remove_dir_all(&dir).expect("cleanup");
     }
 }
@@ -0,0 +1,181 @@
+//! Snapshots of the package states, to find out later what changed outside of UAD-ng
+//! (e.g. an OEM update re-enabling packages after a reboot).
+//!
+//! Unlike backups, they're only compared, never restored.
+//! Each device has a single snapshot, `CONFIG_DIR/snapshots/<device>.json`.
+
+use crate::CONFIG_DIR;
+use crate::core::adb::PmListPacksFlag;
+use crate::core::backend::backend;
+use crate::core::sync::{Phone, verify_package_state};
+use crate::core::uad_lists::PackageState;
+use crate::core::utils::device_file_stem;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    /// Local time, `%Y-%m-%d %H:%M:%S`
+    pub timestamp: String,
+    /// `user id -> package -> state`
+    pub users: BTreeMap<u16, BTreeMap<String, PackageState>>,
+}
+
+/// A package whose state isn't the one of the snapshot anymore
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Change {
+    pub user: u16,
+    pub package: String,
+    pub before: PackageState,
+    pub now: PackageState,
+}
+
+/// `--user` flag of the commands, omitted for single-user devices like the package list does
+fn user_flag(phone: &Phone, id: u16) -> Option<u16> {
+    (phone.user_list.len() > 1).then_some(id)
+}
+
+/// Ids of the users of `phone`, or only the owner if they're unknown
+fn user_ids(phone: &Phone) -> Vec<u16> {
+    if phone.user_list.is_empty() {
+        vec![0]
+    } else {
+        phone.user_list.iter().map(|u| u.id).collect()
+    }
+}
+
+/// The current state of every package of every user, read from the device
+pub fn snapshot_state(phone: &Phone) -> Result<StateSnapshot, String> {
+    let mut users = BTreeMap::new();
+    for id in user_ids(phone) {
+        let user_id = user_flag(phone, id);
+        let names = backend().list_packages_sys(
+            &phone.adb_id,
+            Some(PmListPacksFlag::IncludeUninstalled),
+            user_id,
+        )?;
+        let states = verify_package_state(&phone.adb_id, user_id, &names)?;
+        users.insert(id, names.into_iter().zip(states).collect());
+    }
+    Ok(StateSnapshot {
+        timestamp: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        users,
+    })
+}
+
+/// Packages of `snapshot` whose state on the device changed since.
+/// Packages installed since aren't part of it.
+pub fn compare(phone: &Phone, snapshot: &StateSnapshot) -> Result<Vec<Change>, String> {
+    let mut changes = vec![];
+    for (&id, before) in &snapshot.users {
+        let names: Vec<String> = before.keys().cloned().collect();
+        let now = verify_package_state(&phone.adb_id, user_flag(phone, id), &names)?;
+        changes.extend(diff(id, before, names.into_iter().zip(now)));
+    }
+    Ok(changes)
+}
+
+fn diff(
+    user: u16,
+    before: &BTreeMap<String, PackageState>,
+    now: impl IntoIterator<Item = (String, PackageState)>,
+) -> Vec<Change> {
+    now.into_iter()
+        .filter_map(|(package, now)| {
+            let before = *before.get(&package)?;
+            (before != now).then_some(Change {
+                user,
+                package,
+                before,
+                now,
+            })
+        })
+        .collect()
+}
+
+fn snapshot_file(device_id: &str) -> PathBuf {
+    CONFIG_DIR
+        .join("snapshots")
+        .join(format!("{}.json", device_file_stem(device_id)))
+}
+
+/// Replaces the snapshot of the device
+pub fn save(device_id: &str, snapshot: &StateSnapshot) -> Result<(), String> {
+    write(&snapshot_file(device_id), snapshot)
+}
+
+/// The snapshot of the device, if any
+pub fn load(device_id: &str) -> Option<StateSnapshot> {
+    read(&snapshot_file(device_id))
+}
+
+fn write(path: &Path, snapshot: &StateSnapshot) -> Result<(), String> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    let json = serde_json::to_string_pretty(snapshot).map_err(|e| e.to_string())?;
+    fs::write(path, json).map_err(|e| e.to_string())
+}
+
+fn read(path: &Path) -> Option<StateSnapshot> {
+    let data = fs::read_to_string(path).ok()?;
+    serde_json::from_str(&data)
+        .map_err(|e| warn!("Invalid snapshot {}: {e}", path.display()))
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn changes_since_snapshot() {
+        let before = BTreeMap::from([
+            ("com.a".to_string(), PackageState::Uninstalled),
+            ("com.b".to_string(), PackageState::Disabled),
+            ("com.c".to_string(), PackageState::Enabled),
+        ]);
+        let now = [
+            ("com.a".to_string(), PackageState::Enabled),
+            ("com.b".to_string(), PackageState::Disabled),
+            ("com.c".to_string(), PackageState::Uninstalled),
+            ("com.new".to_string(), PackageState::Enabled),
+        ];
+        assert_eq!(
+            diff(10, &before, now),
+            [
+                Change {
+                    user: 10,
+                    package: "com.a".to_string(),
+                    before: PackageState::Uninstalled,
+                    now: PackageState::Enabled,
+                },
+                Change {
+                    user: 10,
+                    package: "com.c".to_string(),
+                    before: PackageState::Enabled,
+                    now: PackageState::Uninstalled,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = std::env::temp_dir().join(format!("uadng_snapshot_{}", std::process::id()));
+        let path = dir.join("serial.json");
+        assert_eq!(read(&path), None);
+        let snapshot = StateSnapshot {
+            timestamp: "2024-01-01 00:00:00".to_string(),
+            users: BTreeMap::from([(
+                0,
+                BTreeMap::from([("com.a".to_string(), PackageState::Disabled)]),
+            )]),
+        };
+        write(&path, &snapshot).expect("writable temp dir");
+        assert_eq!(read(&path), Some(snapshot));
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
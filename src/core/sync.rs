@@ -1,14 +1,30 @@
 use crate::core::{
-    adb::{ACommand as AdbCommand, PM_CLEAR_PACK, to_trimmed_utf8},
+    adb::{AdbOutcome, PM_CLEAR_PACK, PmListPacksFlag, Transport, UserInfo, is_genuine_version},
+    backend::backend,
     uad_lists::PackageState,
 };
 use crate::gui::{views::list::PackageInfo, widgets::package_row::PackageRow};
 use retry::{OperationResult, delay::Fixed, retry};
 use serde::{Deserialize, Serialize};
-use std::process::Command;
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{LazyLock, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
 
-#[cfg(target_os = "windows")]
-use std::os::windows::process::CommandExt;
+/// When set, [`adb_shell_command`] only logs what it would run.
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Enable or disable dry-run mode, see [`is_dry_run`]
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether state-changing commands are only logged, not sent to the device
+#[must_use]
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
 
 /// An Android device, typically a phone
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -22,6 +38,13 @@ pub struct Phone {
     pub user_list: Vec<User>,
     /// Unique serial identifier
     pub adb_id: String, // could be `Copy`
+    /// Tells apart the same device listed twice (USB and Wi-Fi)
+    pub transport: Transport,
+    /// Read from this backup instead of a connected device:
+    /// nothing may be sent to `adb_id`
+    pub backup: Option<PathBuf>,
+    /// See [`crate::core::config::DeviceSettings::favorite`]
+    pub favorite: bool,
 }
 
 impl Default for Phone {
@@ -31,34 +54,255 @@ impl Default for Phone {
             android_sdk: 0,
             user_list: vec![],
             adb_id: String::default(),
+            transport: Transport::Unknown,
+            backup: None,
+            favorite: false,
         }
     }
 }
 
 impl std::fmt::Display for Phone {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}", self.model)
+        if self.favorite {
+            write!(f, "\u{2605} ")?;
+        }
+        if self.backup.is_some() {
+            return write!(f, "{} (offline / from backup)", self.model);
+        }
+        match self.transport {
+            Transport::Unknown => write!(f, "{}", self.model),
+            // already says so
+            Transport::Emulator if self.model.starts_with(EMULATOR_NAME) => {
+                write!(f, "{}", self.model)
+            }
+            t => write!(f, "{} ({t})", self.model),
+        }
+    }
+}
+
+/// What a user is for, from its `UserInfo` flags
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UserKind {
+    Owner,
+    Secondary,
+    /// Managed (work) profile
+    Work,
+    Guest,
+    Restricted,
+    /// Any other profile: clone, private space, Secure Folder, ...
+    Profile,
+}
+
+impl UserKind {
+    #[must_use]
+    pub const fn from_flags(id: u16, flags: u32) -> Self {
+        if flags & UserInfo::FLAG_MANAGED_PROFILE != 0 {
+            Self::Work
+        } else if flags & UserInfo::FLAG_PROFILE != 0 {
+            Self::Profile
+        } else if flags & UserInfo::FLAG_GUEST != 0 {
+            Self::Guest
+        } else if flags & UserInfo::FLAG_RESTRICTED != 0 {
+            Self::Restricted
+        } else if flags & UserInfo::FLAG_PRIMARY != 0 || id == 0 {
+            Self::Owner
+        } else {
+            Self::Secondary
+        }
+    }
+}
+
+impl std::fmt::Display for UserKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Owner => "Owner",
+            Self::Secondary => "Secondary",
+            Self::Work => "Work",
+            Self::Guest => "Guest",
+            Self::Restricted => "Restricted",
+            Self::Profile => "Profile",
+        })
     }
 }
 
-/// `UserInfo` but relevant to UAD
-#[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
+/// `UserInfo` but relevant to UAD.
+///
+/// `id` is the key: `name` and `kind` are only for display,
+/// and unknown for users read from a backup.
+#[derive(Default, Debug, Clone)]
 pub struct User {
     pub id: u16,
     pub index: usize,
     pub protected: bool,
+    /// Why it's `protected`, see [`protection_reason`]
+    pub protected_reason: Option<String>,
+    pub name: Option<String>,
+    pub kind: Option<UserKind>,
+}
+
+impl User {
+    /// The owner and protected users must never be removed
+    #[must_use]
+    pub fn is_removable(&self) -> bool {
+        self.id != 0 && !self.protected && self.kind != Some(UserKind::Owner)
+    }
+}
+
+impl PartialEq for User {
+    fn eq(&self, other: &Self) -> bool {
+        self.id == other.id
+    }
+}
+
+impl Eq for User {}
+
+impl PartialOrd for User {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// By `id`, like [`PartialEq`]
+impl Ord for User {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.id.cmp(&other.id)
+    }
 }
 
 impl std::fmt::Display for User {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "user {}", self.id)
+        write!(f, "user {}", self.id)?;
+        // names of secondary users and profiles say more than their kind
+        match (self.kind, &self.name) {
+            (Some(kind @ (UserKind::Owner | UserKind::Work | UserKind::Guest)), _)
+            | (Some(kind), None) => write!(f, " ({kind})"),
+            (_, Some(name)) => write!(f, " ({name})"),
+            (None, None) => Ok(()),
+        }
     }
 }
 
 /// An enum to contain different variants for errors yielded by ADB.
+///
+/// `exit_code` is `None` if the command didn't run, or was killed.
 #[derive(Debug, Clone)]
 pub enum AdbError {
-    Generic(String),
+    /// A shell command failed.
+    /// `command` is the full `adb` invocation, so it can be re-run manually.
+    Command {
+        error: String,
+        command: String,
+        exit_code: Option<i32>,
+    },
+    /// The device went away (unplugged, offline, ...) in the middle of an operation.
+    /// Any other command sent to it will fail the same way.
+    DeviceOffline {
+        error: String,
+        command: String,
+        exit_code: Option<i32>,
+    },
+    /// USB debugging isn't (or no longer) allowed on the device.
+    /// Like [`Self::DeviceOffline`], the next commands will fail too.
+    Unauthorized {
+        error: String,
+        command: String,
+        exit_code: Option<i32>,
+    },
+    /// The package isn't on the device (for this user): there's nothing left to act on
+    NotInstalled {
+        error: String,
+        command: String,
+        exit_code: Option<i32>,
+    },
+}
+
+impl AdbError {
+    /// Human-readable description
+    #[must_use]
+    pub fn message(&self) -> &str {
+        match self {
+            Self::Command { error, .. }
+            | Self::DeviceOffline { error, .. }
+            | Self::Unauthorized { error, .. }
+            | Self::NotInstalled { error, .. } => error,
+        }
+    }
+    /// The `adb` invocation that failed
+    #[must_use]
+    pub fn command(&self) -> &str {
+        match self {
+            Self::Command { command, .. }
+            | Self::DeviceOffline { command, .. }
+            | Self::Unauthorized { command, .. }
+            | Self::NotInstalled { command, .. } => command,
+        }
+    }
+    /// Exit status of the command, if it ran until the end
+    #[must_use]
+    pub const fn exit_code(&self) -> Option<i32> {
+        match self {
+            Self::Command { exit_code, .. }
+            | Self::DeviceOffline { exit_code, .. }
+            | Self::Unauthorized { exit_code, .. }
+            | Self::NotInstalled { exit_code, .. } => *exit_code,
+        }
+    }
+    /// Whether the commands still to send to the device will fail the same way,
+    /// so a batch should be aborted
+    #[must_use]
+    pub const fn is_fatal(&self) -> bool {
+        matches!(self, Self::DeviceOffline { .. } | Self::Unauthorized { .. })
+    }
+
+    /// The variant matching the `output` of the failed `command`
+    fn classify(output: &str, error: String, command: String, exit_code: Option<i32>) -> Self {
+        if is_device_lost(output) {
+            Self::DeviceOffline {
+                error,
+                command,
+                exit_code,
+            }
+        } else if is_unauthorized(output) {
+            Self::Unauthorized {
+                error,
+                command,
+                exit_code,
+            }
+        } else if is_not_installed(output) {
+            Self::NotInstalled {
+                error,
+                command,
+                exit_code,
+            }
+        } else {
+            Self::Command {
+                error,
+                command,
+                exit_code,
+            }
+        }
+    }
+}
+
+/// Check if ADB output means the device is no longer reachable
+#[must_use]
+pub fn is_device_lost(output: &str) -> bool {
+    output.contains("device offline")
+        || output.contains("no devices/emulators found")
+        // "adb: device 'SERIAL' not found"
+        || (output.contains("device '") && output.contains("' not found"))
+}
+
+/// Check if ADB output means USB debugging isn't allowed (anymore)
+#[must_use]
+pub fn is_unauthorized(output: &str) -> bool {
+    output.contains("device unauthorized")
+}
+
+/// Check if `pm` output means the package isn't there
+#[must_use]
+pub fn is_not_installed(output: &str) -> bool {
+    output.contains("[not installed for") || output.contains("Unknown package")
 }
 
 /// Runs an **arbitrary command** on the device's default `sh` implementation.
@@ -75,36 +319,72 @@ pub async fn adb_shell_command<S: AsRef<str>>(
     let serial = device_serial.as_ref();
 
     let label = &p.removal;
-
-    let mut cmd = Command::new("adb");
-    if !serial.is_empty() {
-        cmd.args(["-s", serial]);
+    let command = if serial.is_empty() {
+        format!("adb shell {action}")
+    } else {
+        format!("adb -s {serial} shell {action}")
+    };
+    if is_dry_run() {
+        info!("[DRY RUN] [{label}] {command}");
+        return Ok(p);
     }
-    cmd.arg("shell");
-    // this works because `sh` splits spaces
-    cmd.arg(&action);
 
-    #[cfg(target_os = "windows")]
-    let cmd = cmd.creation_flags(0x0800_0000); // do not open a cmd window
+    let output = backend().shell_outcome(serial, &action);
+    check_action_output(label, &action, command, output).map(|()| p)
+}
 
-    match match cmd.output() {
-        Err(e) => {
-            error!("ADB: {e}");
-            Err("Cannot run ADB, likely not found".to_string())
+/// Install `apk` (from the host) for `user_id`, then run `actions` in order.
+///
+/// Stops at the first failure. For packages whose APK is gone from the device,
+/// which `install-existing` can't bring back.
+pub async fn adb_install_apk(
+    serial: String,
+    apk: PathBuf,
+    user_id: Option<u16>,
+    actions: Vec<String>,
+    p: PackageInfo,
+) -> Result<PackageInfo, AdbError> {
+    let label = &p.removal;
+    let action = format!(
+        "install -r{} {}",
+        user_id.map(|u| format!(" --user {u}")).unwrap_or_default(),
+        apk.display()
+    );
+    let adb = if serial.is_empty() {
+        "adb".to_string()
+    } else {
+        format!("adb -s {serial}")
+    };
+    if is_dry_run() {
+        info!("[DRY RUN] [{label}] {adb} {action}");
+        for a in &actions {
+            info!("[DRY RUN] [{label}] {adb} shell {a}");
         }
-        Ok(o) => {
-            let stdout = to_trimmed_utf8(o.stdout);
-            if o.status.success() {
-                Ok(stdout)
-            } else {
-                let stderr = to_trimmed_utf8(o.stderr);
+        return Ok(p);
+    }
+    let installed = backend().install(&serial, &apk, user_id);
+    check_action_output(label, &action, format!("{adb} {action}"), installed)?;
+    for a in actions {
+        let output = backend().shell_outcome(&serial, &a);
+        check_action_output(label, &a, format!("{adb} shell {a}"), output)?;
+    }
+    Ok(p)
+}
 
-                // ADB does really weird things. Some errors are not redirected to stderr
-                let err = if stdout.is_empty() { stderr } else { stdout };
-                Err(err)
-            }
-        }
-    } {
+/// Turn the `output` of `action` into an error if it failed.
+///
+/// `Err` outputs are for commands that didn't run (ADB missing, script cut short, ...).
+fn check_action_output(
+    label: &str,
+    action: &str,
+    command: String,
+    output: Result<AdbOutcome, String>,
+) -> Result<(), AdbError> {
+    let (exit_code, output) = match output {
+        Ok(outcome) => (outcome.exit_code, outcome.into_result()),
+        Err(err) => (None, Err(err)),
+    };
+    match output {
         Ok(o) => {
             // On old devices, adb commands can return the `0` exit code even if there
             // is an error. On Android 4.4, ADB doesn't check if the package exists.
@@ -112,19 +392,372 @@ pub async fn adb_shell_command<S: AsRef<str>>(
             // Some commands are even killed by ADB before finishing and UAD-ng can't catch
             // the output.
             if ["Error", "Failure"].iter().any(|&e| o.contains(e)) {
-                return Err(AdbError::Generic(format!("[{label}] {action} -> {o}")));
+                return Err(AdbError::classify(
+                    &o,
+                    format!("[{label}] {action} -> {o}"),
+                    command,
+                    exit_code,
+                ));
             }
 
             info!("[{label}] {action} -> {o}");
-            Ok(p)
+            Ok(())
         }
         Err(err) => {
-            if !err.contains("[not installed for") {
-                return Err(AdbError::Generic(format!("[{label}] {action} -> {err}")));
+            let error = if err.contains("[not installed for") {
+                err.clone()
+            } else {
+                format!("[{label}] {action} -> {err}")
+            };
+            Err(AdbError::classify(&err, error, command, exit_code))
+        }
+    }
+}
+
+/// Printed after each command of a [`batch_script`], followed by its exit status
+pub const BATCH_MARKER: &str = "__UADNG_DONE__";
+
+/// Join `actions` into a single shell script, so they're sent in one `adb shell` call.
+/// The output of each command is followed by a [`BATCH_MARKER`] line with its exit status.
+#[must_use]
+pub fn batch_script(actions: &[String]) -> String {
+    actions
+        .iter()
+        .map(|a| format!("{a} 2>&1; echo {BATCH_MARKER} $?"))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+/// Maximum commands sent in a single `adb shell` call,
+/// so the command line stays well under ADB's limits
+pub const BATCH_SIZE: usize = 50;
+
+/// `(exit status, output)` of a command of a [`batch_script`], see [`parse_batch_output`]
+pub type BatchResult = Result<(i32, String), String>;
+
+/// Run `actions` as [`batch_script`]s of at most [`BATCH_SIZE`] commands,
+/// and return the results of all of them
+pub fn shell_batches(serial: &str, actions: &[String]) -> Result<Vec<BatchResult>, String> {
+    let mut results = Vec::with_capacity(actions.len());
+    for chunk in actions.chunks(BATCH_SIZE) {
+        let output = backend().shell(serial, &batch_script(chunk))?;
+        results.extend(parse_batch_output(&output, chunk.len()));
+    }
+    Ok(results)
+}
+
+/// Split the output of a [`batch_script`] into the `(exit status, output)` of each of its `n` commands.
+/// Commands without a [`BATCH_MARKER`] (the script got cut short) are errors.
+#[must_use]
+pub fn parse_batch_output(output: &str, n: usize) -> Vec<Result<(i32, String), String>> {
+    let mut results = Vec::with_capacity(n);
+    let mut current = vec![];
+    for line in output.lines() {
+        let Some((before, status)) = line.split_once(BATCH_MARKER) else {
+            current.push(line);
+            continue;
+        };
+        // output without a trailing newline
+        if !before.is_empty() {
+            current.push(before);
+        }
+        let out = current.join("\n");
+        current.clear();
+        // `$?` is always a number
+        results.push(Ok((status.trim().parse().unwrap_or(-1), out)));
+    }
+    results.resize_with(n, || Err("No output, the command didn't run".to_string()));
+    results
+}
+
+/// How long [`adb_shell_batch`] waits for its device to be listed
+pub const BATCH_DEVICE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Like [`adb_shell_command`], for the `actions` of many packages at once,
+/// in a single `adb shell` call. Results are in the order of `batch`.
+///
+/// As with separate commands, only the first action of a package decides its result:
+/// it's the one changing its state (see [`apply_pkg_state_commands`]).
+pub async fn adb_shell_batch(
+    device_serial: String,
+    batch: Vec<(PackageInfo, Vec<String>)>,
+) -> Vec<Result<PackageInfo, AdbError>> {
+    let serial = device_serial.as_str();
+    let command = |action: &str| {
+        if serial.is_empty() {
+            format!("adb shell {action}")
+        } else {
+            format!("adb -s {serial} shell {action}")
+        }
+    };
+
+    if is_dry_run() {
+        for (p, actions) in &batch {
+            for action in actions {
+                info!("[DRY RUN] [{}] {}", p.removal, command(action));
+            }
+        }
+        return batch.into_iter().map(|(p, _)| Ok(p)).collect();
+    }
+
+    // a phone in the middle of a boot isn't listed yet
+    if let Err(err) = backend().wait_for_device(serial, BATCH_DEVICE_TIMEOUT) {
+        let wait_command = if serial.is_empty() {
+            "adb wait-for-device".to_string()
+        } else {
+            format!("adb -s {serial} wait-for-device")
+        };
+        return batch
+            .into_iter()
+            .map(|_| {
+                Err(AdbError::DeviceOffline {
+                    error: err.to_string(),
+                    command: wait_command.clone(),
+                    exit_code: None,
+                })
+            })
+            .collect();
+    }
+
+    let all_actions: Vec<String> = batch.iter().flat_map(|(_, a)| a.clone()).collect();
+    let mut outputs = match backend().shell(serial, &batch_script(&all_actions)) {
+        Ok(out) => parse_batch_output(&out, all_actions.len()),
+        // the whole call failed, e.g. the device is gone
+        Err(err) => vec![Err(err); all_actions.len()],
+    }
+    .into_iter();
+
+    batch
+        .into_iter()
+        .map(|(p, actions)| {
+            let mut result = Ok(());
+            for (j, (action, output)) in actions.iter().zip(outputs.by_ref()).enumerate() {
+                // `2>&1` merged both streams
+                let output = output.map(|(exit_code, stdout)| AdbOutcome {
+                    command: command(action),
+                    stdout,
+                    stderr: String::new(),
+                    exit_code: Some(exit_code),
+                });
+                let checked = check_action_output(&p.removal, action, command(action), output);
+                match checked {
+                    Err(err) if j == 0 => result = Err(err),
+                    Err(err) => warn!("{}", err.message()),
+                    Ok(()) => {}
+                }
+            }
+            result.map(|()| p)
+        })
+        .collect()
+}
+
+/// Current state of each of `packages`, in the same order.
+///
+/// Costs 2 `pm list packages` calls, whatever the number of packages.
+/// Packages neither enabled nor disabled are considered uninstalled,
+/// see [`leftover_apks`] for the ones that aren't entirely.
+pub fn verify_package_state(
+    serial: &str,
+    user_id: Option<u16>,
+    packages: &[String],
+) -> Result<Vec<PackageState>, String> {
+    let list = |flag| -> Result<HashSet<String>, String> {
+        Ok(backend()
+            .list_packages_sys(serial, Some(flag), user_id)?
+            .into_iter()
+            .collect())
+    };
+    let enabled = list(PmListPacksFlag::OnlyEnabled)?;
+    let disabled = list(PmListPacksFlag::OnlyDisabled)?;
+    Ok(packages
+        .iter()
+        .map(|p| {
+            if enabled.contains(p) {
+                PackageState::Enabled
+            } else if disabled.contains(p) {
+                PackageState::Disabled
+            } else {
+                PackageState::Uninstalled
+            }
+        })
+        .collect())
+}
+
+/// Which of the `uninstalled` packages still have APKs listed by `pm path`,
+/// 1 `adb shell` call per [`BATCH_SIZE`] packages:
+/// some APKs of a split package can survive its uninstall.
+pub fn leftover_apks(
+    serial: &str,
+    user_id: Option<u16>,
+    uninstalled: &[String],
+) -> Result<HashSet<String>, String> {
+    let actions: Vec<String> = uninstalled
+        .iter()
+        .map(|p| match user_id {
+            Some(id) => format!("pm path --user {id} {p}"),
+            None => format!("pm path {p}"),
+        })
+        .collect();
+    Ok(uninstalled
+        .iter()
+        .zip(shell_batches(serial, &actions)?)
+        .filter_map(|(p, result)| {
+            // `pm path` fails for packages that are entirely gone
+            let (_, out) = result.ok()?;
+            let paths = parse_pm_path(&out);
+            if paths.is_empty() {
+                return None;
             }
-            Err(AdbError::Generic(err))
+            warn!(
+                "{p} isn't fully uninstalled, APKs remain: {}",
+                paths.join(", ")
+            );
+            Some(p.clone())
+        })
+        .collect())
+}
+
+/// `COMPONENT_ENABLED_STATE_DISABLED_UNTIL_USED` of Android's `PackageManager`:
+/// disabled by the system (not the user), until an app needs the package
+pub const DISABLED_UNTIL_USED: u8 = 4;
+
+/// The `enabled=` code of the package for `user_id`, from its `dumpsys package`:
+/// ```txt
+///     User 0: ceDataInode=4452 installed=true hidden=false suspended=false stopped=true notLaunched=false enabled=4 instant=false virtual=false
+/// ```
+#[must_use]
+pub fn parse_enabled_state(dumpsys: &str, user_id: u16) -> Option<u8> {
+    let prefix = format!("User {user_id}:");
+    dumpsys
+        .lines()
+        .map(str::trim)
+        .find(|l| l.starts_with(&prefix))?
+        .split_whitespace()
+        .find_map(|w| w.strip_prefix("enabled="))?
+        .parse()
+        .ok()
+}
+
+/// The [`DISABLED_UNTIL_USED`] packages of every user, by user id,
+/// from a single `dumpsys package` of the device.
+/// `pm list packages -d` doesn't tell them apart from the ones disabled by the user.
+pub fn disabled_until_used(serial: &str) -> HashMap<u16, HashSet<String>> {
+    // only the lines `parse_disabled_until_used` needs, instead of megabytes of dump
+    let dump = "dumpsys package packages | grep -E '^Hidden|Package \\[|User [0-9]+:'";
+    match backend().shell(serial, dump) {
+        Ok(out) => parse_disabled_until_used(&out),
+        Err(err) => {
+            warn!("Couldn't tell the packages disabled until used: {err}");
+            HashMap::new()
+        }
+    }
+}
+
+/// The [`DISABLED_UNTIL_USED`] packages of every user, from the `Packages:` section
+/// of `dumpsys package`, see [`parse_enabled_state`]:
+/// ```txt
+///   Package [com.android.bips] (d3b6b7e):
+///     User 0: ceDataInode=4452 installed=true hidden=false suspended=false stopped=true notLaunched=false enabled=4 instant=false virtual=false
+/// ```
+#[must_use]
+pub fn parse_disabled_until_used(dumpsys: &str) -> HashMap<u16, HashSet<String>> {
+    let mut until_used: HashMap<u16, HashSet<String>> = HashMap::new();
+    let mut package = None;
+    for line in dumpsys.lines().map(str::trim) {
+        // the previous versions of updated system apps
+        if line.starts_with("Hidden system packages") {
+            break;
+        }
+        if let Some(rest) = line.strip_prefix("Package [") {
+            package = rest.split_once(']').map(|(name, _)| name);
+            continue;
+        }
+        let (Some(name), Some(user)) = (
+            package,
+            line.strip_prefix("User ")
+                .and_then(|l| l.split_once(':'))
+                .and_then(|(id, _)| id.parse().ok()),
+        ) else {
+            continue;
+        };
+        if parse_enabled_state(line, user) == Some(DISABLED_UNTIL_USED) {
+            until_used.entry(user).or_default().insert(name.to_string());
         }
     }
+    until_used
+}
+
+/// The APKs listed by `pm path`, 1 `package:<path>` line each.
+/// Split packages have several: the base APK, then its splits.
+#[must_use]
+pub fn parse_pm_path(output: &str) -> Vec<&str> {
+    output
+        .lines()
+        .filter_map(|l| l.trim().strip_prefix("package:"))
+        .collect()
+}
+
+/// `(total, free)` bytes of the `/data` partition, see [`parse_df`]
+pub fn get_storage_info(serial: &str) -> Option<(u64, u64)> {
+    match backend().df_data(serial) {
+        Ok(out) => parse_df(&out),
+        Err(err) => {
+            warn!("Couldn't get the storage info: {err}");
+            None
+        }
+    }
+}
+
+/// Parse the `(total, free)` bytes out of `df /data`, which comes in several flavors:
+/// ```txt
+/// Filesystem      1K-blocks     Used Available Use% Mounted on
+/// /dev/block/dm-5 115249252 31208740  83909440  28% /data
+/// ```
+/// or, on older devices (toolbox), human-readable:
+/// ```txt
+/// Filesystem             Size   Used   Free   Blksize
+/// /data                 12.5G   4.3G   8.2G   4096
+/// ```
+/// Long filesystem names can push the numbers onto the next line.
+#[must_use]
+pub fn parse_df(out: &str) -> Option<(u64, u64)> {
+    let mut lines = out.lines().skip_while(|l| !l.starts_with("Filesystem"));
+    let header = lines.next()?;
+    // "1K-blocks", "512-blocks"
+    let block_size = header.split_whitespace().find_map(|col| {
+        let size = col.strip_suffix("-blocks")?;
+        match size.strip_suffix('K') {
+            Some(k) => k.parse::<u64>().ok().map(|k| k * 1024),
+            None => size.parse().ok(),
+        }
+    });
+    // filesystem, total, used, free
+    let values: Vec<&str> = lines.flat_map(str::split_whitespace).take(4).collect();
+    let parse = |v: &str| match block_size {
+        Some(block_size) => v.parse::<u64>().ok().map(|blocks| blocks * block_size),
+        None => parse_human_size(v),
+    };
+    Some((parse(values.get(1)?)?, parse(values.get(3)?)?))
+}
+
+/// "12.5G" -> bytes. Units are powers of 1024, as in `df -h`.
+fn parse_human_size(s: &str) -> Option<u64> {
+    let (number, unit) = s.split_at(s.find(|c: char| c.is_ascii_alphabetic()).unwrap_or(s.len()));
+    let exp = match unit.trim_end_matches('B') {
+        "" => 0,
+        "K" => 1,
+        "M" => 2,
+        "G" => 3,
+        "T" => 4,
+        _ => return None,
+    };
+    let number: f64 = number.parse().ok()?;
+    #[expect(
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss,
+        reason = "sizes are positive and way below 2^53"
+    )]
+    Some((number * 1024_f64.powi(exp)) as u64)
 }
 
 /// If `None`, returns an empty String, not " --user 0"
@@ -167,11 +800,17 @@ impl From<&PackageRow> for CorePackage {
     }
 }
 
+/// `selected_user` set to `None` omits the `--user` flag,
+/// letting the device pick its default scope.
+///
+/// `keep_data` uninstalls with `pm uninstall -k` where it's available,
+/// see [`crate::core::config::DeviceSettings::keep_data`].
 pub fn apply_pkg_state_commands(
     package: &CorePackage,
     wanted_state: PackageState,
-    selected_user: User,
+    selected_user: Option<User>,
     phone: &Phone,
+    keep_data: bool,
 ) -> Vec<String> {
     // https://github.com/Universal-Debloater-Alliance/universal-android-debloater/wiki/ADB-reference
     // ALWAYS PUT THE COMMAND THAT CHANGES THE PACKAGE STATE FIRST!
@@ -182,7 +821,13 @@ pub fn apply_pkg_state_commands(
                 i if i >= 23 => vec!["cmd package install-existing"],
                 21 | 22 => vec!["pm unhide"],
                 19 | 20 => vec!["pm unblock", PM_CLEAR_PACK],
-                _ => unreachable!("already prevented by the GUI"),
+                sdk => {
+                    warn!(
+                        "Cannot restore {} on SDK {sdk}: no known command",
+                        package.name
+                    );
+                    vec![]
+                }
             },
             _ => vec![],
         },
@@ -195,7 +840,12 @@ pub fn apply_pkg_state_commands(
         },
         PackageState::Uninstalled => match package.state {
             PackageState::Enabled | PackageState::Disabled => match phone.android_sdk {
-                sdk if sdk >= 23 => vec!["pm uninstall"], // > Android Marshmallow (6.0)
+                // > Android Marshmallow (6.0)
+                sdk if sdk >= 23 => vec![if keep_data {
+                    "pm uninstall -k"
+                } else {
+                    "pm uninstall"
+                }],
                 21 | 22 => vec!["pm hide", PM_CLEAR_PACK], // Android Lollipop (5.x)
                 _ => vec!["pm block", PM_CLEAR_PACK], // Disable mode is unavailable on older devices because the specific ADB commands need root
             },
@@ -204,7 +854,7 @@ pub fn apply_pkg_state_commands(
         PackageState::All => vec![],
     }; // this should be a `tinyvec`, as `len <= 4`
 
-    let user = supports_multi_user(phone).then_some(selected_user);
+    let user = selected_user.filter(|_| supports_multi_user(phone));
     request_builder(&commands, &package.name, user)
 }
 
@@ -223,9 +873,8 @@ pub fn request_builder(commands: &[&str], package: &str, user: Option<User>) ->
 ///
 /// If `serial` is empty, it lets ADB choose the default device.
 pub fn get_device_model(serial: &str) -> String {
-    AdbCommand::new()
-        .shell(serial)
-        .getprop("ro.product.model")
+    backend()
+        .getprop(serial, "ro.product.model")
         .unwrap_or_else(|err| {
             eprintln!("ERROR: {err}");
             error!("{err}");
@@ -241,9 +890,8 @@ pub fn get_device_model(serial: &str) -> String {
 ///
 /// If `serial` is empty, it lets ADB choose the default device.
 pub fn get_device_brand(serial: &str) -> String {
-    AdbCommand::new()
-        .shell(serial)
-        .getprop("ro.product.brand")
+    backend()
+        .getprop(serial, "ro.product.brand")
         // `trim` is just-in-case
         .map(|s| s.trim().to_string())
         .unwrap_or_default()
@@ -254,9 +902,8 @@ pub fn get_device_brand(serial: &str) -> String {
 ///
 /// If `device_serial` is empty, it lets ADB choose the default device.
 pub fn get_android_sdk(device_serial: &str) -> u8 {
-    AdbCommand::new()
-        .shell(device_serial)
-        .getprop("ro.build.version.sdk")
+    backend()
+        .getprop(device_serial, "ro.build.version.sdk")
         .map_or(0, |sdk| {
             sdk.parse().expect("SDK version numeral must be valid")
         })
@@ -279,57 +926,162 @@ pub const fn supports_multi_user(dev: &Phone) -> bool {
     dev.android_sdk >= MULTI_USER_SDK
 }
 
+/// Check if `pm` output means the shell isn't allowed to access a user,
+/// e.g. `java.lang.SecurityException: Shell does not have permission to access user 150`
+#[must_use]
+pub fn is_permission_denied(err: &str) -> bool {
+    err.contains("SecurityException")
+        || err.contains("does not have permission")
+        || err.contains("Permission denied")
+}
+
+/// Short explanation of the `pm` error that makes a user protected, for the UI
+#[must_use]
+pub fn protection_reason(err: &str) -> String {
+    let lower = err.to_lowercase();
+    if is_permission_denied(err) {
+        "permission denied (e.g. a profile managed by an organization)".to_string()
+    } else if lower.contains("not running") || lower.contains("stopped") {
+        "user not running".to_string()
+    } else if lower.contains("restricted") {
+        "restricted user".to_string()
+    } else {
+        // the exception is on the last line of Java stack traces
+        err.lines()
+            .map(str::trim)
+            .rfind(|l| !l.is_empty())
+            .unwrap_or("unknown error")
+            .to_string()
+    }
+}
+
+/// Decide if a user is protected, from the result of listing its packages.
+/// `list` is retried on transient errors (anything but [`is_permission_denied`]).
+/// A user that keeps failing is assumed protected, as acting on it would fail anyway.
+///
+/// Returns `Err` with the [`protection_reason`] if it's protected.
+fn protection_from<T>(mut list: impl FnMut() -> Result<T, String>) -> Result<(), String> {
+    retry(Fixed::from_millis(300).take(2), || match list() {
+        Ok(_) => OperationResult::Ok(()),
+        Err(e) if is_permission_denied(&e) => OperationResult::Err(e),
+        Err(e) => OperationResult::Retry(e),
+    })
+    .map_err(|e| {
+        let err = e.error;
+        if !is_permission_denied(&err) {
+            warn!("Assuming protected user after repeated errors: {err}");
+        }
+        protection_reason(&err)
+    })
+}
+
 /// Check if a `user_id` is protected on a device by trying
 /// to list associated packages.
+/// Returns why it is, see [`protection_reason`].
 ///
 /// If `device_serial` is empty, it lets ADB choose the default device.
-pub fn is_protected_user<S: AsRef<str>>(user_id: u16, device_serial: S) -> bool {
-    AdbCommand::new()
-        .shell(device_serial)
-        .pm()
-        .list_packages_sys(None, Some(user_id))
-        .is_err()
+pub fn is_protected_user<S: AsRef<str>>(user_id: u16, device_serial: S) -> Result<(), String> {
+    protection_from(|| backend().list_packages_sys(device_serial.as_ref(), None, Some(user_id)))
 }
 
 pub fn list_users_idx_prot(device_serial: &str) -> Vec<User> {
-    AdbCommand::new()
-        .shell(device_serial)
-        .pm()
-        .list_users()
+    backend()
+        .list_users(device_serial)
         .map(|out| {
-            out.into_iter()
-                .enumerate()
-                .map(|(i, user)| {
-                    let id = user.get_id();
-                    User {
-                        id,
-                        index: i,
-                        protected: is_protected_user(id, device_serial),
-                    }
-                })
-                .collect()
+            index_users(
+                out.into_iter()
+                    .map(|user| {
+                        let id = user.get_id();
+                        let protected_reason = is_protected_user(id, device_serial).err();
+                        User {
+                            id,
+                            index: 0,
+                            protected: protected_reason.is_some(),
+                            protected_reason,
+                            name: Some(user.get_name())
+                                .filter(|n| !n.is_empty())
+                                .map(String::from),
+                            kind: Some(UserKind::from_flags(id, user.get_flags())),
+                        }
+                    })
+                    .collect(),
+            )
         })
         .unwrap_or_default()
 }
 
+/// Sorted by id, whatever order `pm list users` gave them in.
+/// `index` is set afterwards, as the packages are loaded in this order.
+fn index_users(mut users: Vec<User>) -> Vec<User> {
+    users.sort();
+    for (i, user) in users.iter_mut().enumerate() {
+        user.index = i;
+    }
+    users
+}
+
+/// `pm` reports some failures as a success, followed by an `Error:` line
+fn check_pm_output(out: String) -> Result<String, String> {
+    if out.lines().any(|ln| ln.starts_with("Error")) {
+        Err(out)
+    } else {
+        Ok(out)
+    }
+}
+
+/// Creates a user named `name`
+pub async fn create_user(device_serial: String, name: String) -> Result<String, String> {
+    if is_dry_run() {
+        info!("[DRY RUN] adb -s {device_serial} shell pm create-user {name}");
+        return Ok(String::new());
+    }
+    backend()
+        .create_user(&device_serial, &name)
+        .and_then(check_pm_output)
+}
+
+/// Removes `user`, with all its data. Refused if it's not [`User::is_removable`].
+pub async fn remove_user(device_serial: String, user: User) -> Result<String, String> {
+    if !user.is_removable() {
+        return Err(format!("{user} can't be removed"));
+    }
+    if is_dry_run() {
+        info!(
+            "[DRY RUN] adb -s {device_serial} shell pm remove-user {}",
+            user.id
+        );
+        return Ok(String::new());
+    }
+    backend()
+        .remove_user(&device_serial, user.id)
+        .and_then(check_pm_output)
+}
+
 /// This matches serials (`getprop ro.serialno`)
 /// that are authorized by the user.
 pub async fn get_devices_list() -> Vec<Phone> {
     retry(
         Fixed::from_millis(500).take(if cfg!(debug_assertions) { 3 } else { 120 }),
-        || match AdbCommand::new().devices() {
+        || match backend().devices_long() {
             Ok(devices) => {
                 let mut device_list: Vec<Phone> = vec![];
-                if devices.iter().all(|(_, stat)| stat != "device") {
+                if devices.iter().all(|d| d.status != "device") {
                     return OperationResult::Retry(vec![]);
                 }
                 for device in devices {
-                    let serial = &device.0;
+                    let serial = &device.serial;
+                    let android_sdk = get_android_sdk(serial);
+                    // see `revalidate_device_names`
+                    let cached = cached_device_name(&mut device_names(), serial, android_sdk);
+                    let model = cached.unwrap_or_else(|| fetch_device_name(serial, android_sdk));
                     device_list.push(Phone {
-                        model: format!("{} {}", get_device_brand(serial), get_device_model(serial)),
-                        android_sdk: get_android_sdk(serial),
+                        model,
+                        android_sdk,
                         user_list: list_users_idx_prot(serial),
                         adb_id: serial.to_string(),
+                        transport: device.transport,
+                        backup: None,
+                        favorite: false,
                     });
                 }
                 OperationResult::Ok(device_list)
@@ -344,9 +1096,704 @@ pub async fn get_devices_list() -> Vec<Phone> {
     .unwrap_or_default()
 }
 
+/// Flag the `favorites` serials, and list them first.
+/// The other devices keep their order.
+pub fn pin_favorites(devices: &mut [Phone], favorites: &HashSet<&str>) {
+    for device in devices.iter_mut() {
+        device.favorite = favorites.contains(device.adb_id.as_str());
+    }
+    devices.sort_by_key(|d| !d.favorite);
+}
+
+/// Names of the devices seen during this session, by serial,
+/// so reconnecting one shows it without querying its brand and model first
+static DEVICE_NAMES: LazyLock<Mutex<HashMap<String, CachedName>>> = LazyLock::new(Mutex::default);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CachedName {
+    sdk: u8,
+    name: String,
+    /// Shown from the cache since it was read, see [`revalidate_device_names`]
+    stale: bool,
+}
+
+fn device_names() -> MutexGuard<'static, HashMap<String, CachedName>> {
+    DEVICE_NAMES.lock().expect("device names lock poisoned")
+}
+
+/// The name of `serial` from `cache`, which then needs to be re-validated.
+///
+/// A different SDK (e.g. after an OS update) invalidates the entry, as the ROM may have changed.
+fn cached_device_name(
+    cache: &mut HashMap<String, CachedName>,
+    serial: &str,
+    sdk: u8,
+) -> Option<String> {
+    let cached = cache.get_mut(serial).filter(|c| c.sdk == sdk && sdk != 0)?;
+    cached.stale = true;
+    Some(cached.name.clone())
+}
+
+/// Reads the brand and model of `serial`, and caches the resulting name.
+/// The lock isn't held meanwhile, as `getprop` may block for a while.
+fn fetch_device_name(serial: &str, sdk: u8) -> String {
+    let name = format_device_name(&get_device_brand(serial), &get_device_model(serial), serial);
+    device_names().insert(
+        serial.to_string(),
+        CachedName {
+            sdk,
+            name: name.clone(),
+            stale: false,
+        },
+    );
+    name
+}
+
+/// Reads again the names of the `devices` that [`get_devices_list`] took from the cache.
+/// Returns the `(serial, name)` of the ones that changed meanwhile.
+pub async fn revalidate_device_names(devices: Vec<Phone>) -> Vec<(String, String)> {
+    devices
+        .into_iter()
+        .filter(|d| device_names().get(&d.adb_id).is_some_and(|c| c.stale))
+        .filter_map(|d| {
+            let name = fetch_device_name(&d.adb_id, d.android_sdk);
+            (name != d.model).then_some((d.adb_id, name))
+        })
+        .collect()
+}
+
+/// `"{brand} {model}"`, skipping empty parts. `serial` if both are empty.
+///
+/// Emulators report build names like `sdk_gphone64_x86_64` (or nothing while booting):
+/// they're named `Android Emulator ({serial})` instead, to tell them apart.
+#[must_use]
+pub fn format_device_name(brand: &str, model: &str, serial: &str) -> String {
+    if serial.starts_with("emulator-") && is_generic_emulator_name(brand.trim(), model.trim()) {
+        return format!("{EMULATOR_NAME} ({serial})");
+    }
+    let name = [brand.trim(), model.trim()]
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if name.is_empty() {
+        serial.to_string()
+    } else {
+        name
+    }
+}
+
+const EMULATOR_NAME: &str = "Android Emulator";
+
+/// The brand and model of the stock emulator images (or none),
+/// as opposed to e.g. a Genymotion device naming the phone it emulates
+fn is_generic_emulator_name(brand: &str, model: &str) -> bool {
+    model.is_empty()
+        || model.starts_with("sdk_")
+        || model.starts_with("Android SDK built for")
+        || brand.eq_ignore_ascii_case("generic")
+}
+
+/// Status of a device, as listed by `adb devices`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DeviceStatus {
+    /// Usable
+    Device,
+    /// USB debugging wasn't accepted on the device
+    Unauthorized,
+    Offline,
+    /// The host doesn't let ADB access the device: missing driver (Windows) or udev rule (Linux)
+    NoPermissions,
+    Other(String),
+}
+
+impl DeviceStatus {
+    #[must_use]
+    pub fn parse(status: &str) -> Self {
+        match status.trim() {
+            "device" => Self::Device,
+            "unauthorized" => Self::Unauthorized,
+            "offline" => Self::Offline,
+            // "no permissions (missing udev rules? ...); see [http://...]"
+            s if s.starts_with("no permissions") => Self::NoPermissions,
+            s => Self::Other(s.to_string()),
+        }
+    }
+}
+
+/// `(serial, status)` of everything ADB lists, usable or not.
+/// Empty if ADB can't be run.
+pub async fn get_devices_status() -> Vec<(String, DeviceStatus)> {
+    backend()
+        .devices()
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(serial, status)| (serial, DeviceStatus::parse(&status)))
+        .collect()
+}
+
+/// Polls ADB until `serial` is usable again after a reboot, or `timeout` is elapsed.
+///
+/// The device may still be listed right after `adb reboot`,
+/// so it has to go away first (or not, if it rebooted really fast).
+pub async fn wait_for_reconnect(serial: String, timeout: Duration) -> bool {
+    const POLL: Duration = Duration::from_secs(1);
+    let start = Instant::now();
+    let status = || {
+        backend()
+            .devices()
+            .unwrap_or_default()
+            .into_iter()
+            .find(|(s, _)| *s == serial)
+            .map(|(_, status)| DeviceStatus::parse(&status))
+    };
+    let mut went_away = false;
+    while start.elapsed() < timeout {
+        match status() {
+            Some(DeviceStatus::Device)
+                if went_away || start.elapsed() > Duration::from_secs(15) =>
+            {
+                return true;
+            }
+            Some(DeviceStatus::Device) => (),
+            _ => went_away = true,
+        }
+        std::thread::sleep(POLL);
+    }
+    false
+}
+
+/// Whether `adb` runs, but isn't the real one.
+/// Some Windows users have a stub `adb.exe` which only prints an ad or its usage.
+pub async fn is_fake_adb() -> bool {
+    match backend().version() {
+        Ok(out) if !is_genuine_version(&out) => {
+            warn!("adb doesn't look like the Android Platform Tools one: {out}");
+            true
+        }
+        _ => false,
+    }
+}
+
 pub async fn initial_load() -> bool {
-    match AdbCommand::new().devices() {
+    match backend().devices() {
         Ok(_devices) => true,
         Err(_err) => false,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACK: &str = "com.example.app";
+
+    fn phone(android_sdk: u8) -> Phone {
+        Phone {
+            android_sdk,
+            ..Phone::default()
+        }
+    }
+
+    /// Expected commands (without flags nor package) for every
+    /// `(current state, wanted state)` pair, by SDK range
+    fn expected(sdk: u8, current: PackageState, wanted: PackageState) -> Vec<&'static str> {
+        use PackageState::{Disabled, Enabled, Uninstalled};
+        match (current, wanted) {
+            (Disabled, Enabled) => vec!["pm enable"],
+            (Uninstalled, Enabled) => match sdk {
+                23.. => vec!["cmd package install-existing"],
+                21 | 22 => vec!["pm unhide"],
+                19 | 20 => vec!["pm unblock", "pm clear"],
+                _ => vec![],
+            },
+            (Enabled | Uninstalled, Disabled) => match sdk {
+                23.. => vec!["pm disable-user", "am force-stop", "pm clear"],
+                _ => vec![],
+            },
+            (Enabled | Disabled, Uninstalled) => match sdk {
+                23.. => vec!["pm uninstall"],
+                21 | 22 => vec!["pm hide", "pm clear"],
+                _ => vec!["pm block", "pm clear"],
+            },
+            _ => vec![],
+        }
+    }
+
+    #[test]
+    fn pkg_state_commands_matrix() {
+        use PackageState::{All, Disabled, Enabled, Uninstalled};
+        let user = User {
+            id: 10,
+            index: 1,
+            protected: false,
+            ..User::default()
+        };
+        for sdk in 19..=34 {
+            let phone = phone(sdk);
+            let flag = if sdk >= MULTI_USER_SDK {
+                " --user 10"
+            } else {
+                ""
+            };
+            for current in [Enabled, Disabled, Uninstalled] {
+                let package = CorePackage {
+                    name: PACK.to_string(),
+                    state: current,
+                };
+                for wanted in [Enabled, Disabled, Uninstalled, All] {
+                    let want: Vec<String> = expected(sdk, current, wanted)
+                        .into_iter()
+                        .map(|c| format!("{c}{flag} {PACK}"))
+                        .collect();
+                    assert_eq!(
+                        apply_pkg_state_commands(
+                            &package,
+                            wanted,
+                            Some(user.clone()),
+                            &phone,
+                            false
+                        ),
+                        want,
+                        "SDK {sdk}: {current:?} -> {wanted:?}"
+                    );
+                }
+            }
+        }
+    }
+
+    /// Scripted results of `pm list packages`, in call order
+    fn scripted(results: Vec<Result<(), &str>>) -> impl FnMut() -> Result<(), String> {
+        let mut results = results.into_iter();
+        move || {
+            results
+                .next()
+                .expect("no more scripted results")
+                .map_err(String::from)
+        }
+    }
+
+    #[test]
+    fn protected_user_permission_denied() {
+        const DENIED: &str = "Exception occurred while executing 'list':\n\
+            java.lang.SecurityException: Shell does not have permission to access user 150";
+        assert!(is_permission_denied(DENIED));
+        assert_eq!(
+            protection_from(scripted(vec![Err(DENIED)])),
+            Err("permission denied (e.g. a profile managed by an organization)".to_string())
+        );
+    }
+
+    #[test]
+    fn protected_user_reasons() {
+        assert_eq!(
+            protection_reason("Error: user 11 is not running"),
+            "user not running"
+        );
+        assert_eq!(
+            protection_reason("Shell can't access restricted profile 12"),
+            "restricted user"
+        );
+        assert_eq!(
+            protection_reason("Exception occurred while executing 'list':\nUnknown user 13\n"),
+            "Unknown user 13"
+        );
+    }
+
+    #[test]
+    fn protected_user_transient_error() {
+        const TRANSIENT: &str = "cmd: Can't find service: package";
+        assert!(!is_permission_denied(TRANSIENT));
+        assert!(protection_from(scripted(vec![Err(TRANSIENT), Ok(())])).is_ok());
+        assert!(protection_from(scripted(vec![Ok(())])).is_ok());
+        // still failing after retries
+        assert_eq!(
+            protection_from(scripted(vec![
+                Err(TRANSIENT),
+                Err(TRANSIENT),
+                Err(TRANSIENT)
+            ])),
+            Err(TRANSIENT.to_string())
+        );
+    }
+
+    #[test]
+    fn batch_output() {
+        let actions = ["pm uninstall a".to_string(), "pm uninstall b".to_string()];
+        assert_eq!(
+            batch_script(&actions),
+            format!(
+                "pm uninstall a 2>&1; echo {BATCH_MARKER} $?; pm uninstall b 2>&1; echo {BATCH_MARKER} $?"
+            )
+        );
+
+        let out =
+            format!("Success\n{BATCH_MARKER} 0\nFailure [not installed for 0]\n{BATCH_MARKER} 1");
+        assert_eq!(
+            parse_batch_output(&out, 2),
+            vec![
+                Ok((0, "Success".to_string())),
+                Ok((1, "Failure [not installed for 0]".to_string()))
+            ]
+        );
+        // no trailing newline, and cut short
+        let results = parse_batch_output(&format!("Success{BATCH_MARKER} 0"), 2);
+        assert_eq!(results[0], Ok((0, "Success".to_string())));
+        assert!(results[1].is_err());
+    }
+
+    /// Times 100 separate `adb shell` calls against a single [`batch_script`] of them,
+    /// with the read-only `pm path android`:
+    /// `UAD_BENCH_SERIAL=<serial> cargo test batch_speedup -- --ignored --nocapture`
+    #[test]
+    #[ignore = "needs a device"]
+    fn batch_speedup() {
+        use crate::core::backend::{AdbBackend, AdbCli};
+
+        let Ok(serial) = std::env::var("UAD_BENCH_SERIAL") else {
+            eprintln!("UAD_BENCH_SERIAL isn't set, skipping");
+            return;
+        };
+        let actions = vec!["pm path android".to_string(); 100];
+
+        let start = Instant::now();
+        for action in &actions {
+            AdbCli.shell(&serial, action).expect("read-only command");
+        }
+        let separate = start.elapsed();
+
+        let batch_start = Instant::now();
+        let out = AdbCli
+            .shell(&serial, &batch_script(&actions))
+            .expect("read-only commands");
+        let batched = batch_start.elapsed();
+
+        assert!(
+            parse_batch_output(&out, actions.len())
+                .iter()
+                .all(|r| matches!(r, Ok((0, _))))
+        );
+        eprintln!(
+            "{} commands: {separate:?} separately, {batched:?} batched ({:.1}x)",
+            actions.len(),
+            separate.as_secs_f64() / batched.as_secs_f64()
+        );
+        assert!(batched < separate);
+    }
+
+    #[test]
+    fn device_name_cache() {
+        let mut cache = HashMap::from([(
+            "serial".to_string(),
+            CachedName {
+                sdk: 33,
+                name: "Pixel 6".to_string(),
+                stale: false,
+            },
+        )]);
+        assert_eq!(cached_device_name(&mut cache, "other", 33), None);
+        assert_eq!(
+            cached_device_name(&mut cache, "serial", 33).as_deref(),
+            Some("Pixel 6")
+        );
+        assert!(cache["serial"].stale);
+        // OS update
+        assert_eq!(cached_device_name(&mut cache, "serial", 34), None);
+        // SDK couldn't be read: don't trust the cache
+        cache.get_mut("serial").expect("cached").sdk = 0;
+        assert_eq!(cached_device_name(&mut cache, "serial", 0), None);
+    }
+
+    #[test]
+    fn device_names() {
+        assert_eq!(
+            format_device_name("Google", "Pixel 6", "abc"),
+            "Google Pixel 6"
+        );
+        assert_eq!(format_device_name("", "Pixel 6", "abc"), "Pixel 6");
+        assert_eq!(format_device_name("Google", " ", "abc"), "Google");
+        assert_eq!(format_device_name(" ", "", "abc"), "abc");
+        assert_eq!(format_device_name("", "", ""), "");
+        assert_eq!(
+            format_device_name("google", "sdk_gphone64_x86_64", "emulator-5554"),
+            "Android Emulator (emulator-5554)"
+        );
+        assert_eq!(
+            format_device_name("", "", "emulator-5556"),
+            "Android Emulator (emulator-5556)"
+        );
+        assert_eq!(
+            format_device_name("Google", "Pixel 6", "emulator-5554"),
+            "Google Pixel 6"
+        );
+    }
+
+    #[test]
+    fn user_labels() {
+        let user = |id, name: &str, flags| User {
+            id,
+            name: Some(name.to_string()),
+            kind: Some(UserKind::from_flags(id, flags)),
+            ..User::default()
+        };
+        assert_eq!(user(0, "Owner", 0xc13).to_string(), "user 0 (Owner)");
+        assert_eq!(
+            user(10, "Work profile", 0x1030).to_string(),
+            "user 10 (Work)"
+        );
+        assert_eq!(
+            user(150, "Secure Folder", 0x1090).to_string(),
+            "user 150 (Secure Folder)"
+        );
+        assert_eq!(user(11, "Guest", 0x414).to_string(), "user 11 (Guest)");
+        assert_eq!(user(12, "Kids", 0x410).to_string(), "user 12 (Kids)");
+        assert!(!user(0, "Owner", 0xc13).is_removable());
+        assert!(user(10, "Work profile", 0x1030).is_removable());
+        assert!(
+            !User {
+                protected: true,
+                ..user(150, "Secure Folder", 0x1090)
+            }
+            .is_removable()
+        );
+        // from a backup
+        let bare = User {
+            id: 10,
+            ..User::default()
+        };
+        assert_eq!(bare.to_string(), "user 10");
+        // the ID is the key
+        assert_eq!(bare, user(10, "Work profile", 0x1030));
+    }
+
+    #[test]
+    fn device_statuses() {
+        assert_eq!(DeviceStatus::parse("device"), DeviceStatus::Device);
+        assert_eq!(
+            DeviceStatus::parse("unauthorized"),
+            DeviceStatus::Unauthorized
+        );
+        assert_eq!(
+            DeviceStatus::parse(
+                "no permissions (missing udev rules? user is in the plugdev group); \
+                see [http://developer.android.com/tools/device.html]"
+            ),
+            DeviceStatus::NoPermissions
+        );
+        assert_eq!(
+            DeviceStatus::parse("recovery"),
+            DeviceStatus::Other("recovery".to_string())
+        );
+    }
+
+    #[test]
+    fn df_outputs() {
+        assert_eq!(
+            parse_df(
+                "Filesystem      1K-blocks     Used Available Use% Mounted on\n\
+                /dev/block/dm-5 115249252 31208740  83909440  28% /data"
+            ),
+            Some((115_249_252 * 1024, 83_909_440 * 1024))
+        );
+        // wrapped
+        assert_eq!(
+            parse_df(
+                "Filesystem           1K-blocks      Used Available Use% Mounted on\n\
+                /dev/block/bootdevice/by-name/userdata\n\
+                \x20                    1000      600       400  60% /data"
+            ),
+            Some((1000 * 1024, 400 * 1024))
+        );
+        assert_eq!(
+            parse_df(
+                "Filesystem             Size   Used   Free   Blksize\n\
+                /data                 12.5G   4.3G   8.0M   4096"
+            ),
+            Some((13_421_772_800, 8 * 1024 * 1024))
+        );
+        assert_eq!(parse_df("df: /data: Permission denied"), None);
+        assert_eq!(parse_df("Filesystem Size Used Free\n/data ? ? ?"), None);
+    }
+
+    #[test]
+    fn device_lost_outputs() {
+        assert!(is_device_lost("adb: device offline"));
+        assert!(is_device_lost("error: device 'R58M123' not found"));
+        assert!(is_device_lost("adb: no devices/emulators found"));
+        assert!(!is_device_lost("Failure [not installed for 0]"));
+        assert!(!is_device_lost("Package com.example.app not found"));
+    }
+
+    #[test]
+    fn failure_variants() {
+        let output = |out: &str| {
+            let outcome = AdbOutcome {
+                command: "adb shell pm".to_string(),
+                stdout: out.to_string(),
+                stderr: String::new(),
+                exit_code: Some(1),
+            };
+            check_action_output("Recommended", "pm", "adb shell pm".to_string(), Ok(outcome))
+                .expect_err("failed")
+        };
+        assert!(matches!(
+            output("adb: device offline"),
+            AdbError::DeviceOffline { .. }
+        ));
+        let unauthorized = output("error: device unauthorized.");
+        assert!(matches!(unauthorized, AdbError::Unauthorized { .. }));
+        assert!(unauthorized.is_fatal());
+        let gone = output("Failure [not installed for 0]");
+        assert!(matches!(gone, AdbError::NotInstalled { .. }));
+        assert!(!gone.is_fatal());
+        assert_eq!(gone.message(), "Failure [not installed for 0]");
+        assert!(matches!(
+            output("Error: java.lang.SecurityException"),
+            AdbError::Command { .. }
+        ));
+    }
+
+    #[test]
+    fn pkg_state_commands_unscoped() {
+        let package = CorePackage {
+            name: PACK.to_string(),
+            state: PackageState::Enabled,
+        };
+        assert_eq!(
+            apply_pkg_state_commands(&package, PackageState::Uninstalled, None, &phone(34), false),
+            vec![format!("pm uninstall {PACK}")]
+        );
+    }
+
+    #[test]
+    fn pkg_state_commands_keep_data() {
+        let package = CorePackage {
+            name: PACK.to_string(),
+            state: PackageState::Enabled,
+        };
+        let user = User {
+            id: 10,
+            ..User::default()
+        };
+        assert_eq!(
+            apply_pkg_state_commands(
+                &package,
+                PackageState::Uninstalled,
+                Some(user.clone()),
+                &phone(34),
+                true
+            ),
+            vec![format!("pm uninstall -k --user 10 {PACK}")]
+        );
+        // `-k` only exists for `pm uninstall`
+        assert_eq!(
+            apply_pkg_state_commands(&package, PackageState::Uninstalled, None, &phone(21), true),
+            vec![format!("pm hide {PACK}"), format!("{PM_CLEAR_PACK} {PACK}")]
+        );
+        // only uninstalls are affected
+        assert_eq!(
+            apply_pkg_state_commands(&package, PackageState::Disabled, None, &phone(34), true)[0],
+            format!("pm disable-user {PACK}")
+        );
+    }
+
+    #[test]
+    fn pkg_state_commands_unknown_sdk_does_not_panic() {
+        let package = CorePackage {
+            name: PACK.to_string(),
+            state: PackageState::Uninstalled,
+        };
+        for sdk in [0, 18] {
+            assert!(
+                apply_pkg_state_commands(&package, PackageState::Enabled, None, &phone(sdk), false)
+                    .is_empty()
+            );
+        }
+    }
+
+    #[test]
+    fn users_sorted_by_id() {
+        let user = |id| User {
+            id,
+            ..User::default()
+        };
+        assert!(user(0) < user(10));
+        let users = index_users(vec![user(10), user(0), user(150)]);
+        assert_eq!(
+            users.iter().map(|u| (u.id, u.index)).collect::<Vec<_>>(),
+            [(0, 0), (10, 1), (150, 2)]
+        );
+    }
+
+    #[test]
+    fn favorites_first() {
+        let mut devices: Vec<Phone> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|serial| Phone {
+                adb_id: serial.to_string(),
+                ..Phone::default()
+            })
+            .collect();
+        pin_favorites(&mut devices, &HashSet::from(["c", "b"]));
+        assert_eq!(
+            devices
+                .iter()
+                .map(|d| (d.adb_id.as_str(), d.favorite))
+                .collect::<Vec<_>>(),
+            [("b", true), ("c", true), ("a", false), ("d", false)]
+        );
+        assert!(devices[0].to_string().starts_with('\u{2605}'));
+    }
+
+    #[test]
+    fn enabled_state_codes() {
+        const DUMPSYS: &str = "    User 0: ceDataInode=4452 installed=true hidden=false suspended=false stopped=true notLaunched=false enabled=4 instant=false virtual=false
+    User 10: ceDataInode=0 installed=true hidden=false suspended=false stopped=true notLaunched=true enabled=3 instant=false virtual=false";
+        assert_eq!(parse_enabled_state(DUMPSYS, 0), Some(DISABLED_UNTIL_USED));
+        assert_eq!(parse_enabled_state(DUMPSYS, 10), Some(3));
+        assert_eq!(parse_enabled_state(DUMPSYS, 11), None);
+        assert_eq!(parse_enabled_state("", 0), None);
+
+        let dumpsys = format!(
+            "Packages:\n  Package [com.android.bips] (d3b6b7e):\n{DUMPSYS}\n  Package [com.android.egg] (a1):\n    User 0: installed=true enabled=2\n    User 10: installed=true enabled=4\nHidden system packages:\n  Package [com.android.chrome] (b2):\n    User 0: installed=true enabled=4"
+        );
+        let until_used = parse_disabled_until_used(&dumpsys);
+        assert_eq!(
+            until_used[&0],
+            HashSet::from(["com.android.bips".to_string()])
+        );
+        assert_eq!(
+            until_used[&10],
+            HashSet::from(["com.android.egg".to_string()])
+        );
+        assert_eq!(until_used.len(), 2);
+        assert!(parse_disabled_until_used("").is_empty());
+    }
+
+    #[test]
+    fn split_apk_paths() {
+        let app = "/data/app/~~Xy1==/com.google.android.youtube-Ab2==";
+        let output = format!(
+            "package:{app}/base.apk\npackage:{app}/split_config.arm64_v8a.apk\r\npackage:{app}/split_config.en.apk\n"
+        );
+        assert_eq!(
+            parse_pm_path(&output),
+            [
+                format!("{app}/base.apk"),
+                format!("{app}/split_config.arm64_v8a.apk"),
+                format!("{app}/split_config.en.apk"),
+            ]
+        );
+        // fully uninstalled: nothing, or an error
+        assert!(parse_pm_path("").is_empty());
+        assert!(parse_pm_path("Error: unknown package").is_empty());
+
+        let batch =
+            format!("package:{app}/split_config.en.apk\n{BATCH_MARKER} 0\n{BATCH_MARKER} 1");
+        let paths: Vec<usize> = parse_batch_output(&batch, 2)
+            .into_iter()
+            .map(|r| parse_pm_path(&r.expect("marked").1).len())
+            .collect();
+        assert_eq!(paths, [1, 0]);
+    }
+}
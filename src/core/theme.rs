@@ -1,18 +1,21 @@
 use dark_light;
 use iced::{Color, color};
-use std::sync::LazyLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, RwLock};
 
-/*
-In-memory caching.
-This fixes the perf bug
-caused by Iced repeatedly calling `palette`.
-
-Coincidentally, this also ensures consistent colors across the GUI,
-at the cost of requiring a restart to update the palette.
-(this is just a patch, not a fix)
-*/
-pub static OS_COLOR_SCHEME: LazyLock<dark_light::Mode> =
-    LazyLock::new(|| dark_light::detect().unwrap_or(dark_light::Mode::Unspecified));
+/// `Dark` or `Light`, according to the OS color scheme.
+///
+/// This is slow-ish, so it must not be called from `palette`
+/// (Iced calls it repeatedly). The GUI keeps the result in its state instead,
+/// and polls this to follow OS changes.
+#[must_use]
+pub fn detect_os_theme() -> Theme {
+    match dark_light::detect().unwrap_or(dark_light::Mode::Unspecified) {
+        dark_light::Mode::Light => Theme::Light,
+        dark_light::Mode::Dark | dark_light::Mode::Unspecified => Theme::Dark,
+    }
+}
 
 #[derive(Default, Debug, PartialEq, Eq, Copy, Clone)]
 /// Color scheme
@@ -49,6 +52,7 @@ pub struct BrightColors {
     pub secondary: Color,
     pub surface: Color,
     pub error: Color,
+    pub warning: Color,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -61,14 +65,19 @@ pub struct ColorPalette {
 impl Theme {
     pub const ALL: [Self; 4] = [Self::Auto, Self::Lupin, Self::Dark, Self::Light];
 
+    /// Palette of the theme, with the overrides of [`set_color_overrides`]
+    #[must_use]
+    pub fn palette(self) -> ColorPalette {
+        let overrides = OVERRIDES.read().expect("color overrides lock poisoned");
+        overrides.apply(self, self.base_palette())
+    }
+
     #[allow(
         clippy::unreadable_literal,
         reason = "https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/pull/578#discussion_r1759653408"
     )]
-    /// This `fn` _could_ be `const`,
-    /// but `deref`ing a lazy-`static` is non-`const`.
     #[must_use]
-    pub fn palette(self) -> ColorPalette {
+    pub const fn base_palette(self) -> ColorPalette {
         const DARK: ColorPalette = ColorPalette {
             base: BaseColors {
                 background: color!(0x111111),
@@ -85,6 +94,7 @@ impl Theme {
                 secondary: color!(0x49EB7A),
                 surface: color!(0xE0E0E0),
                 error: color!(0xC13047),
+                warning: color!(0xD4A017),
             },
         };
         const LIGHT: ColorPalette = ColorPalette {
@@ -103,6 +113,7 @@ impl Theme {
                 secondary: color!(0x3797A4),
                 surface: color!(0x000000),
                 error: color!(0xC13047),
+                warning: color!(0x9A6A00),
             },
         };
         const LUPIN: ColorPalette = ColorPalette {
@@ -121,20 +132,146 @@ impl Theme {
                 secondary: color!(0x49EB7A),
                 surface: color!(0xF4F8F3),
                 error: color!(0xE63E6D),
+                warning: color!(0xF1C452),
             },
         };
         match self {
-            Self::Dark => DARK,
+            // The GUI resolves `Auto` with `detect_os_theme`
+            Self::Dark | Self::Auto => DARK,
             Self::Light => LIGHT,
             Self::Lupin => LUPIN,
-            Self::Auto => match *OS_COLOR_SCHEME {
-                dark_light::Mode::Light => LIGHT,
-                dark_light::Mode::Dark | dark_light::Mode::Unspecified => DARK,
-            },
         }
     }
 }
 
+/// User overrides of the semantic colors of a theme, as `#RRGGBB`.
+///
+/// In `config.toml`:
+/// ```toml
+/// [general.theme_colors.Dark]
+/// success = "#56B4E9"
+/// danger = "#D55E00"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SemanticColors {
+    pub primary: Option<String>,
+    /// Restore buttons, "enabled" texts
+    pub success: Option<String>,
+    /// Uninstall buttons, errors
+    pub danger: Option<String>,
+    /// Warnings, such as risky settings
+    pub warning: Option<String>,
+}
+
+/// Parsed [`SemanticColors`]
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Semantic {
+    primary: Option<Color>,
+    success: Option<Color>,
+    danger: Option<Color>,
+    warning: Option<Color>,
+}
+
+#[derive(Debug, Default)]
+struct Overrides {
+    color_blind: bool,
+    per_theme: Vec<(Theme, Semantic)>,
+}
+
+static OVERRIDES: LazyLock<RwLock<Overrides>> = LazyLock::new(RwLock::default);
+
+/// Blue/vermillion instead of green/red, and orange warnings, from the Okabe-Ito palette:
+/// they stay distinguishable with the common color vision deficiencies.
+const COLOR_BLIND: Semantic = Semantic {
+    primary: None,
+    success: Some(color!(0x56, 0xB4, 0xE9)),
+    danger: Some(color!(0xD5, 0x5E, 0x00)),
+    warning: Some(color!(0xE6, 0x9F, 0x00)),
+};
+
+impl Overrides {
+    fn apply(&self, theme: Theme, mut palette: ColorPalette) -> ColorPalette {
+        let custom = self
+            .per_theme
+            .iter()
+            .find(|(t, _)| *t == theme)
+            .map(|(_, colors)| *colors)
+            .unwrap_or_default();
+        let base = if self.color_blind {
+            COLOR_BLIND
+        } else {
+            Semantic::default()
+        };
+        if let Some(c) = custom.primary.or(base.primary) {
+            palette.bright.primary = c;
+        }
+        if let Some(c) = custom.success.or(base.success) {
+            palette.bright.secondary = c;
+            palette.normal.secondary = darken(c);
+        }
+        if let Some(c) = custom.danger.or(base.danger) {
+            palette.bright.error = c;
+            palette.normal.error = darken(c);
+        }
+        if let Some(c) = custom.warning.or(base.warning) {
+            palette.bright.warning = c;
+        }
+        palette
+    }
+}
+
+fn darken(c: Color) -> Color {
+    Color::from_rgb(c.r * 0.75, c.g * 0.75, c.b * 0.75)
+}
+
+/// `#RRGGBB` (or `RRGGBB`) to a color
+#[must_use]
+pub fn parse_hex_color(s: &str) -> Option<Color> {
+    let hex = s.trim().trim_start_matches('#');
+    if hex.len() != 6 || !hex.is_ascii() {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    Some(Color::from_rgb8(channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Makes [`Theme::palette`] use the color-blind palette and/or the user colors.
+///
+/// `per_theme` is keyed by theme name, like in `config.toml`. Invalid colors are ignored.
+pub fn set_color_overrides(color_blind: bool, per_theme: &HashMap<String, SemanticColors>) {
+    let parse = |name: &str, color: Option<&String>| {
+        let color = color?;
+        let parsed = parse_hex_color(color);
+        if parsed.is_none() {
+            warn!("Ignoring invalid {name} color `{color}`, expected `#RRGGBB`");
+        }
+        parsed
+    };
+    let per_theme = per_theme
+        .iter()
+        .filter_map(|(name, colors)| {
+            let theme = Theme::ALL.into_iter().find(|t| t.to_string() == *name);
+            if theme.is_none() {
+                warn!("Ignoring the colors of unknown theme `{name}`");
+            }
+            Some((
+                theme?,
+                Semantic {
+                    primary: parse("primary", colors.primary.as_ref()),
+                    success: parse("success", colors.success.as_ref()),
+                    danger: parse("danger", colors.danger.as_ref()),
+                    warning: parse("warning", colors.warning.as_ref()),
+                },
+            ))
+        })
+        .collect();
+    *OVERRIDES.write().expect("color overrides lock poisoned") = Overrides {
+        color_blind,
+        per_theme,
+    };
+}
+
 impl std::fmt::Display for Theme {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
@@ -149,3 +286,52 @@ impl std::fmt::Display for Theme {
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hex_colors() {
+        assert_eq!(
+            parse_hex_color("#56B4E9"),
+            Some(Color::from_rgb8(0x56, 0xB4, 0xE9))
+        );
+        assert_eq!(
+            parse_hex_color("d55e00"),
+            Some(Color::from_rgb8(0xD5, 0x5E, 0))
+        );
+        for bad in ["", "#FFF", "#GGGGGG", "#56B4E9FF", "#é5B4E"] {
+            assert_eq!(parse_hex_color(bad), None, "{bad}");
+        }
+    }
+
+    #[test]
+    fn color_overrides() {
+        let base = Theme::Dark.base_palette();
+        let red = Color::from_rgb8(0xFF, 0, 0);
+        let overrides = Overrides {
+            color_blind: true,
+            per_theme: vec![(
+                Theme::Dark,
+                Semantic {
+                    danger: Some(red),
+                    ..Semantic::default()
+                },
+            )],
+        };
+        let dark = overrides.apply(Theme::Dark, base);
+        assert_eq!(dark.bright.error, red);
+        assert_eq!(dark.bright.secondary, COLOR_BLIND.success.expect("set"));
+        assert_eq!(dark.bright.primary, base.bright.primary);
+
+        assert_eq!(dark.bright.warning, COLOR_BLIND.warning.expect("set"));
+
+        let lupin = overrides.apply(Theme::Lupin, Theme::Lupin.base_palette());
+        assert_eq!(lupin.bright.error, COLOR_BLIND.danger.expect("set"));
+
+        let none = Overrides::default().apply(Theme::Dark, base);
+        assert_eq!(none.bright.error, base.bright.error);
+        assert_eq!(none.bright.warning, base.bright.warning);
+    }
+}
@@ -6,10 +6,22 @@ use serde_json;
 use std::borrow::Cow;
 use std::collections::HashMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::Path;
 
 pub const LIST_FNAME: &str = "uad_lists.json";
 
+/// Where [`LIST_FNAME`] is downloaded from, unless overridden
+pub const DEFAULT_LIST_URL: &str = "https://raw.githubusercontent.com\
+    /Universal-Debloater-Alliance\
+    /universal-android-debloater\
+    /main\
+    /resources\
+    /assets";
+
+/// Overrides both [`DEFAULT_LIST_URL`] and
+/// [`crate::core::config::GeneralSettings::list_url`], e.g. for mirrors
+pub const LIST_URL_ENV: &str = "UAD_LIST_URL";
+
 #[allow(
     clippy::large_include_file,
     reason = "https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/discussions/608"
@@ -28,7 +40,7 @@ pub struct Package {
     pub removal: Removal,
 }
 
-#[derive(Default, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum UadList {
     #[default]
     All,
@@ -46,7 +58,10 @@ pub enum UadListState {
     #[default]
     Downloading,
     Done,
+    /// Most likely no network
     Failed,
+    /// The list source is reachable but unusable (bad URL, HTTP error, invalid list)
+    SourceFailed,
 }
 
 impl std::fmt::Display for UadListState {
@@ -56,6 +71,7 @@ impl std::fmt::Display for UadListState {
             Self::Downloading => "Checking updates...".to_string(),
             Self::Done => format!("Done (last was {})", format_diff_time_from_now(date)),
             Self::Failed => "Failed to check update!".to_string(),
+            Self::SourceFailed => "The list source is broken, see the logs".to_string(),
         };
         write!(f, "{s}")
     }
@@ -148,7 +164,7 @@ impl Opposite for PackageState {
 }
 
 // Bad names. To be changed!
-#[derive(Default, Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[derive(Default, Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum Removal {
     #[default]
     Recommended,
@@ -186,6 +202,33 @@ impl Removal {
             Self::Unlisted => "Unlisted",
         }
     }
+
+    /// What removing a package of this category risks, for newcomers.
+    /// `None` for [`Self::All`], which isn't a category.
+    pub const fn description(self) -> Option<&'static str> {
+        match self {
+            Self::All => None,
+            Self::Recommended => Some(
+                "Safe to remove: bloatware or apps with better alternatives. \
+                 Nothing important should break.",
+            ),
+            Self::Advanced => Some(
+                "Removing may break obvious features (e.g. an app store, a launcher) \
+                 but won't prevent the device from booting.",
+            ),
+            Self::Expert => Some(
+                "Removing may break things in unexpected ways. \
+                 Read the description, and only go ahead if you know what the package does.",
+            ),
+            Self::Unsafe => Some(
+                "Can break vital parts of the system and cause a bootloop. \
+                 Only selectable in expert mode: have a backup and a way to recover.",
+            ),
+            Self::Unlisted => Some(
+                "Not reviewed by the maintainers of the lists yet: there is no advice for them.",
+            ),
+        }
+    }
 }
 
 impl std::fmt::Display for Removal {
@@ -200,67 +243,359 @@ impl From<Removal> for Cow<'_, str> {
     }
 }
 
+/// Who ships a package, guessed from its name (see [`Origin::classify`]).
+///
+/// Unlike [`UadList`], it's known for unlisted packages too, such as regional carrier apps.
+#[derive(Default, Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Origin {
+    #[default]
+    All,
+    FirstParty,
+    Oem,
+    Carrier,
+    Other,
+}
+
+/// Package prefixes of OEMs whose brand (`ro.product.brand`) isn't in their package names
+const OEM_PREFIXES: [(&str, &[&str]); 8] = [
+    ("samsung", &["com.sec.", "com.samsung."]),
+    ("xiaomi", &["com.miui.", "com.xiaomi."]),
+    ("redmi", &["com.miui.", "com.xiaomi."]),
+    ("poco", &["com.miui.", "com.xiaomi."]),
+    ("oneplus", &["com.oneplus.", "com.oplus.", "com.heytap."]),
+    (
+        "oppo",
+        &["com.oppo.", "com.coloros.", "com.oplus.", "com.heytap."],
+    ),
+    (
+        "realme",
+        &["com.realme.", "com.coloros.", "com.oplus.", "com.heytap."],
+    ),
+    (
+        "sony",
+        &["com.sony.", "com.sonyericsson.", "com.sonymobile."],
+    ),
+];
+
+/// Package prefixes of mobile network operators
+const CARRIER_PREFIXES: [&str; 20] = [
+    "com.att.",
+    "com.vzw.",
+    "com.verizon.",
+    "com.tmobile.",
+    "com.sprint.",
+    "com.metropcs.",
+    "com.uscc.",
+    "com.cricketwireless.",
+    "com.orange.",
+    "com.vodafone.",
+    "de.telekom.",
+    "com.telekom.",
+    "com.telefonica.",
+    "com.movistar.",
+    "com.telstra.",
+    "uk.co.ee.",
+    "com.rogers.",
+    "com.telus.",
+    "com.jio.",
+    "com.airtel.",
+];
+
+impl Origin {
+    pub const ALL: [Self; 5] = [
+        Self::All,
+        Self::FirstParty,
+        Self::Oem,
+        Self::Carrier,
+        Self::Other,
+    ];
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::All => "All origins",
+            Self::FirstParty => "AOSP/Google",
+            Self::Oem => "OEM",
+            Self::Carrier => "Carrier",
+            Self::Other => "Other",
+        }
+    }
+
+    /// Heuristics on the package `name`.
+    /// `brand` is the device brand (see [`crate::core::sync::get_device_brand`]),
+    /// for OEM packages.
+    pub fn classify(name: &str, brand: &str) -> Self {
+        if name == "android" || name.starts_with("com.android.") || name.starts_with("com.google.")
+        {
+            return Self::FirstParty;
+        }
+        if CARRIER_PREFIXES.iter().any(|p| name.starts_with(p)) {
+            return Self::Carrier;
+        }
+        let brand = brand.to_lowercase();
+        if !brand.is_empty() {
+            let is_brand_part = name.split('.').any(|part| part == brand);
+            let has_oem_prefix = OEM_PREFIXES
+                .iter()
+                .filter(|(b, _)| *b == brand)
+                .flat_map(|(_, prefixes)| prefixes.iter())
+                .any(|p| name.starts_with(p));
+            if is_brand_part || has_oem_prefix {
+                return Self::Oem;
+            }
+        }
+        Self::Other
+    }
+}
+
+impl std::fmt::Display for Origin {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl From<Origin> for Cow<'_, str> {
+    fn from(origin: Origin) -> Self {
+        Cow::Borrowed(origin.as_str())
+    }
+}
+
 pub type PackageHashMap = HashMap<String, Package>;
-pub fn load_debloat_lists(remote: bool) -> Result<PackageHashMap, PackageHashMap> {
-    let cached_uad_lists: PathBuf = CACHE_DIR.join(LIST_FNAME);
-    let mut error = false;
-    let list: PackageHashMap = if remote {
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ListFetchError {
+    /// The server couldn't be reached
+    Network(String),
+    /// The configured URL can't be used
+    InvalidUrl(String),
+    /// The server answered, but not with a valid list
+    Source(String),
+}
+
+impl std::fmt::Display for ListFetchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Network(e) => write!(f, "network error: {e}"),
+            Self::InvalidUrl(url) => write!(f, "invalid list URL: `{url}`"),
+            Self::Source(e) => write!(f, "bad list source: {e}"),
+        }
+    }
+}
+
+impl From<ListFetchError> for UadListState {
+    fn from(err: ListFetchError) -> Self {
+        match err {
+            ListFetchError::Network(_) => Self::Failed,
+            ListFetchError::InvalidUrl(_) | ListFetchError::Source(_) => Self::SourceFailed,
+        }
+    }
+}
+
+/// URL of [`LIST_FNAME`]: [`LIST_URL_ENV`], else `configured`, else [`DEFAULT_LIST_URL`].
+///
+/// Both a base URL and the full URL of the file are accepted.
+pub fn resolve_list_url(
+    env: Option<&str>,
+    configured: Option<&str>,
+) -> Result<String, ListFetchError> {
+    let base = env
+        .or(configured)
+        .map(str::trim)
+        .filter(|url| !url.is_empty())
+        .unwrap_or(DEFAULT_LIST_URL);
+    let host = base
+        .strip_prefix("https://")
+        .or_else(|| base.strip_prefix("http://"))
+        .and_then(|rest| rest.split('/').next())
+        .filter(|host| !host.is_empty() && !host.contains(char::is_whitespace));
+    if host.is_none() || base.contains(char::is_whitespace) {
+        return Err(ListFetchError::InvalidUrl(base.to_string()));
+    }
+    let is_file = Path::new(base)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+    Ok(if is_file {
+        base.to_string()
+    } else {
+        format!("{}/{LIST_FNAME}", base.trim_end_matches('/'))
+    })
+}
+
+fn fetch_remote_list(url: &str) -> Result<PackageHashMap, ListFetchError> {
+    use ureq::Error;
+    let mut data = ureq::get(url).call().map_err(|e| match e {
+        Error::Io(_)
+        | Error::Timeout(_)
+        | Error::HostNotFound
+        | Error::ConnectionFailed
+        | Error::ConnectProxyFailed(_)
+        | Error::BodyStalled => ListFetchError::Network(e.to_string()),
+        _ => ListFetchError::Source(e.to_string()),
+    })?;
+    // https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/discussions/608
+    let text = data
+        .body_mut()
+        .with_config()
+        .limit(1 << (3 + 10 + 10))
+        .read_to_string()
+        .map_err(|e| ListFetchError::Source(e.to_string()))?;
+    let list = serde_json::from_str(&text).map_err(|e| ListFetchError::Source(e.to_string()))?;
+    fs::write(CACHE_DIR.join(LIST_FNAME), &text).expect("Unable to write file");
+    Ok(list)
+}
+
+/// `list_url` is [`crate::core::config::GeneralSettings::list_url`].
+///
+/// On failure, the local list is returned along what went wrong.
+pub fn load_debloat_lists(
+    remote: bool,
+    list_url: Option<&str>,
+) -> Result<PackageHashMap, (PackageHashMap, ListFetchError)> {
+    if !remote {
+        warn!("Could not load remote debloat list");
+        return Ok(get_local_lists());
+    }
+    let url = std::env::var(LIST_URL_ENV).ok();
+    let fetched = resolve_list_url(url.as_deref(), list_url).and_then(|url| {
+        info!("Downloading the debloat list from {url}");
         retry(Fixed::from_millis(1000).take(60), || {
-            match ureq::get(format!(
-                "https://raw.githubusercontent.com\
-                    /Universal-Debloater-Alliance\
-                    /universal-android-debloater\
-                    /main\
-                    /resources\
-                    /assets\
-                    /{LIST_FNAME}"
-            ))
-            .call()
-            {
-                Ok(mut data) => {
-                    // https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/discussions/608
-                    let text = data
-                        .body_mut()
-                        .with_config()
-                        .limit(1 << (3 + 10 + 10))
-                        .read_to_string()
-                        .expect("remote list is bigger than 8MiB");
-                    fs::write(cached_uad_lists.clone(), &text).expect("Unable to write file");
-                    let list: PackageHashMap =
-                        serde_json::from_str(&text).expect("Unable to parse");
-                    OperationResult::Ok(list)
-                }
-                Err(e) => {
+            match fetch_remote_list(&url) {
+                Ok(list) => OperationResult::Ok(list),
+                Err(e @ ListFetchError::Network(_)) => {
                     warn!("Could not load remote debloat list: {e}");
-                    error = true;
-                    OperationResult::Retry(PackageHashMap::new())
+                    OperationResult::Retry(e)
                 }
+                // retrying won't fix the source
+                Err(e) => OperationResult::Err(e),
             }
         })
-        .unwrap_or_else(|_| get_local_lists())
-    } else {
-        warn!("Could not load remote debloat list");
-        get_local_lists()
-    };
-
-    (if error { Err } else { Ok })(list)
+        .map_err(|e| e.error)
+    });
+    fetched.map_err(|e| (get_local_lists(), e))
 }
 
 fn get_local_lists() -> PackageHashMap {
-    let cached_uad_lists = CACHE_DIR.join(LIST_FNAME);
-    serde_json::from_str(
-        fs::read_to_string(cached_uad_lists)
-            .as_deref()
-            .unwrap_or(DATA),
-    )
-    .expect("Unable to parse")
+    read_cached_lists(&CACHE_DIR.join(LIST_FNAME))
+}
+
+/// The cached list, or the embedded one if there's none.
+///
+/// A corrupt cache (e.g. truncated by a killed download) is deleted,
+/// so the next remote update starts clean.
+fn read_cached_lists(cached: &Path) -> PackageHashMap {
+    if let Ok(text) = fs::read_to_string(cached) {
+        match serde_json::from_str(&text) {
+            Ok(list) => return list,
+            Err(e) => {
+                warn!(
+                    "Cached debloat list {} is corrupt ({e}), deleting it. Using the embedded (and older) list until the next update",
+                    cached.display()
+                );
+                if let Err(rm_err) = fs::remove_file(cached) {
+                    error!("Could not delete the corrupt cached list: {rm_err}");
+                }
+            }
+        }
+    }
+    serde_json::from_str(DATA).expect("Unable to parse")
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    #[test]
+    fn removal_descriptions() {
+        assert!(Removal::All.description().is_none());
+        for removal in Removal::CATEGORIES {
+            assert!(removal.description().is_some(), "{removal}");
+        }
+    }
+
+    #[test]
+    fn package_origins() {
+        assert_eq!(Origin::classify("android", ""), Origin::FirstParty);
+        assert_eq!(
+            Origin::classify("com.google.android.youtube", "samsung"),
+            Origin::FirstParty
+        );
+        assert_eq!(
+            Origin::classify("com.sec.android.app.sbrowser", "samsung"),
+            Origin::Oem
+        );
+        assert_eq!(
+            Origin::classify("com.motorola.help", "Motorola"),
+            Origin::Oem
+        );
+        // another brand's
+        assert_eq!(
+            Origin::classify("com.sec.android.app.sbrowser", "google"),
+            Origin::Other
+        );
+        assert_eq!(
+            Origin::classify("com.vzw.hss.myverizon", "samsung"),
+            Origin::Carrier
+        );
+        assert_eq!(
+            Origin::classify("org.mozilla.firefox", "samsung"),
+            Origin::Other
+        );
+        assert_eq!(Origin::classify("com.whatsapp", ""), Origin::Other);
+    }
+
     #[test]
     fn test_parse_json() {
         let _: PackageHashMap = serde_json::from_str(DATA).expect("Unable to parse");
     }
+
+    #[test]
+    fn corrupt_cache_recovery() {
+        let dir = std::env::temp_dir().join(format!("uadng_lists_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir");
+        let cached = dir.join(LIST_FNAME);
+        let embedded: PackageHashMap = serde_json::from_str(DATA).expect("Unable to parse");
+
+        fs::write(&cached, &DATA.as_bytes()[..DATA.len() / 2]).expect("writable");
+        assert_eq!(read_cached_lists(&cached), embedded);
+        assert!(!cached.exists());
+        // no cache at all
+        assert_eq!(read_cached_lists(&cached), embedded);
+
+        fs::write(
+            &cached,
+            r#"{"com.example": {"list": "Oem", "description": "",
+            "dependencies": [], "neededBy": [], "labels": [], "removal": "Expert"}}"#,
+        )
+        .expect("writable");
+        assert_eq!(read_cached_lists(&cached).len(), 1);
+        fs::remove_dir_all(&dir).expect("removable");
+    }
+
+    #[test]
+    fn list_url_override() {
+        let default = format!("{DEFAULT_LIST_URL}/{LIST_FNAME}");
+        assert_eq!(resolve_list_url(None, None), Ok(default.clone()));
+        assert_eq!(resolve_list_url(None, Some(" ")), Ok(default));
+        assert_eq!(
+            resolve_list_url(None, Some("https://git.corp/uad/")),
+            Ok(format!("https://git.corp/uad/{LIST_FNAME}"))
+        );
+        // the environment wins
+        assert_eq!(
+            resolve_list_url(
+                Some("http://10.0.0.2/lists.json"),
+                Some("https://git.corp/uad")
+            ),
+            Ok("http://10.0.0.2/lists.json".to_string())
+        );
+        for bad in [
+            "git.corp/uad",
+            "ftp://git.corp",
+            "https://",
+            "https://git corp",
+        ] {
+            assert_eq!(
+                resolve_list_url(Some(bad), None),
+                Err(ListFetchError::InvalidUrl(bad.to_string()))
+            );
+        }
+    }
 }
@@ -29,6 +29,10 @@ pub struct ReleaseAsset {
 pub struct SelfUpdateState {
     pub latest_release: Option<Release>,
     pub status: SelfUpdateStatus,
+    /// While [`SelfUpdateStatus::Updating`]
+    pub self_update_progress: Option<DownloadProgress>,
+    /// Why the last update failed
+    pub self_update_error: Option<String>,
 }
 
 #[derive(Default, Debug, PartialEq, Eq, Clone)]
@@ -38,6 +42,8 @@ pub enum SelfUpdateStatus {
     Checking,
     Done,
     Failed,
+    /// See [`crate::core::config::GeneralSettings::self_update_enabled`]
+    Disabled,
 }
 
 impl std::fmt::Display for SelfUpdateStatus {
@@ -47,120 +53,266 @@ impl std::fmt::Display for SelfUpdateStatus {
             Self::Updating => "Updating...",
             Self::Failed => "Failed to check update!",
             Self::Done => "Done",
+            Self::Disabled => "(Updates are disabled in the settings)",
         };
         write!(f, "{s}")
     }
 }
 
-/// Download a file from the internet
+/// Bytes of a download received so far
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DownloadProgress {
+    pub downloaded: u64,
+    /// `None` when the server doesn't send a `Content-Length`
+    pub total: Option<u64>,
+}
+
+impl DownloadProgress {
+    /// From 0 to 100, if the total is known
+    pub fn percent(&self) -> Option<u8> {
+        self.total
+            .filter(|&t| t > 0)
+            .map(|t| u8::try_from((self.downloaded.min(t) * 100) / t).unwrap_or(100))
+    }
+}
+
+impl std::fmt::Display for DownloadProgress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kib = self.downloaded / 1024;
+        match self.percent() {
+            Some(p) => write!(f, "{p}% ({kib} KiB)"),
+            None => write!(f, "{kib} KiB"),
+        }
+    }
+}
+
+/// Download a file from the internet, calling `on_progress` as it's received.
+///
+/// A partial file is deleted on failure.
 #[cfg(feature = "self-update")]
-#[allow(clippy::unused_async, reason = "`.call` is equivalent to `.await`")]
-pub async fn download_file(url: &str, dest_file: PathBuf) -> Result<(), String> {
+pub fn download_file(
+    url: &str,
+    dest_file: &Path,
+    on_progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<(), String> {
     debug!("downloading file from {url}");
 
-    match ureq::get(url).call() {
-        Ok(mut res) => {
-            let mut file = fs::File::create(dest_file).map_err(|e| e.to_string())?;
+    let mut res = ureq::get(url).call().map_err(|e| e.to_string())?;
+    let total = res.body().content_length();
+    let mut file = fs::File::create(dest_file).map_err(|e| e.to_string())?;
+    let mut reader = ProgressReader {
+        inner: res.body_mut().as_reader(),
+        progress: DownloadProgress {
+            downloaded: 0,
+            total,
+        },
+        on_progress,
+    };
+
+    let copied = copy(&mut reader, &mut file).map_err(|e| e.to_string());
+    let result = match copied {
+        Ok(n) if total.is_some_and(|t| t != n) => Err(format!(
+            "Interrupted download: {n} of {} bytes",
+            total.unwrap_or_default()
+        )),
+        Ok(_) => Ok(()),
+        Err(e) => Err(e),
+    };
+    if result.is_err() {
+        drop(file);
+        let _ = fs::remove_file(dest_file);
+    }
+    result
+}
 
-            if let Err(e) = copy(&mut res.body_mut().as_reader(), &mut file) {
-                return Err(e.to_string());
-            }
-        }
-        Err(e) => return Err(e.to_string()),
+/// Reports what goes through it
+#[cfg(feature = "self-update")]
+struct ProgressReader<'a, R> {
+    inner: R,
+    progress: DownloadProgress,
+    on_progress: &'a mut dyn FnMut(DownloadProgress),
+}
+
+#[cfg(feature = "self-update")]
+impl<R: io::Read> io::Read for ProgressReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.progress.downloaded += n as u64;
+        (self.on_progress)(self.progress);
+        Ok(n)
     }
-    Ok(())
 }
 
 /// Downloads the latest release file that matches `bin_name`, renames the current
 /// executable to a temp path, renames the new version as the original file name,
-/// then returns both the original file name (new version) and temp path (old version)
+/// then returns both the original file name (new version) and temp path (old version).
+///
+/// It blocks, reporting the download to `on_progress`.
+/// On failure, the downloaded files are deleted and the current executable is left as is.
 #[cfg(feature = "self-update")]
-pub async fn download_update_to_temp_file(
+pub fn download_update_to_temp_file(
     bin_name: &str,
-    release: Release,
-) -> Result<(PathBuf, PathBuf), ()> {
-    let current_bin_path = std::env::current_exe().map_err(|_| ())?;
+    release: &Release,
+    on_progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<(PathBuf, PathBuf), String> {
+    let current_bin_path = std::env::current_exe().map_err(|e| e.to_string())?;
+    let bin_dir = current_bin_path
+        .parent()
+        .ok_or("The executable has no parent folder")?;
 
     // Path to download the new version to
-    let download_path = current_bin_path
-        .parent()
-        .ok_or(())?
-        .join(format!("tmp_{bin_name}"));
+    let download_path = bin_dir.join(format!("tmp_{bin_name}"));
 
     // Path to temporarily force rename current process to, se we can then
     // rename `download_path` to `current_bin_path` and then launch new version
     // cleanly as `current_bin_path`
-    let tmp_path = current_bin_path
-        .parent()
-        .ok_or(())?
-        .join(format!("tmp2_{bin_name}"));
+    let tmp_path = bin_dir.join(format!("tmp2_{bin_name}"));
+
+    if let Err(e) = fetch_binary(bin_name, release, &download_path, on_progress) {
+        error!("Couldn't download {NAME} update: {e}");
+        let _ = fs::remove_file(&download_path);
+        return Err(e);
+    }
+
+    if let Err(e) = rename(&current_bin_path, &tmp_path) {
+        error!("[SelfUpdate] Couldn't rename from current to temporary binary path: {e}");
+        let _ = fs::remove_file(&download_path);
+        return Err(e);
+    }
+    if let Err(e) = rename(&download_path, &current_bin_path) {
+        error!("[SelfUpdate] Couldn't rename from downloaded to current binary path: {e}");
+        if let Err(restore_error) = rename(&tmp_path, &current_bin_path) {
+            error!("[SelfUpdate] Couldn't restore the current binary: {restore_error}");
+        }
+        let _ = fs::remove_file(&download_path);
+        return Err(e);
+    }
+
+    Ok((current_bin_path, tmp_path))
+}
 
+/// Downloads the asset of `bin_name` in `release`, to an executable `download_path`
+#[cfg(feature = "self-update")]
+fn fetch_binary(
+    bin_name: &str,
+    release: &Release,
+    download_path: &Path,
+    on_progress: &mut dyn FnMut(DownloadProgress),
+) -> Result<(), String> {
     // MacOS and Linux release are gziped tarball
     #[cfg(not(target_os = "windows"))]
-    {
-        let asset_name = format!("{bin_name}.tar.gz");
+    let asset_name = format!("{bin_name}.tar.gz");
+    // For Windows we download the new binary directly
+    #[cfg(target_os = "windows")]
+    let asset_name = bin_name.to_string();
 
-        let asset = release
-            .assets
-            .iter()
-            .find(|a| a.name == asset_name)
-            .cloned()
-            .ok_or(())?;
+    let asset = release
+        .assets
+        .iter()
+        .find(|a| a.name == asset_name)
+        .ok_or(format!("No `{asset_name}` in release {}", release.tag_name))?;
 
-        let archive_path = current_bin_path.parent().ok_or(())?.join(&asset_name);
+    #[cfg(not(target_os = "windows"))]
+    {
+        use std::os::unix::fs::PermissionsExt;
 
-        if let Err(e) = download_file(&asset.download_url, archive_path.clone()).await {
-            error!("Couldn't download {NAME} update: {e}");
-            return Err(());
-        }
+        let archive_path = download_path.with_file_name(&asset_name);
+        download_file(&asset.download_url, &archive_path, on_progress)?;
 
-        if extract_binary_from_tar(&archive_path, &download_path).is_err() {
-            error!("Couldn't extract {NAME} release tarball");
-            return Err(());
-        }
+        let extracted = extract_binary_from_tar(&archive_path, download_path);
+        let _ = fs::remove_file(&archive_path);
+        extracted.map_err(|e| format!("Couldn't extract {NAME} release tarball: {e}"))?;
 
-        std::fs::remove_file(&archive_path).map_err(|_| ())?;
+        // Make the file executable
+        let mut permissions = fs::metadata(download_path)
+            .map_err(|e| e.to_string())?
+            .permissions();
+        permissions.set_mode(0o755);
+        fs::set_permissions(download_path, permissions)
+            .map_err(|e| format!("Couldn't set permission to temp file: {e}"))?;
     }
 
-    // For Windows we download the new binary directly
     #[cfg(target_os = "windows")]
-    {
-        let asset = release
-            .assets
-            .iter()
-            .find(|a| a.name == bin_name)
-            .cloned()
-            .ok_or(())?;
-
-        if let Err(e) = download_file(&asset.download_url, download_path.clone()).await {
-            error!("Couldn't download {NAME} update: {}", e);
-            return Err(());
-        }
+    download_file(&asset.download_url, download_path, on_progress)?;
+
+    if is_executable(download_path) {
+        Ok(())
+    } else {
+        Err("The downloaded binary isn't executable".to_string())
     }
+}
 
-    // Make the file executable
+/// A non-empty file, executable by its owner on Unix
+#[cfg(feature = "self-update")]
+fn is_executable(path: &Path) -> bool {
+    let Ok(metadata) = fs::metadata(path) else {
+        return false;
+    };
     #[cfg(not(target_os = "windows"))]
     {
         use std::os::unix::fs::PermissionsExt;
-
-        let mut permissions = fs::metadata(&download_path).map_err(|_| ())?.permissions();
-        permissions.set_mode(0o755);
-        if let Err(e) = fs::set_permissions(&download_path, permissions) {
-            error!("[SelfUpdate] Couldn't set permission to temp file: {e}");
-            return Err(());
+        if metadata.permissions().mode() & 0o100 == 0 {
+            return false;
         }
     }
+    metadata.is_file() && metadata.len() > 0
+}
 
-    if let Err(e) = rename(&current_bin_path, &tmp_path) {
-        error!("[SelfUpdate] Couldn't rename from current to temporary binary path: {e}");
-        return Err(());
-    }
-    if let Err(e) = rename(&download_path, &current_bin_path) {
-        error!("[SelfUpdate] Couldn't rename from downloaded to current binary path: {e}");
-        return Err(());
+/// Flag passed to the new version, followed by the path of the old one, so it deletes it.
+/// The old version can't delete itself: it's still running.
+#[cfg(feature = "self-update")]
+pub const SELF_UPDATE_TEMP_FLAG: &str = "--self-update-temp";
+
+/// Arguments of the new version: the current ones (`args`, without the binary),
+/// with [`SELF_UPDATE_TEMP_FLAG`] pointing to `cleanup_path` only.
+///
+/// A previous flag is dropped, on its own or with its path,
+/// as the arg parsing of the new process would fail.
+#[cfg(feature = "self-update")]
+pub fn relaunch_args(
+    args: impl IntoIterator<Item = String>,
+    cleanup_path: &Path,
+) -> Vec<std::ffi::OsString> {
+    let mut relaunch = vec![];
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == SELF_UPDATE_TEMP_FLAG {
+            // its path
+            args.next();
+        } else {
+            relaunch.push(arg.into());
+        }
     }
+    relaunch.push(SELF_UPDATE_TEMP_FLAG.into());
+    relaunch.push(cleanup_path.into());
+    relaunch
+}
 
-    Ok((current_bin_path, tmp_path))
+/// Deletes the old version left by a self-update, if `args` has a [`SELF_UPDATE_TEMP_FLAG`].
+///
+/// It runs in the background, as the old process may take a while to exit
+/// (and release its file, on Windows).
+#[cfg(feature = "self-update")]
+pub fn cleanup_after_update(args: impl IntoIterator<Item = String>) {
+    let mut args = args.into_iter();
+    let Some(path) = args
+        .by_ref()
+        .find(|a| a == SELF_UPDATE_TEMP_FLAG)
+        .and_then(|_| args.next())
+        .map(PathBuf::from)
+    else {
+        return;
+    };
+    std::thread::spawn(move || match remove_file(&path) {
+        Ok(()) => info!(
+            "[SelfUpdate] Removed the previous version {}",
+            path.display()
+        ),
+        Err(e) => error!(
+            "[SelfUpdate] Couldn't remove the previous version {}: {e}",
+            path.display()
+        ),
+    });
 }
 
 #[cfg(not(feature = "self-update"))]
@@ -168,34 +320,52 @@ pub fn get_latest_release() -> Result<Option<Release>, ()> {
     Ok(None)
 }
 
+/// How long the release check may take, so the status resolves quickly on a slow network
+#[cfg(feature = "self-update")]
+pub const RELEASE_CHECK_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
 // UAD-ng only has pre-releases so we can't use
 // https://api.github.com/repos/Universal-Debloater-Alliance/universal-android-debloater/releases/latest
 // to only get the latest release
+/// A timeout counts as "no update": it's likely a blocked or slow network,
+/// not worth an error in the UI.
 #[cfg(feature = "self-update")]
 pub fn get_latest_release() -> Result<Option<Release>, ()> {
     debug!("Checking for {NAME} update");
 
-    if let Ok(mut res) = ureq::get("https://api.github.com/repos/Universal-Debloater-Alliance/universal-android-debloater/releases/latest")
-        .call() {
-        let release: Release = serde_json::from_value(
-            res.body_mut().read_json::<serde_json::Value>()
-                .map_err(|_| ())?
-                .clone(),
-        )
-        .map_err(|_| ())?;
-
-        let release_version = release.tag_name.strip_prefix('v').unwrap_or(&release.tag_name);
-
-        if release_version != "dev-build"
-            && release_version > env!("CARGO_PKG_VERSION")
-        {
-            Ok(Some(release))
-        } else {
+    let agent: ureq::Agent = ureq::Agent::config_builder()
+        .timeout_global(Some(RELEASE_CHECK_TIMEOUT))
+        .build()
+        .into();
+    match agent
+        .get("https://api.github.com/repos/Universal-Debloater-Alliance/universal-android-debloater/releases/latest")
+        .call()
+    {
+        Ok(mut res) => {
+            let release: Release = serde_json::from_value(
+                res.body_mut()
+                    .read_json::<serde_json::Value>()
+                    .map_err(|_| ())?
+                    .clone(),
+            )
+            .map_err(|_| ())?;
+
+            let release_version = release.tag_name.strip_prefix('v').unwrap_or(&release.tag_name);
+
+            if release_version != "dev-build" && release_version > env!("CARGO_PKG_VERSION") {
+                Ok(Some(release))
+            } else {
+                Ok(None)
+            }
+        }
+        Err(ureq::Error::Timeout(t)) => {
+            warn!("{NAME} update check timed out ({t}), skipping it");
             Ok(None)
         }
-    } else {
-        debug!("Failed to check {NAME} update");
-        Err(())
+        Err(e) => {
+            debug!("Failed to check {NAME} update: {e}");
+            Err(())
+        }
     }
 }
 
@@ -301,3 +471,35 @@ where
     )
     .map_err(|e| e.to_string())
 }
+
+#[cfg(all(test, feature = "self-update"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relaunch_arguments() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        let old = Path::new("/opt/tmp2_uad-ng");
+        let expected = |s: &str| {
+            let mut v: Vec<std::ffi::OsString> = args(s).into_iter().map(Into::into).collect();
+            v.push(SELF_UPDATE_TEMP_FLAG.into());
+            v.push(old.into());
+            v
+        };
+        assert_eq!(relaunch_args(args(""), old), expected(""));
+        assert_eq!(relaunch_args(args("--mock"), old), expected("--mock"));
+        // from a previous update
+        assert_eq!(
+            relaunch_args(args("--self-update-temp /a --mock"), old),
+            expected("--mock")
+        );
+        assert_eq!(
+            relaunch_args(args("--mock --self-update-temp /a"), old),
+            expected("--mock")
+        );
+        assert_eq!(
+            relaunch_args(args("--mock --self-update-temp"), old),
+            expected("--mock")
+        );
+    }
+}
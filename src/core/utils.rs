@@ -1,23 +1,25 @@
 #![warn(clippy::unwrap_used)]
 
+use crate::CONFIG_DIR;
 use crate::core::{
-    adb::{ACommand as AdbCommand, PmListPacksFlag},
-    sync::User,
+    adb::PmListPacksFlag,
+    backend::backend,
+    sync::{User, get_device_brand},
     theme::Theme,
-    uad_lists::{PackageHashMap, PackageState, Removal, UadList},
+    uad_lists::{Origin, PackageHashMap, PackageState, Removal, UadList},
 };
 use crate::gui::widgets::package_row::PackageRow;
 use chrono::{DateTime, offset::Utc};
 use csv::Writer;
 use std::{
-    collections::HashSet,
+    collections::{BTreeSet, HashSet},
     fmt, fs,
     path::{Path, PathBuf},
+    time::Duration,
 };
 
 /// Canonical shortened name of the application
 pub const NAME: &str = "UAD-ng";
-pub const EXPORT_FILE_NAME: &str = "selection_export.txt";
 
 /// Returns `true` if `c` matches the regex `\w`
 #[inline]
@@ -64,64 +66,73 @@ pub enum Error {
     DialogClosed,
 }
 
+/// Packages of the user, `until_used` being its
+/// [`crate::core::sync::disabled_until_used`] ones
 pub fn fetch_packages(
     uad_lists: &PackageHashMap,
     device_serial: &str,
     user_id: Option<u16>,
+    until_used: &HashSet<String>,
 ) -> Vec<PackageRow> {
-    let all_sys_packs = AdbCommand::new()
-        .shell(device_serial)
-        .pm()
-        .list_packages_sys(Some(PmListPacksFlag::IncludeUninstalled), user_id)
+    let all_sys_packs = backend()
+        .list_packages_sys(
+            device_serial,
+            Some(PmListPacksFlag::IncludeUninstalled),
+            user_id,
+        )
         .unwrap_or_default();
-    let enabled_sys_packs: HashSet<String> = AdbCommand::new()
-        .shell(device_serial)
-        .pm()
-        .list_packages_sys(Some(PmListPacksFlag::OnlyEnabled), user_id)
+    let enabled_sys_packs: HashSet<String> = backend()
+        .list_packages_sys(device_serial, Some(PmListPacksFlag::OnlyEnabled), user_id)
         .unwrap_or_default()
         .into_iter()
         .collect();
-    let disabled_sys_packs: HashSet<String> = AdbCommand::new()
-        .shell(device_serial)
-        .pm()
-        .list_packages_sys(Some(PmListPacksFlag::OnlyDisabled), user_id)
+    let disabled_sys_packs: HashSet<String> = backend()
+        .list_packages_sys(device_serial, Some(PmListPacksFlag::OnlyDisabled), user_id)
         .unwrap_or_default()
         .into_iter()
         .collect();
 
-    let mut description;
-    let mut uad_list;
-    let mut state;
-    let mut removal;
-    let mut user_package: Vec<PackageRow> = Vec::new();
-
-    for pack_name in all_sys_packs {
-        let p_name = &pack_name;
-        state = PackageState::Uninstalled;
-        description = "[No description]: CONTRIBUTION WELCOMED";
-        uad_list = UadList::Unlisted;
-        removal = Removal::Unlisted;
-
-        if let Some(package) = uad_lists.get(p_name) {
-            if !package.description.is_empty() {
-                description = &package.description;
-            }
-            uad_list = package.list;
-            removal = package.removal;
-        }
+    let brand = get_device_brand(device_serial);
+    let mut user_package: Vec<PackageRow> = all_sys_packs
+        .iter()
+        .map(|p_name| {
+            let state = if enabled_sys_packs.contains(p_name) {
+                PackageState::Enabled
+            } else if disabled_sys_packs.contains(p_name) {
+                PackageState::Disabled
+            } else {
+                PackageState::Uninstalled
+            };
+            let mut row = package_row(uad_lists, p_name, state, &brand);
+            row.disabled_until_used =
+                state == PackageState::Disabled && until_used.contains(p_name);
+            row
+        })
+        .collect();
+    user_package.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
+    user_package
+}
 
-        if enabled_sys_packs.contains(p_name) {
-            state = PackageState::Enabled;
-        } else if disabled_sys_packs.contains(p_name) {
-            state = PackageState::Disabled;
+/// The row of the package `name`, described from `uad_lists`
+pub fn package_row(
+    uad_lists: &PackageHashMap,
+    name: &str,
+    state: PackageState,
+    brand: &str,
+) -> PackageRow {
+    let mut description = "[No description]: CONTRIBUTION WELCOMED";
+    let mut uad_list = UadList::Unlisted;
+    let mut removal = Removal::Unlisted;
+    if let Some(package) = uad_lists.get(name) {
+        if !package.description.is_empty() {
+            description = &package.description;
         }
-
-        let package_row =
-            PackageRow::new(p_name, state, description, uad_list, removal, false, false);
-        user_package.push(package_row);
+        uad_list = package.list;
+        removal = package.removal;
     }
-    user_package.sort_by(|a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()));
-    user_package
+    let mut row = PackageRow::new(name, state, description, uad_list, removal, false, false);
+    row.origin = Origin::classify(name, brand);
+    row
 }
 
 pub fn string_to_theme(theme: &str) -> Theme {
@@ -135,6 +146,37 @@ pub fn string_to_theme(theme: &str) -> Theme {
     }
 }
 
+/// CLI flag that keeps the config and cache beside the executable,
+/// for example to run from a USB stick without traces on the computer
+pub const PORTABLE_FLAG: &str = "--portable";
+/// File beside the executable, with the same effect as [`PORTABLE_FLAG`]
+pub const PORTABLE_MARKER: &str = "portable.txt";
+
+/// The folder of the executable, in portable mode (see [`PORTABLE_FLAG`])
+pub fn portable_dir() -> Option<PathBuf> {
+    let exe_dir = std::env::current_exe().ok()?.parent()?.to_path_buf();
+    is_portable(std::env::args().skip(1), &exe_dir).then_some(exe_dir)
+}
+
+fn is_portable(mut args: impl Iterator<Item = String>, exe_dir: &Path) -> bool {
+    args.any(|arg| arg == PORTABLE_FLAG) || exe_dir.join(PORTABLE_MARKER).is_file()
+}
+
+/// `os_dir` (such as [`dirs::config_dir`]), or `name` beside the executable in portable mode.
+///
+/// Headless systems may have no such folder: `uad-{name}` in the temp dir is used instead.
+pub fn data_dir(os_dir: Option<PathBuf>, name: &str) -> PathBuf {
+    portable_dir()
+        .map(|d| d.join(name))
+        .or(os_dir)
+        .unwrap_or_else(|| fallback_data_dir(name))
+}
+
+/// See [`data_dir`]
+pub fn fallback_data_dir(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("uad-{name}"))
+}
+
 pub fn setup_uad_dir(dir: &Path) -> PathBuf {
     let dir = dir.join("uad");
     if let Err(e) = fs::create_dir_all(&dir) {
@@ -185,9 +227,16 @@ pub fn format_diff_time_from_now(date: DateTime<Utc>) -> String {
     }
 }
 
-/// Export selected packages.
-/// File will be saved in same directory where UAD-ng is located.
-pub async fn export_selection(packages: Vec<PackageRow>) -> Result<bool, String> {
+/// Name of a selection export made at `t`.
+/// Timestamped, like backups, so previous exports aren't overwritten.
+#[must_use]
+pub fn generate_export_name(t: chrono::NaiveDateTime) -> String {
+    t.format("selection_export_%Y%m%d_%H%M%S.txt").to_string()
+}
+
+/// Export selected packages into `dir`.
+/// Returns the path of the file.
+pub async fn export_selection(packages: Vec<PackageRow>, dir: PathBuf) -> Result<PathBuf, String> {
     let selected = packages
         .iter()
         .filter(|p| p.selected)
@@ -195,12 +244,277 @@ pub async fn export_selection(packages: Vec<PackageRow>) -> Result<bool, String>
         .collect::<Vec<String>>()
         .join("\n");
 
-    match fs::write(EXPORT_FILE_NAME, selected) {
-        Ok(()) => Ok(true),
+    check_writable(&dir)?;
+    let path = dir.join(generate_export_name(chrono::Local::now().naive_local()));
+    match fs::write(&path, selected) {
+        Ok(()) => Ok(path),
         Err(err) => Err(err.to_string()),
     }
 }
 
+/// Human-readable size, such as "1.2 GB"
+#[must_use]
+#[expect(
+    clippy::cast_precision_loss,
+    reason = "1 decimal is displayed, precision is irrelevant"
+)]
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["KB", "MB", "GB", "TB"];
+    if bytes < 1024 {
+        return format!("{bytes} B");
+    }
+    let mut size = bytes as f64 / 1024.0;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    format!("{size:.1} {}", UNITS[unit])
+}
+
+/// Rough time left to process `total` items when the first `done` took `elapsed`,
+/// such as "about 2 min left". `None` until there's something to extrapolate from.
+#[must_use]
+pub fn format_eta(done: usize, total: usize, elapsed: Duration) -> Option<String> {
+    if done == 0 || done >= total {
+        return None;
+    }
+    let left_ms = elapsed.as_millis() * (total - done) as u128 / done as u128;
+    let left = left_ms.div_ceil(1000).max(1);
+    Some(if left < 60 {
+        format!("about {left} s left")
+    } else {
+        format!("about {} min left", left.div_ceil(60))
+    })
+}
+
+/// A subset of regular expressions, limited to what's useful for package names,
+/// see [`PATTERN_SYNTAX`]. Other regex syntax is rejected, rather than matched literally.
+///
+/// Like with regexes, it matches anywhere in the text unless anchored.
+/// Matching runs in `O(text × pattern)`: there's no backtracking.
+/// What a [`Pattern`] supports, as told to the user
+pub const PATTERN_SYNTAX: &str = "`.` any character, `*` repeats it 0 or more times, \
+`+` 1 or more, `?` makes it optional; `^` and `$` anchor the start and the end.
+Escape `.` and the other special characters with `\\`, e.g. `^com\\.google\\.`";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pattern {
+    pieces: Vec<(Atom, Repeat)>,
+    anchored_start: bool,
+    anchored_end: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Atom {
+    Any,
+    Char(char),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Repeat {
+    One,
+    Optional,
+    Star,
+}
+
+impl Pattern {
+    pub fn new(pattern: &str) -> Result<Self, String> {
+        let (anchored_start, pattern) = match pattern.strip_prefix('^') {
+            Some(p) => (true, p),
+            None => (false, pattern),
+        };
+        let (anchored_end, pattern) = match pattern.strip_suffix('$') {
+            Some(p) if !p.ends_with('\\') => (true, p),
+            _ => (false, pattern),
+        };
+
+        let mut pieces: Vec<(Atom, Repeat)> = vec![];
+        let mut chars = pattern.chars();
+        while let Some(c) = chars.next() {
+            let atom = match c {
+                '.' => Atom::Any,
+                '\\' => Atom::Char(chars.next().ok_or("Trailing `\\`")?),
+                '*' | '+' | '?' => {
+                    let Some(&mut (atom, ref mut r @ Repeat::One)) = pieces.last_mut() else {
+                        return Err(format!("Nothing to repeat before `{c}`"));
+                    };
+                    match c {
+                        '*' => *r = Repeat::Star,
+                        '?' => *r = Repeat::Optional,
+                        // `a+` is `aa*`
+                        _ => pieces.push((atom, Repeat::Star)),
+                    }
+                    continue;
+                }
+                '[' | ']' | '(' | ')' | '{' | '}' | '|' | '^' | '$' => {
+                    return Err(format!("`{c}` is not supported, escape it with `\\`"));
+                }
+                c => Atom::Char(c),
+            };
+            pieces.push((atom, Repeat::One));
+        }
+        Ok(Self {
+            pieces,
+            anchored_start,
+            anchored_end,
+        })
+    }
+
+    /// Tracks every piece the text may have reached at once, instead of trying them in turn
+    #[must_use]
+    pub fn is_match(&self, text: &str) -> bool {
+        let end = self.pieces.len();
+        let mut reached = vec![false; end + 1];
+        self.reach(&mut reached, 0);
+        for c in text.chars() {
+            if reached[end] && !self.anchored_end {
+                return true;
+            }
+            let mut next = vec![false; end + 1];
+            for (i, &(atom, repeat)) in self.pieces.iter().enumerate() {
+                if reached[i] && (atom == Atom::Any || atom == Atom::Char(c)) {
+                    let to = if repeat == Repeat::Star { i } else { i + 1 };
+                    self.reach(&mut next, to);
+                }
+            }
+            if !self.anchored_start {
+                self.reach(&mut next, 0);
+            }
+            reached = next;
+        }
+        reached[end]
+    }
+
+    /// Marks piece `i` as reached, and the ones after it that may match nothing
+    fn reach(&self, reached: &mut [bool], mut i: usize) {
+        while !reached[i] {
+            reached[i] = true;
+            match self.pieces.get(i) {
+                Some((_, Repeat::Optional | Repeat::Star)) => i += 1,
+                _ => break,
+            }
+        }
+    }
+}
+
+/// Piece of free-form text, see [`linkify`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Segment<'a> {
+    Text(&'a str),
+    Link(&'a str),
+}
+
+/// Split `s` into plain text and `http(s)://` URLs.
+///
+/// A URL ends at the first whitespace.
+/// Trailing punctuation is assumed to belong to the surrounding sentence.
+#[must_use]
+pub fn linkify(s: &str) -> Vec<Segment<'_>> {
+    let mut segments = vec![];
+    let mut rest = s;
+    while let Some(start) = ["https://", "http://"]
+        .iter()
+        .filter_map(|scheme| rest.find(scheme))
+        .min()
+    {
+        let len = rest[start..]
+            .find(char::is_whitespace)
+            .unwrap_or(rest.len() - start);
+        let url = rest[start..start + len]
+            .trim_end_matches(['.', ',', ';', ':', '!', '?', ')', ']', '>', '\'', '"']);
+        if start > 0 {
+            segments.push(Segment::Text(&rest[..start]));
+        }
+        segments.push(Segment::Link(url));
+        rest = &rest[start + url.len()..];
+    }
+    if !rest.is_empty() {
+        segments.push(Segment::Text(rest));
+    }
+    segments
+}
+
+/// Check that files can be created in `dir` (creating it if needed),
+/// by writing and deleting a probe file.
+///
+/// This catches unplugged drives and read-only volumes
+/// before any real work is done.
+pub fn check_writable(dir: &Path) -> Result<(), String> {
+    let not_writable = |e: std::io::Error| {
+        error!("{}: {e}", dir.display());
+        format!("Folder is not writable: {}", dir.display())
+    };
+    fs::create_dir_all(dir).map_err(not_writable)?;
+    let probe = dir.join(".uadng_write_test");
+    fs::write(&probe, []).map_err(not_writable)?;
+    fs::remove_file(&probe).map_err(not_writable)
+}
+
+/// `device_id`, usable as a file name.
+/// Serials of TCP devices contain `:`, which isn't allowed in Windows file names.
+pub fn device_file_stem(device_id: &str) -> String {
+    device_id
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '.' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Where the in-progress selection of a user of a device is kept between sessions
+fn selection_file(dir: &Path, device_id: &str, user_id: u16) -> PathBuf {
+    dir.join("selections")
+        .join(device_file_stem(device_id))
+        .join(format!("{user_id}.txt"))
+}
+
+/// Save the names of the selected packages of a user of a device,
+/// in the same format as [`export_selection`].
+/// An empty selection removes the saved file.
+pub fn save_selection(device_id: &str, user_id: u16, names: &[String]) -> Result<(), String> {
+    write_selection(&selection_file(&CONFIG_DIR, device_id, user_id), names)
+}
+
+/// Names of the packages saved by [`save_selection`], if any
+pub fn load_selection(device_id: &str, user_id: u16) -> Vec<String> {
+    read_selection(&selection_file(&CONFIG_DIR, device_id, user_id))
+}
+
+fn write_selection(path: &Path, names: &[String]) -> Result<(), String> {
+    if names.is_empty() {
+        return match fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        };
+    }
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|e| e.to_string())?;
+    }
+    fs::write(path, names.join("\n")).map_err(|e| e.to_string())
+}
+
+fn read_selection(path: &Path) -> Vec<String> {
+    fs::read_to_string(path)
+        .map(|s| parse_selection(&s))
+        .unwrap_or_default()
+}
+
+/// Package names of a selection in the format of [`export_selection`]: 1 per line.
+/// Blank lines and surrounding spaces are ignored.
+#[must_use]
+pub fn parse_selection(s: &str) -> Vec<String> {
+    s.lines()
+        .map(str::trim)
+        .filter(|l| !l.is_empty())
+        .map(String::from)
+        .collect()
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct DisplayablePath {
     pub path: PathBuf,
@@ -236,15 +550,39 @@ pub async fn open_folder() -> Result<PathBuf, Error> {
     Ok(picked_folder.path().to_owned())
 }
 
+/// Choose a backup file, starting from `dir`
+pub async fn open_backup_file(dir: PathBuf) -> Result<PathBuf, Error> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .set_directory(dir)
+        .add_filter("Backup", &["json"])
+        .pick_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    Ok(picked_file.path().to_owned())
+}
+
+/// Choose a script, e.g. for [`crate::core::hooks`]
+pub async fn open_script_file() -> Result<PathBuf, Error> {
+    let picked_file = rfd::AsyncFileDialog::new()
+        .pick_file()
+        .await
+        .ok_or(Error::DialogClosed)?;
+
+    Ok(picked_file.path().to_owned())
+}
+
 /// Export uninstalled packages in a csv file.
 /// Exported information will contain package name and description.
 pub async fn export_packages(
     user: User,
     phone_packages: Vec<Vec<PackageRow>>,
-) -> Result<bool, String> {
-    let backup_file = generate_backup_name(chrono::Local::now());
+    dir: PathBuf,
+) -> Result<PathBuf, String> {
+    check_writable(&dir)?;
+    let path = dir.join(generate_backup_name(chrono::Local::now()));
 
-    let file = fs::File::create(backup_file).map_err(|err| err.to_string())?;
+    let file = fs::File::create(&path).map_err(|err| err.to_string())?;
     let mut wtr = Writer::from_writer(file);
 
     wtr.write_record(["Package Name", "Description"])
@@ -262,7 +600,98 @@ pub async fn export_packages(
 
     wtr.flush().map_err(|err| err.to_string())?;
 
-    Ok(true)
+    Ok(path)
+}
+
+/// Packages removed (disabled or uninstalled) for any user that aren't in any list,
+/// one `<state> <package>` line each, sorted.
+///
+/// Only package names: nothing identifies the device or its owner.
+#[must_use]
+pub fn uncategorized_report(phone_packages: &[Vec<PackageRow>]) -> String {
+    let removed: BTreeSet<(String, &str)> = phone_packages
+        .iter()
+        .flatten()
+        .filter(|p| p.uad_list == UadList::Unlisted)
+        .filter_map(|p| match p.state {
+            PackageState::Disabled => Some((p.name.clone(), "disabled")),
+            PackageState::Uninstalled => Some((p.name.clone(), "uninstalled")),
+            PackageState::Enabled | PackageState::All => None,
+        })
+        .collect();
+    std::iter::once("# Removed packages missing from the UAD-ng lists".to_string())
+        .chain(
+            removed
+                .into_iter()
+                .map(|(name, state)| format!("{state} {name}")),
+        )
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Write the [`uncategorized_report`] into a file in `dir`,
+/// to be attached to an issue. Returns the path of the file.
+pub async fn export_uncategorized_report(
+    phone_packages: Vec<Vec<PackageRow>>,
+    dir: PathBuf,
+) -> Result<PathBuf, String> {
+    check_writable(&dir)?;
+    let path = dir.join(
+        chrono::Local::now()
+            .format("uncategorized_report_%Y%m%d_%H%M%S.txt")
+            .to_string(),
+    );
+    fs::write(&path, uncategorized_report(&phone_packages)).map_err(|e| e.to_string())?;
+    Ok(path)
+}
+
+/// Write every package of `user`, whatever its state, as CSV rows with a header.
+/// Descriptions are kept as-is, the CSV quoting takes care of commas and newlines.
+fn write_inventory<W: std::io::Write>(
+    wtr: &mut Writer<W>,
+    user: &User,
+    packages: &[PackageRow],
+) -> csv::Result<()> {
+    wtr.write_record([
+        "Package Name",
+        "State",
+        "Removal",
+        "List",
+        "User",
+        "Description",
+    ])?;
+    let user_id = user.id.to_string();
+    for p in packages {
+        wtr.write_record([
+            p.name.as_str(),
+            &p.state.to_string(),
+            &p.removal.to_string(),
+            &p.uad_list.to_string(),
+            &user_id,
+            &p.description,
+        ])?;
+    }
+    wtr.flush()?;
+    Ok(())
+}
+
+/// Export all packages of `user` into a CSV file in `dir`,
+/// for analysis in a spreadsheet. Returns the path of the file.
+pub async fn export_inventory_csv(
+    user: User,
+    packages: Vec<PackageRow>,
+    dir: PathBuf,
+) -> Result<PathBuf, String> {
+    check_writable(&dir)?;
+    let path = dir.join(
+        chrono::Local::now()
+            .format("package_inventory_%Y%m%d_%H%M%S.csv")
+            .to_string(),
+    );
+    let file = fs::File::create(&path).map_err(|err| err.to_string())?;
+    write_inventory(&mut Writer::from_writer(file), &user, &packages)
+        .map_err(|err| err.to_string())?;
+    Ok(path)
 }
 
 #[cfg(test)]
@@ -270,6 +699,65 @@ mod tests {
     use super::*;
     use chrono::TimeZone;
 
+    #[test]
+    fn export_name() {
+        let t = chrono::NaiveDate::from_ymd_opt(2024, 3, 9)
+            .and_then(|d| d.and_hms_opt(7, 5, 30))
+            .expect("valid date");
+        assert_eq!(
+            generate_export_name(t),
+            "selection_export_20240309_070530.txt"
+        );
+    }
+
+    #[test]
+    fn uncategorized() {
+        let row = |name, state, list| {
+            PackageRow::new(name, state, "", list, Removal::Unlisted, false, false)
+        };
+        let packages = vec![
+            vec![
+                row("com.b", PackageState::Uninstalled, UadList::Unlisted),
+                row("com.a", PackageState::Disabled, UadList::Unlisted),
+                row("com.c", PackageState::Enabled, UadList::Unlisted),
+                row("com.listed", PackageState::Uninstalled, UadList::Oem),
+            ],
+            // same package on another user
+            vec![row("com.b", PackageState::Uninstalled, UadList::Unlisted)],
+        ];
+        assert_eq!(
+            uncategorized_report(&packages),
+            "# Removed packages missing from the UAD-ng lists\ndisabled com.a\nuninstalled com.b"
+        );
+    }
+
+    #[test]
+    fn inventory_csv() {
+        let packages = vec![PackageRow::new(
+            "com.example.a",
+            PackageState::Disabled,
+            "Tracks you, \"badly\".\nSee docs",
+            UadList::Oem,
+            Removal::Advanced,
+            false,
+            false,
+        )];
+        let user = User {
+            id: 10,
+            index: 1,
+            protected: false,
+            ..User::default()
+        };
+        let mut wtr = Writer::from_writer(vec![]);
+        write_inventory(&mut wtr, &user, &packages).expect("in-memory writes");
+        let csv = String::from_utf8(wtr.into_inner().expect("flushed")).expect("UTF-8");
+        assert_eq!(
+            csv,
+            "Package Name,State,Removal,List,User,Description\n\
+            com.example.a,Disabled,Advanced,oem,10,\"Tracks you, \"\"badly\"\".\nSee docs\"\n"
+        );
+    }
+
     #[test]
     fn backup_name() {
         assert_eq!(
@@ -277,4 +765,114 @@ mod tests {
             "uninstalled_packages_19700101.csv".to_string()
         );
     }
+
+    #[test]
+    fn selection_round_trip() {
+        let dir = std::env::temp_dir().join(format!("uadng_selection_{}", std::process::id()));
+        let path = selection_file(&dir, "192.168.1.2:5555", 10);
+        assert!(path.ends_with(Path::new("selections/192.168.1.2_5555/10.txt")));
+        let names = vec!["com.example.a".to_string(), "org.example.b".to_string()];
+        write_selection(&path, &names).expect("selection should be saved");
+        assert_eq!(read_selection(&path), names);
+        write_selection(&path, &[]).expect("selection should be removed");
+        assert!(read_selection(&path).is_empty());
+        let _ = fs::remove_dir_all(&dir);
+
+        assert_eq!(
+            parse_selection("com.example.a\r\n\n  org.example.b \n"),
+            names
+        );
+        assert!(parse_selection(" \n").is_empty());
+    }
+
+    #[test]
+    fn human_sizes() {
+        assert_eq!(format_size(0), "0 B");
+        assert_eq!(format_size(1023), "1023 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(1_288_490_189), "1.2 GB");
+    }
+
+    #[test]
+    fn etas() {
+        let secs = Duration::from_secs;
+        assert_eq!(format_eta(0, 100, secs(3)), None);
+        assert_eq!(format_eta(100, 100, secs(3)), None);
+        assert_eq!(
+            format_eta(50, 100, secs(3)).as_deref(),
+            Some("about 3 s left")
+        );
+        assert_eq!(
+            format_eta(10, 100, secs(20)).as_deref(),
+            Some("about 3 min left")
+        );
+        assert_eq!(
+            format_eta(99, 100, Duration::from_millis(10)).as_deref(),
+            Some("about 1 s left")
+        );
+    }
+
+    #[test]
+    fn patterns() {
+        let matches = |p: &str, s: &str| Pattern::new(p).expect("valid pattern").is_match(s);
+        assert!(matches("com.sec.*", "com.sec.android.app"));
+        assert!(matches("sec", "com.sec.android.app"));
+        assert!(!matches("^sec", "com.sec.android.app"));
+        assert!(matches("^com\\.google\\.", "com.google.android.gm"));
+        assert!(!matches("^com\\.google\\.", "com.googlex"));
+        assert!(matches("\\.gm$", "com.google.android.gm"));
+        assert!(!matches("\\.gm$", "com.google.android.gms"));
+        assert!(matches("^a+b?c*$", "aaac"));
+        assert!(!matches("^a+b?c*$", "bc"));
+        assert!(matches("a+$", "baa"));
+        assert!(!matches("^.*x.+$", "ax"));
+        // would take ages with backtracking
+        let text = "a".repeat(40);
+        assert!(matches(&format!("^{}{text}$", "a?".repeat(40)), &text));
+        assert!(!matches(&format!("^{}b", "a*".repeat(40)), &text));
+        assert!(Pattern::new("*a").is_err());
+        assert!(Pattern::new("a**").is_err());
+        assert!(Pattern::new("(a|b)").is_err());
+        assert!(Pattern::new("a\\").is_err());
+    }
+
+    #[test]
+    fn linkify_urls() {
+        use Segment::{Link, Text};
+        assert_eq!(linkify(""), vec![]);
+        assert_eq!(linkify("no links"), vec![Text("no links")]);
+        assert_eq!(
+            linkify("See https://example.org/a_b. Or (http://x.y)"),
+            vec![
+                Text("See "),
+                Link("https://example.org/a_b"),
+                Text(". Or ("),
+                Link("http://x.y"),
+                Text(")"),
+            ]
+        );
+        assert_eq!(linkify("https://a.b"), vec![Link("https://a.b")]);
+    }
+
+    #[test]
+    fn writable_folder() {
+        let dir = std::env::temp_dir().join("uadng_writable_test");
+        assert_eq!(check_writable(&dir), Ok(()));
+        assert!(!dir.join(".uadng_write_test").exists());
+        let file = dir.join("not_a_dir");
+        fs::write(&file, []).expect("temp dir should be writable");
+        assert!(check_writable(&file).is_err());
+    }
+
+    #[test]
+    fn portable_mode() {
+        let dir = std::env::temp_dir().join(format!("uadng_portable_{}", std::process::id()));
+        fs::create_dir_all(&dir).expect("temp dir should be writable");
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert!(!is_portable(args("--mock").into_iter(), &dir));
+        assert!(is_portable(args("--mock --portable").into_iter(), &dir));
+        fs::write(dir.join(PORTABLE_MARKER), []).expect("temp dir should be writable");
+        assert!(is_portable(args("").into_iter(), &dir));
+        let _ = fs::remove_dir_all(&dir);
+    }
 }
@@ -2,9 +2,14 @@ pub mod style;
 pub mod views;
 pub mod widgets;
 
-use crate::core::adb;
-use crate::core::sync::{Phone, get_devices_list, initial_load};
-use crate::core::theme::{OS_COLOR_SCHEME, Theme};
+use crate::core::backend::backend;
+use crate::core::config::Config;
+use crate::core::save::offline_phone;
+use crate::core::sync::{
+    AdbError, Phone, get_devices_list, get_devices_status, initial_load, is_fake_adb,
+    pin_favorites, revalidate_device_names, wait_for_reconnect,
+};
+use crate::core::theme::{Theme, detect_os_theme};
 use crate::core::uad_lists::UadListState;
 use crate::core::update::{Release, SelfUpdateState, SelfUpdateStatus, get_latest_release};
 use crate::core::utils::{NAME, string_to_theme};
@@ -13,20 +18,26 @@ use iced::advanced::graphics::image::image_rs::ImageFormat;
 use iced::font;
 use iced::window::icon;
 use views::about::{About as AboutView, Message as AboutMessage};
-use views::list::{List as AppsView, LoadingState as ListLoadingState, Message as AppsMessage};
+use views::list::{
+    List as AppsView, LoadingState as ListLoadingState, Message as AppsMessage, Preset,
+};
 use views::settings::{Message as SettingsMessage, Settings as SettingsView};
+use widgets::command_palette::{PALETTE_INPUT, command_palette};
 use widgets::navigation_menu::nav_menu;
 
-use iced::widget::column;
+use iced::widget::{column, text_input};
 use iced::{
-    Alignment, Application, Command, Element, Length, Renderer, Settings,
+    Alignment, Application, Command, Element, Length, Renderer, Settings, Subscription,
     window::Settings as Window,
 };
+use std::collections::HashSet;
 #[cfg(feature = "self-update")]
 use std::path::PathBuf;
 
 #[cfg(feature = "self-update")]
-use crate::core::update::{BIN_NAME, download_update_to_temp_file, remove_file};
+use crate::core::update::{
+    BIN_NAME, DownloadProgress, download_update_to_temp_file, relaunch_args, rename,
+};
 
 #[derive(Default, Debug, Clone)]
 enum View {
@@ -54,6 +65,10 @@ pub struct UadGui {
     update_state: UpdateState,
     nb_running_async_adb_commands: u32,
     adb_satisfied: bool,
+    /// What `Theme::Auto` currently stands for: `Dark` or `Light`
+    os_theme: Theme,
+    /// Search of the command palette, while it's open
+    palette_query: Option<String>,
 }
 
 #[derive(Debug, Clone)]
@@ -66,15 +81,32 @@ pub enum Message {
     AboutAction(AboutMessage),
     AppsAction(AppsMessage),
     SettingsAction(SettingsMessage),
+    /// Look for the connected devices again, starting over
     RefreshButtonPressed,
+    /// Reload the packages of the selected device only
+    ReloadDevicePressed,
     RebootButtonPressed,
+    /// Reboot, and reselect the device once it's back
+    RebootAndWaitPressed,
+    /// Whether the device came back in time
+    RebootedDeviceBack(bool),
     LoadDevices(Vec<Phone>),
+    /// `(serial, name)` of the devices whose cached name was outdated
+    DeviceNamesRevalidated(Vec<(String, String)>),
     #[cfg(feature = "self-update")]
-    _NewReleaseDownloaded(Result<(PathBuf, PathBuf), ()>),
+    NewReleaseDownloaded(Result<(PathBuf, PathBuf), String>),
+    #[cfg(feature = "self-update")]
+    SelfUpdateProgress(DownloadProgress),
     GetLatestRelease(Result<Option<Release>, ()>),
     FontLoaded(Result<(), iced::font::Error>),
     Nothing,
     ADBSatisfied(bool),
+    OsThemeChanged(Theme),
+    /// Open or close the command palette (Ctrl+K)
+    TogglePalette,
+    PaletteInput(String),
+    /// Close the command palette and run this command
+    PaletteRun(Box<Message>),
 }
 
 impl Application for UadGui {
@@ -84,24 +116,82 @@ impl Application for UadGui {
     type Flags = ();
 
     fn new(_flags: ()) -> (Self, Command<Message>) {
+        let mut gui = Self {
+            os_theme: detect_os_theme(),
+            ..Self::default()
+        };
+        let check_release = gui.check_self_update();
         (
-            Self::default(),
+            gui,
             Command::batch([
                 // Used in crate::gui::widgets::navigation_menu::ICONS. Name is `icomoon`.
                 font::load(include_bytes!("../../resources/assets/icons.ttf").as_slice())
                     .map(Message::FontLoaded),
                 Command::perform(initial_load(), Message::ADBSatisfied),
+                Command::perform(is_fake_adb(), |fake| {
+                    Message::AppsAction(AppsMessage::FakeAdb(fake))
+                }),
                 Command::perform(get_devices_list(), Message::LoadDevices),
-                Command::perform(
-                    async move { get_latest_release() },
-                    Message::GetLatestRelease,
-                ),
+                check_release,
             ]),
         )
     }
 
     fn theme(&self) -> Theme {
-        string_to_theme(&self.settings_view.general.theme)
+        match string_to_theme(&self.settings_view.general.theme) {
+            Theme::Auto => self.os_theme,
+            theme => theme,
+        }
+    }
+
+    fn subscription(&self) -> Subscription<Message> {
+        let os_theme = if string_to_theme(&self.settings_view.general.theme) == Theme::Auto {
+            os_theme_changes()
+        } else {
+            Subscription::none()
+        };
+        let live_state_sync =
+            if self.settings_view.general.live_state_sync && self.selected_device.is_some() {
+                live_state_ticks()
+            } else {
+                Subscription::none()
+            };
+        // while a modal is open, Escape is the only shortcut
+        let keyboard = match self.view {
+            _ if self.palette_query.is_some() => escape_closes_modal(|| Message::TogglePalette),
+            View::List if self.apps_view.has_modal() => {
+                escape_closes_modal(|| Message::AppsAction(AppsMessage::ModalHide))
+            }
+            View::Settings if self.settings_view.has_modal() => {
+                escape_closes_modal(|| Message::SettingsAction(SettingsMessage::ModalHide))
+            }
+            View::List if self.selected_device.is_some() => list_keyboard(),
+            _ => Subscription::none(),
+        };
+        let spinner = if self.apps_view.is_busy() {
+            spinner_ticks()
+        } else {
+            Subscription::none()
+        };
+        #[cfg(feature = "self-update")]
+        let self_update = match &self.update_state.self_update {
+            SelfUpdateState {
+                status: SelfUpdateStatus::Updating,
+                latest_release: Some(release),
+                ..
+            } => self_update_download(release.clone()),
+            _ => Subscription::none(),
+        };
+        #[cfg(not(feature = "self-update"))]
+        let self_update = Subscription::none();
+        Subscription::batch([
+            os_theme,
+            live_state_sync,
+            keyboard,
+            palette_shortcut(),
+            spinner,
+            self_update,
+        ])
     }
 
     fn title(&self) -> String {
@@ -110,7 +200,11 @@ impl Application for UadGui {
     #[allow(clippy::too_many_lines)]
     fn update(&mut self, msg: Message) -> Command<Message> {
         match msg {
-            Message::LoadDevices(devices_list) => {
+            Message::LoadDevices(mut devices_list) => {
+                pin_favorites(
+                    &mut devices_list,
+                    &favorite_devices(&Config::load_configuration_file()),
+                );
                 self.selected_device = match &self.selected_device {
                     Some(s_device) => {
                         // Try to reload last selected phone
@@ -119,32 +213,68 @@ impl Application for UadGui {
                             .find(|phone| phone.adb_id == s_device.adb_id)
                             .cloned()
                     }
-                    None => devices_list.first().cloned(),
+                    None if self.settings_view.general.auto_select_device => {
+                        devices_list.first().cloned()
+                    }
+                    None => None,
                 };
+                let no_devices = devices_list.is_empty();
+                let revalidate_names = Command::perform(
+                    revalidate_device_names(devices_list.clone()),
+                    Message::DeviceNamesRevalidated,
+                );
                 self.devices_list = devices_list;
 
                 #[expect(unused_must_use, reason = "side-effect")]
                 {
                     self.update(Message::SettingsAction(SettingsMessage::LoadDeviceSettings));
                 }
+                if self.selected_device.is_none() && !no_devices {
+                    // see `GeneralSettings::auto_select_device`
+                    self.apps_view.loading_state = ListLoadingState::SelectingDevice;
+                    return revalidate_names;
+                }
 
-                self.update(Message::AppsAction(AppsMessage::LoadUadList(true)))
+                let load_lists = self.update(Message::AppsAction(AppsMessage::LoadUadList(true)));
+                if no_devices {
+                    // tell the user why, if we can
+                    Command::batch([
+                        load_lists,
+                        Command::perform(get_devices_status(), |statuses| {
+                            Message::AppsAction(AppsMessage::DevicesStatus(statuses))
+                        }),
+                    ])
+                } else {
+                    Command::batch([load_lists, revalidate_names])
+                }
+            }
+            Message::DeviceNamesRevalidated(names) => {
+                for (serial, name) in names {
+                    for device in self
+                        .devices_list
+                        .iter_mut()
+                        .chain(&mut self.selected_device)
+                        .filter(|d| d.adb_id == serial)
+                    {
+                        device.model.clone_from(&name);
+                    }
+                }
+                Command::none()
             }
             Message::AppsPress => {
                 self.view = View::List;
-                Command::none()
+                // Alt may have been released while the list didn't listen
+                self.update(Message::AppsAction(AppsMessage::InvertMode(false)))
             }
             Message::AboutPressed => {
                 self.view = View::About;
-                self.update_state.self_update = SelfUpdateState::default();
-                Command::perform(
-                    async move { get_latest_release() },
-                    Message::GetLatestRelease,
-                )
+                self.check_self_update()
             }
             Message::SettingsPressed => {
                 self.view = View::Settings;
-                Command::none()
+                self.settings_view
+                    .scroll_to_theme()
+                    .map(Message::SettingsAction)
             }
             Message::RefreshButtonPressed => {
                 self.apps_view = AppsView::default();
@@ -154,7 +284,19 @@ impl Application for UadGui {
                         self.adb_satisfied,
                     )));
                 }
-                Command::perform(get_devices_list(), Message::LoadDevices)
+                Command::batch([
+                    Command::perform(is_fake_adb(), |fake| {
+                        Message::AppsAction(AppsMessage::FakeAdb(fake))
+                    }),
+                    Command::perform(get_devices_list(), Message::LoadDevices),
+                ])
+            }
+            Message::ReloadDevicePressed => {
+                if self.selected_device.is_none() {
+                    return self.update(Message::RefreshButtonPressed);
+                }
+                self.view = View::List;
+                self.update(Message::AppsAction(AppsMessage::ReloadPackages))
             }
             Message::RebootButtonPressed => {
                 self.apps_view = AppsView::default();
@@ -164,21 +306,73 @@ impl Application for UadGui {
                 };
                 self.selected_device = None;
                 self.devices_list = vec![];
+                Command::perform(async move { backend().reboot(&serial) }, |_| {
+                    Message::Nothing
+                })
+            }
+            Message::RebootAndWaitPressed => {
+                let Some(device) = self.selected_device.clone() else {
+                    return Command::none();
+                };
+                self.apps_view = AppsView::default();
+                self.apps_view.loading_state = ListLoadingState::Rebooting(device.model);
+                self.view = View::List;
+                let serial = device.adb_id;
                 Command::perform(
-                    async { adb::ACommand::new().shell(serial).reboot() },
-                    |_| Message::Nothing,
+                    async move {
+                        if let Err(err) = backend().reboot(&serial) {
+                            error!("Reboot failed: {err}");
+                            return false;
+                        }
+                        wait_for_reconnect(serial, REBOOT_TIMEOUT).await
+                    },
+                    Message::RebootedDeviceBack,
                 )
             }
-            Message::AppsAction(msg) => self
-                .apps_view
-                .update(
-                    &mut self.settings_view,
-                    &mut self.selected_device.clone().unwrap_or_default(),
-                    &mut self.update_state.uad_list,
-                    msg,
-                )
-                .map(Message::AppsAction),
+            Message::RebootedDeviceBack(back) => {
+                if back {
+                    // `LoadDevices` reselects `selected_device`
+                    Command::perform(get_devices_list(), Message::LoadDevices)
+                } else {
+                    let model = self
+                        .selected_device
+                        .take()
+                        .map(|d| d.model)
+                        .unwrap_or_default();
+                    self.devices_list = vec![];
+                    self.apps_view.loading_state = ListLoadingState::RebootTimedOut(model);
+                    Command::none()
+                }
+            }
+            Message::AppsAction(AppsMessage::GoToSettings) => self.update(Message::SettingsPressed),
+            Message::AppsAction(msg) => {
+                if let AppsMessage::ChangePackageState(_, _, Err(AdbError::DeviceOffline { .. })) =
+                    &msg
+                {
+                    self.selected_device = None;
+                }
+                self.apps_view
+                    .update(
+                        &mut self.settings_view,
+                        &mut self.selected_device.clone().unwrap_or_default(),
+                        &mut self.update_state.uad_list,
+                        msg,
+                    )
+                    .map(Message::AppsAction)
+            }
             Message::SettingsAction(msg) => {
+                let users_changed = matches!(msg, SettingsMessage::UsersChanged(Ok(_)));
+                let self_update_toggled = matches!(msg, SettingsMessage::SelfUpdateEnabled(_));
+                let device_reset = matches!(msg, SettingsMessage::DeviceSettingsReset);
+                let favorite_toggled = match msg {
+                    SettingsMessage::FavoriteDevice(favorite) => Some(favorite),
+                    SettingsMessage::DeviceSettingsReset => Some(false),
+                    _ => None,
+                };
+                let opened_offline = match &msg {
+                    SettingsMessage::BackupFileChosen(Ok(path)) => Some(offline_phone(path)),
+                    _ => None,
+                };
                 match msg {
                     SettingsMessage::RestoringDevice(ref output) => {
                         self.nb_running_async_adb_commands -= 1;
@@ -215,18 +409,45 @@ impl Application for UadGui {
                     }
                     _ => (),
                 }
-                self.settings_view
+                let command = self
+                    .settings_view
                     .update(
                         &self.selected_device.clone().unwrap_or_default(),
                         &self.apps_view.phone_packages,
                         &mut self.nb_running_async_adb_commands,
                         msg,
-                        self.apps_view.selected_user,
+                        self.apps_view.selected_user.clone(),
                     )
-                    .map(Message::SettingsAction)
+                    .map(Message::SettingsAction);
+                if let Some(favorite) = favorite_toggled {
+                    if let Some(device) = &mut self.selected_device {
+                        device.favorite = favorite;
+                    }
+                    let config = Config::load_configuration_file();
+                    pin_favorites(&mut self.devices_list, &favorite_devices(&config));
+                }
+                if users_changed {
+                    // packages are indexed by user, so everything is loaded again
+                    Command::batch([command, self.update(Message::RefreshButtonPressed)])
+                } else if device_reset {
+                    // the package list applies the default filters again
+                    Command::batch([command, self.update(Message::ReloadDevicePressed)])
+                } else if self_update_toggled {
+                    Command::batch([command, self.check_self_update()])
+                } else if let Some(opened) = opened_offline {
+                    let next = match opened {
+                        Ok(phone) => self.update(Message::DeviceSelected(phone)),
+                        Err(err) => self.update(Message::SettingsAction(
+                            SettingsMessage::OfflineBackupFailed(err),
+                        )),
+                    };
+                    Command::batch([command, next])
+                } else {
+                    command
+                }
             }
             Message::AboutAction(msg) => {
-                self.about_view.update(msg.clone());
+                let command = self.about_view.update(msg.clone());
 
                 match msg {
                     AboutMessage::UpdateUadLists => {
@@ -236,21 +457,18 @@ impl Application for UadGui {
                     }
                     AboutMessage::DoSelfUpdate => {
                         #[cfg(feature = "self-update")]
-                        if let Some(release) = self.update_state.self_update.latest_release.as_ref()
-                        {
+                        if self.update_state.self_update.latest_release.is_some() {
+                            // the download runs in `self_update_download`
                             self.update_state.self_update.status = SelfUpdateStatus::Updating;
+                            self.update_state.self_update.self_update_progress = None;
+                            self.update_state.self_update.self_update_error = None;
                             self.apps_view.loading_state = ListLoadingState::_UpdatingUad;
-                            Command::perform(
-                                download_update_to_temp_file(BIN_NAME, release.clone()),
-                                Message::_NewReleaseDownloaded,
-                            )
-                        } else {
-                            Command::none()
                         }
-                        #[cfg(not(feature = "self-update"))]
                         Command::none()
                     }
-                    AboutMessage::UrlPressed(_) => Command::none(),
+                    AboutMessage::UrlPressed(_)
+                    | AboutMessage::ToggleAdbDevices
+                    | AboutMessage::AdbDevicesListed(_) => command.map(Message::AboutAction),
                 }
             }
             Message::DeviceSelected(s_device) => {
@@ -270,54 +488,48 @@ impl Application for UadGui {
                     self.update(Message::AppsAction(AppsMessage::ToggleAllSelected(false)));
                     self.update(Message::AppsAction(AppsMessage::ClearSelectedPackages));
                 }
+                if self.apps_view.uad_lists.is_empty() {
+                    // not loaded yet, the device wasn't selected automatically
+                    return self.update(Message::AppsAction(AppsMessage::LoadUadList(true)));
+                }
                 self.update(Message::AppsAction(AppsMessage::LoadPhonePackages((
                     self.apps_view.uad_lists.clone(),
                     UadListState::Done,
                 ))))
             }
             #[cfg(feature = "self-update")]
-            Message::_NewReleaseDownloaded(res) => {
+            Message::NewReleaseDownloaded(res) => {
                 debug!("{NAME} update has been downloaded!");
 
                 if let Ok((relaunch_path, cleanup_path)) = res {
-                    let mut args: Vec<_> = std::env::args().skip(1).collect();
-
-                    // Remove the `--self-update-temp` arg from args if it exists,
-                    // since we need to pass it cleanly. Otherwise new process will
-                    // fail during arg parsing.
-                    if let Some(idx) = args.iter().position(|a| a == "--self-update-temp") {
-                        args.remove(idx);
-                        // Remove path passed after this arg
-                        args.remove(idx);
-                    }
-
-                    match std::process::Command::new(relaunch_path)
-                        .args(args)
-                        .arg("--self-update-temp")
-                        .arg(&cleanup_path)
+                    // the new version deletes the old one (still running) on startup
+                    match std::process::Command::new(&relaunch_path)
+                        .args(relaunch_args(std::env::args().skip(1), &cleanup_path))
                         .spawn()
                     {
                         Ok(_) => {
-                            if let Err(e) = remove_file(cleanup_path) {
-                                error!("Could not remove temp update file: {e}");
-                            }
+                            info!("[SelfUpdate] Relaunched {}", relaunch_path.display());
                             std::process::exit(0)
                         }
                         Err(error) => {
-                            if let Err(e) = remove_file(cleanup_path) {
-                                error!("Could not remove temp update file: {e}");
-                            }
                             error!("Failed to update {NAME}: {error}");
+                            // put the old version back, as the new one doesn't start
+                            if let Err(e) = rename(&cleanup_path, &relaunch_path) {
+                                error!("[SelfUpdate] Couldn't restore the previous version: {e}");
+                            }
+                            self.self_update_failed(format!("Couldn't start it: {error}"))
                         }
                     }
+                } else if let Err(error) = res {
+                    error!("Failed to update {NAME}: {error}");
+                    self.self_update_failed(error)
                 } else {
-                    error!("Failed to update {NAME}!");
-                    #[expect(unused_must_use, reason = "side-effect")]
-                    {
-                        self.update(Message::AppsAction(AppsMessage::UpdateFailed));
-                        self.update_state.self_update.status = SelfUpdateStatus::Failed;
-                    }
+                    Command::none()
                 }
+            }
+            #[cfg(feature = "self-update")]
+            Message::SelfUpdateProgress(progress) => {
+                self.update_state.self_update.self_update_progress = Some(progress);
                 Command::none()
             }
             Message::GetLatestRelease(release) => {
@@ -343,7 +555,27 @@ impl Application for UadGui {
                     self.adb_satisfied,
                 )))
             }
+            Message::OsThemeChanged(theme) => {
+                info!("OS color scheme changed: {theme}");
+                self.os_theme = theme;
+                Command::none()
+            }
             Message::Nothing => Command::none(),
+            Message::TogglePalette => {
+                if self.palette_query.take().is_some() {
+                    return Command::none();
+                }
+                self.palette_query = Some(String::new());
+                text_input::focus(PALETTE_INPUT.clone())
+            }
+            Message::PaletteInput(query) => {
+                self.palette_query = Some(query);
+                Command::none()
+            }
+            Message::PaletteRun(msg) => {
+                self.palette_query = None;
+                self.update(*msg)
+            }
         }
     }
 
@@ -363,7 +595,7 @@ impl Application for UadGui {
                 .map(Message::AppsAction),
             View::About => self
                 .about_view
-                .view(&self.update_state)
+                .view(&self.update_state, &selected_device)
                 .map(Message::AboutAction),
             View::Settings => self
                 .settings_view
@@ -371,23 +603,118 @@ impl Application for UadGui {
                 .map(Message::SettingsAction),
         };
 
-        column![navigation_container, main_container]
+        let content = column![navigation_container, main_container]
             .width(Length::Fill)
-            .align_items(Alignment::Center)
-            .into()
+            .align_items(Alignment::Center);
+
+        match &self.palette_query {
+            Some(query) => command_palette(
+                content,
+                query,
+                &self.palette_commands(),
+                Message::PaletteInput,
+                |msg| Message::PaletteRun(Box::new(msg)),
+                Message::TogglePalette,
+            )
+            .into(),
+            None => content.into(),
+        }
     }
 }
 
+/// Serials of [`crate::core::config::DeviceSettings::favorite`] devices
+fn favorite_devices(config: &Config) -> HashSet<&str> {
+    config
+        .devices
+        .iter()
+        .filter(|d| d.favorite)
+        .map(|d| d.device_id.as_str())
+        .collect()
+}
+
 impl UadGui {
-    pub fn start() -> iced::Result {
-        let logo: &[u8] = match *OS_COLOR_SCHEME {
-            // remember to keep `Unspecified` in sync with `src/core/theme`
-            dark_light::Mode::Dark | dark_light::Mode::Unspecified => {
-                include_bytes!("../../resources/assets/logo-dark.png")
-            }
-            dark_light::Mode::Light => {
-                include_bytes!("../../resources/assets/logo-light.png")
+    /// `(label, message)` of the actions the command palette offers right now
+    fn palette_commands(&self) -> Vec<(String, Message)> {
+        let mut commands = vec![
+            ("Go to packages".to_string(), Message::AppsPress),
+            ("Go to settings".to_string(), Message::SettingsPressed),
+            ("Go to about".to_string(), Message::AboutPressed),
+            ("Rescan devices".to_string(), Message::RefreshButtonPressed),
+            (
+                "Update lists".to_string(),
+                Message::AboutAction(AboutMessage::UpdateUadLists),
+            ),
+        ];
+        if let Some(device) = &self.selected_device {
+            commands.extend([
+                ("Reload packages".to_string(), Message::ReloadDevicePressed),
+                (
+                    "Undo last action".to_string(),
+                    Message::AppsAction(AppsMessage::Undo),
+                ),
+                (
+                    "Backup device".to_string(),
+                    Message::SettingsAction(SettingsMessage::BackupDevice),
+                ),
+                (
+                    "Open backup folder".to_string(),
+                    Message::SettingsAction(SettingsMessage::UrlPressed(
+                        self.settings_view
+                            .general
+                            .backup_folder
+                            .join(&device.adb_id),
+                    )),
+                ),
+            ]);
+            if matches!(self.apps_view.loading_state, ListLoadingState::Ready) {
+                commands.extend(Preset::ALL.map(|preset| {
+                    (
+                        preset.as_str().to_string(),
+                        Message::AppsAction(AppsMessage::ApplyPreset(preset)),
+                    )
+                }));
             }
+        }
+        let current = string_to_theme(&self.settings_view.general.theme);
+        commands.extend(Theme::ALL.into_iter().filter(|&t| t != current).map(|t| {
+            (
+                format!("Switch theme: {t}"),
+                Message::SettingsAction(SettingsMessage::ApplyTheme(t)),
+            )
+        }));
+        commands
+    }
+
+    /// Looks for a new release, unless the updater is disabled in the settings
+    /// (e.g. by a distro package, which manages updates itself)
+    fn check_self_update(&mut self) -> Command<Message> {
+        if !self.settings_view.general.self_update_enabled {
+            self.update_state.self_update = SelfUpdateState {
+                status: SelfUpdateStatus::Disabled,
+                ..SelfUpdateState::default()
+            };
+            return Command::none();
+        }
+        self.update_state.self_update = SelfUpdateState::default();
+        Command::perform(
+            async move { get_latest_release() },
+            Message::GetLatestRelease,
+        )
+    }
+
+    #[cfg(feature = "self-update")]
+    fn self_update_failed(&mut self, error: String) -> Command<Message> {
+        self.update_state.self_update.status = SelfUpdateStatus::Failed;
+        self.update_state.self_update.self_update_progress = None;
+        self.update_state.self_update.self_update_error = Some(error);
+        self.update(Message::AppsAction(AppsMessage::UpdateFailed))
+    }
+
+    pub fn start() -> iced::Result {
+        let logo: &[u8] = if detect_os_theme() == Theme::Light {
+            include_bytes!("../../resources/assets/logo-light.png")
+        } else {
+            include_bytes!("../../resources/assets/logo-dark.png")
         };
 
         Self::run(Settings {
@@ -407,3 +734,188 @@ impl UadGui {
         })
     }
 }
+
+/// How long "Reboot and wait" waits for the device
+const REBOOT_TIMEOUT: std::time::Duration = std::time::Duration::from_mins(3);
+
+/// Up/Down move the focus in the package list, Space toggles the focused package.
+///
+/// Keys already handled by a widget (e.g. the search input) are ignored.
+fn list_keyboard() -> Subscription<Message> {
+    use iced::keyboard::{self, Key, key::Named};
+
+    // Alt inverts the action of the package rows
+    let alt = iced::event::listen_with(|event, _| match event {
+        iced::Event::Keyboard(keyboard::Event::ModifiersChanged(modifiers)) => Some(
+            Message::AppsAction(AppsMessage::InvertMode(modifiers.alt())),
+        ),
+        _ => None,
+    });
+    let keys = keyboard::on_key_press(|key, modifiers| {
+        if modifiers.command() && !modifiers.alt() {
+            return match key.as_ref() {
+                Key::Character("z") => Some(Message::AppsAction(AppsMessage::Undo)),
+                _ => None,
+            };
+        }
+        if modifiers.command() || modifiers.alt() {
+            return None;
+        }
+        let msg = match key {
+            Key::Named(Named::ArrowDown) => AppsMessage::MoveFocus(true),
+            Key::Named(Named::ArrowUp) => AppsMessage::MoveFocus(false),
+            Key::Named(Named::Space) => AppsMessage::ToggleFocused,
+            _ => return None,
+        };
+        Some(Message::AppsAction(msg))
+    });
+    Subscription::batch([alt, keys])
+}
+
+/// Escape sends `close()`, to dismiss the open modal
+fn escape_closes_modal(close: fn() -> Message) -> Subscription<Message> {
+    use iced::keyboard::{self, Key, key::Named};
+
+    keyboard::on_key_press(|key, _| matches!(key, Key::Named(Named::Escape)).then_some(()))
+        .with(close)
+        .map(|(close, ())| close())
+}
+
+/// Ctrl+K (Cmd+K on macOS) toggles the command palette, in every view
+fn palette_shortcut() -> Subscription<Message> {
+    use iced::keyboard::{self, Key};
+
+    keyboard::on_key_press(|key, modifiers| match key.as_ref() {
+        Key::Character("k") if modifiers.command() && !modifiers.alt() => {
+            Some(Message::TogglePalette)
+        }
+        _ => None,
+    })
+}
+
+/// Asks the package list to re-check its visible states, every [`LIVE_STATE_SYNC_INTERVAL`].
+fn live_state_ticks() -> Subscription<Message> {
+    ticks(
+        "live-state-ticks",
+        LIVE_STATE_SYNC_INTERVAL,
+        AppsMessage::LiveStateTick,
+    )
+}
+
+const LIVE_STATE_SYNC_INTERVAL: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Animates the spinner of the loading views, every [`SPINNER_INTERVAL`].
+fn spinner_ticks() -> Subscription<Message> {
+    ticks("spinner-ticks", SPINNER_INTERVAL, AppsMessage::SpinnerTick)
+}
+
+const SPINNER_INTERVAL: std::time::Duration = std::time::Duration::from_millis(150);
+
+/// Downloads `release`, reporting its progress, then its result as [`Message::NewReleaseDownloaded`].
+///
+/// The download blocks, so it runs on a dedicated thread.
+#[cfg(feature = "self-update")]
+fn self_update_download(release: Release) -> Subscription<Message> {
+    use iced::futures::{StreamExt, channel::mpsc};
+
+    iced::subscription::unfold(
+        "self-update-download",
+        None,
+        move |rx: Option<mpsc::UnboundedReceiver<Message>>| {
+            let release = release.clone();
+            async move {
+                let mut rx = rx.unwrap_or_else(|| {
+                    let (tx, new_rx) = mpsc::unbounded();
+                    std::thread::spawn(move || {
+                        let mut last = None;
+                        let res = download_update_to_temp_file(BIN_NAME, &release, &mut |p| {
+                            // a message per percent, not per read
+                            let step = p.percent().map_or(p.downloaded / (256 * 1024), u64::from);
+                            if last != Some(step) {
+                                last = Some(step);
+                                let _ = tx.unbounded_send(Message::SelfUpdateProgress(p));
+                            }
+                        });
+                        let _ = tx.unbounded_send(Message::NewReleaseDownloaded(res));
+                    });
+                    new_rx
+                });
+                match rx.next().await {
+                    Some(message) => (message, Some(rx)),
+                    None => iced::futures::future::pending().await,
+                }
+            }
+        },
+    )
+}
+
+/// Sends `message` to the package list every `interval`.
+///
+/// Like [`os_theme_changes`], the timer runs on a dedicated thread.
+fn ticks(
+    id: &'static str,
+    interval: std::time::Duration,
+    message: AppsMessage,
+) -> Subscription<Message> {
+    use iced::futures::{StreamExt, channel::mpsc};
+
+    iced::subscription::unfold(id, None, move |rx: Option<mpsc::UnboundedReceiver<()>>| {
+        let message = message.clone();
+        async move {
+            let mut rx = rx.unwrap_or_else(|| {
+                let (tx, new_rx) = mpsc::unbounded();
+                std::thread::spawn(move || {
+                    loop {
+                        std::thread::sleep(interval);
+                        if tx.unbounded_send(()).is_err() {
+                            break;
+                        }
+                    }
+                });
+                new_rx
+            });
+            match rx.next().await {
+                Some(()) => (Message::AppsAction(message), Some(rx)),
+                None => iced::futures::future::pending().await,
+            }
+        }
+    })
+}
+
+/// Emits the OS theme whenever it flips.
+///
+/// Polling happens on a dedicated thread, so the executor isn't blocked.
+/// It stops once the subscription is dropped (the receiver goes away).
+fn os_theme_changes() -> Subscription<Message> {
+    use iced::futures::{StreamExt, channel::mpsc};
+
+    iced::subscription::unfold(
+        "os-theme-changes",
+        None,
+        |rx: Option<mpsc::UnboundedReceiver<Theme>>| async move {
+            let mut rx = rx.unwrap_or_else(|| {
+                let (tx, new_rx) = mpsc::unbounded();
+                std::thread::spawn(move || {
+                    let mut last = detect_os_theme();
+                    loop {
+                        std::thread::sleep(std::time::Duration::from_secs(2));
+                        let theme = detect_os_theme();
+                        if theme != last {
+                            last = theme;
+                            if tx.unbounded_send(theme).is_err() {
+                                break;
+                            }
+                        } else if tx.is_closed() {
+                            break;
+                        }
+                    }
+                });
+                new_rx
+            });
+            match rx.next().await {
+                Some(theme) => (Message::OsThemeChanged(theme), Some(rx)),
+                None => iced::futures::future::pending().await,
+            }
+        },
+    )
+}
@@ -2,7 +2,7 @@ use crate::core::theme::Theme;
 use iced::overlay::menu;
 use iced::widget::text_editor;
 use iced::widget::{
-    button, checkbox, container, pick_list, radio, rule, scrollable, text, text_input,
+    button, checkbox, container, pick_list, progress_bar, radio, rule, scrollable, text, text_input,
 };
 use iced::{Background, Border, Color, Shadow, application};
 
@@ -32,6 +32,10 @@ pub enum Container {
     BorderedFrame,
     Tooltip,
     Background,
+    /// Keyboard focus outline
+    Focused,
+    /// Small counter, over a button
+    Badge,
 }
 
 impl container::StyleSheet for Theme {
@@ -72,6 +76,26 @@ impl container::StyleSheet for Theme {
                 shadow: Shadow::default(),
             },
 
+            Container::Focused => container::Appearance {
+                border: Border {
+                    color: pal.bright.primary,
+                    width: 2.0,
+                    radius: 5.0.into(),
+                },
+                ..container::Appearance::default()
+            },
+
+            Container::Badge => container::Appearance {
+                background: Some(Background::Color(pal.bright.primary)),
+                text_color: Some(pal.base.background),
+                border: Border {
+                    color: Color::TRANSPARENT,
+                    width: 0.0,
+                    radius: 8.0.into(),
+                },
+                ..container::Appearance::default()
+            },
+
             Container::Background => container::Appearance {
                 background: Some(Background::Color(pal.base.background)),
                 text_color: Some(pal.bright.surface),
@@ -97,6 +121,8 @@ pub enum Button {
     NormalPackage,
     SelectedPackage,
     Hidden,
+    /// Looks like a hyperlink, for URLs in free-form text
+    Link,
 }
 
 impl button::StyleSheet for Theme {
@@ -164,6 +190,16 @@ impl button::StyleSheet for Theme {
                 },
                 ..appearance
             },
+            Button::Link => button::Appearance {
+                background: Some(Background::Color(Color::TRANSPARENT)),
+                text_color: p.bright.primary,
+                border: Border {
+                    color: Color::TRANSPARENT,
+                    width: 0.0,
+                    radius: 2.0.into(),
+                },
+                ..appearance
+            },
         }
     }
 
@@ -186,6 +222,7 @@ impl button::StyleSheet for Theme {
                 hover_appearance(p.bright.error, None)
             }
             Button::Hidden => hover_appearance(Color::TRANSPARENT, None),
+            Button::Link => hover_appearance(p.normal.primary, Some(p.bright.primary)),
         }
     }
 
@@ -207,7 +244,10 @@ impl button::StyleSheet for Theme {
         };
 
         match style {
-            Button::RestorePackage => disabled_appearance(p.normal.primary, Some(p.bright.primary)),
+            // Same color as `Text::Ok`, used for restores in the review modal
+            Button::RestorePackage => {
+                disabled_appearance(p.bright.secondary, Some(p.bright.secondary))
+            }
             Button::UninstallPackage => disabled_appearance(p.bright.error, None),
             Button::Primary => disabled_appearance(p.bright.primary, Some(p.bright.primary)),
             _ => active,
@@ -508,6 +548,8 @@ pub enum Text {
     Default,
     Ok,
     Danger,
+    /// Cautionary, but not as alarming as `Danger`
+    Warning,
     Commentary,
     Color(Color),
 }
@@ -531,6 +573,9 @@ impl text::StyleSheet for Theme {
             Text::Danger => text::Appearance {
                 color: Some(p.bright.error),
             },
+            Text::Warning => text::Appearance {
+                color: Some(p.bright.warning),
+            },
             Text::Commentary => text::Appearance {
                 color: Some(p.normal.surface),
             },
@@ -631,6 +676,26 @@ impl rule::StyleSheet for Theme {
     }
 }
 
+#[derive(Default, Clone, Copy)]
+pub enum ProgressBar {
+    #[default]
+    Default,
+}
+
+impl progress_bar::StyleSheet for Theme {
+    type Style = ProgressBar;
+
+    fn appearance(&self, style: &Self::Style) -> progress_bar::Appearance {
+        match style {
+            ProgressBar::Default => progress_bar::Appearance {
+                background: Background::Color(self.palette().base.background),
+                bar: Background::Color(self.palette().normal.primary),
+                border_radius: 2.0.into(),
+            },
+        }
+    }
+}
+
 // Unit tests
 #[cfg(test)]
 mod tests {
@@ -1,35 +1,213 @@
 use crate::CACHE_DIR;
 use crate::core::adb;
+use crate::core::backend::backend;
 use crate::core::helpers::button_primary;
+use crate::core::sync::Phone;
 use crate::core::theme::Theme;
 use crate::core::uad_lists::LIST_FNAME;
 use crate::core::utils::{NAME, last_modified_date, open_url};
+use crate::gui::views::list::{FAKE_ADB, GETTING_STARTED_URL};
 use crate::gui::{UpdateState, style, widgets::text};
-use iced::widget::{Space, column, container, row};
-use iced::{Alignment, Element, Length, Renderer};
+use iced::widget::{Space, button, column, container, row};
+use iced::{Alignment, Command, Element, Font, Length, Renderer};
 use std::path::PathBuf;
 
 #[cfg(feature = "self-update")]
-use crate::core::update::SelfUpdateStatus;
+use {crate::core::update::SelfUpdateStatus, iced::widget::progress_bar};
 
 #[derive(Default, Debug, Clone)]
-pub struct About {}
+pub struct About {
+    /// Whether the troubleshooting section is expanded
+    show_adb_devices: bool,
+    /// `adb devices` as parsed, once listed
+    adb_devices: Option<Result<Vec<(String, String)>, String>>,
+}
 
 #[derive(Debug, Clone)]
 pub enum Message {
     UrlPressed(PathBuf),
     UpdateUadLists,
     DoSelfUpdate,
+    ToggleAdbDevices,
+    AdbDevicesListed(Result<Vec<(String, String)>, String>),
 }
 
 impl About {
-    pub fn update(&mut self, msg: Message) {
-        if let Message::UrlPressed(url) = msg {
-            open_url(url);
+    pub fn update(&mut self, msg: Message) -> Command<Message> {
+        match msg {
+            Message::UrlPressed(url) => {
+                open_url(url);
+                Command::none()
+            }
+            Message::ToggleAdbDevices => {
+                self.show_adb_devices = !self.show_adb_devices;
+                self.adb_devices = None;
+                if self.show_adb_devices {
+                    Command::perform(async { backend().devices() }, Message::AdbDevicesListed)
+                } else {
+                    Command::none()
+                }
+            }
+            Message::AdbDevicesListed(devices) => {
+                // hidden again before `adb devices` returned
+                if self.show_adb_devices {
+                    self.adb_devices = Some(devices);
+                }
+                Command::none()
+            }
+            // other events are handled by UadGui update()
+            Message::UpdateUadLists | Message::DoSelfUpdate => Command::none(),
         }
-        // other events are handled by UadGui update()
     }
-    pub fn view(&self, update_state: &UpdateState) -> Element<Message, Theme, Renderer> {
+
+    fn links_row() -> Element<'static, Message, Theme, Renderer> {
+        let website_btn =
+            button_primary("GitHub page").on_press(Message::UrlPressed(PathBuf::from(
+                "https://github.com/Universal-Debloater-Alliance/universal-android-debloater",
+            )));
+
+        let issue_btn = button_primary("Have an issue?")
+            .on_press(Message::UrlPressed(PathBuf::from(
+            "https://github.com/Universal-Debloater-Alliance/universal-android-debloater/issues",
+        )));
+
+        let log_btn = button_primary("Locate the logfiles")
+            .on_press(Message::UrlPressed(CACHE_DIR.to_path_buf()));
+
+        let wiki_btn = button_primary("Wiki").on_press(Message::UrlPressed(PathBuf::from(
+            "https://github.com/Universal-Debloater-Alliance/universal-android-debloater/wiki",
+        )));
+
+        row![website_btn, wiki_btn, issue_btn, log_btn,]
+            .spacing(20)
+            .into()
+    }
+
+    /// What `adb devices` returned, verbatim, for when detection goes wrong
+    fn adb_devices_view(&self) -> Element<'_, Message, Theme, Renderer> {
+        let toggle = button_primary(if self.show_adb_devices {
+            "Hide adb devices output"
+        } else {
+            "Show adb devices output"
+        })
+        .on_press(Message::ToggleAdbDevices);
+
+        let output: Element<_, _, _> = match &self.adb_devices {
+            None if self.show_adb_devices => text("Listing devices...")
+                .style(style::Text::Commentary)
+                .into(),
+            None => Space::new(Length::Shrink, Length::Shrink).into(),
+            Some(Err(e)) => text(e).style(style::Text::Danger).into(),
+            Some(Ok(devices)) if devices.is_empty() => text("(no devices listed)")
+                .style(style::Text::Commentary)
+                .into(),
+            Some(Ok(devices)) => devices
+                .iter()
+                .fold(column![].spacing(2), |col, (serial, status)| {
+                    col.push(text(format!("{serial:<24} {status}")).font(Font::MONOSPACE))
+                })
+                .into(),
+        };
+
+        column![toggle, output]
+            .spacing(10)
+            .align_items(Alignment::Center)
+            .into()
+    }
+    /// The device pick-list lives in the navigation bar,
+    /// this just makes it clear which device it points at.
+    fn device_header(phone: &Phone) -> Element<'static, Message, Theme, Renderer> {
+        let device_row = if phone.adb_id.is_empty() {
+            row![text("No device detected").style(style::Text::Danger)]
+        } else {
+            row![
+                text("Selected device:"),
+                text(phone.model.clone()),
+                Space::new(Length::Fill, Length::Shrink),
+                text(phone.adb_id.clone()).style(style::Text::Commentary)
+            ]
+            .spacing(7)
+        };
+        container(device_row)
+            .padding(10)
+            .width(Length::Fill)
+            .style(style::Container::BorderedFrame)
+            .into()
+    }
+
+    /// The version, its update button, and the update progress or failure
+    #[cfg(feature = "self-update")]
+    fn self_update_row(update_state: &UpdateState) -> Element<'static, Message, Theme, Renderer> {
+        // hidden for packaged builds, see `GeneralSettings::self_update_enabled`
+        let self_update_btn = (update_state.self_update.status != SelfUpdateStatus::Disabled)
+            .then(|| button_primary("Update").on_press(Message::DoSelfUpdate));
+
+        let uad_version_text =
+            text(format!("{NAME} version: v{}", env!("CARGO_PKG_VERSION"))).width(250);
+
+        let self_update_text = update_state
+            .self_update
+            .latest_release
+            .as_ref()
+            .map_or_else(
+                || {
+                    if update_state.self_update.status == SelfUpdateStatus::Done {
+                        "(No update available)".to_string()
+                    } else {
+                        update_state.self_update.status.to_string()
+                    }
+                },
+                |r| {
+                    if update_state.self_update.status == SelfUpdateStatus::Updating {
+                        update_state.self_update.status.to_string()
+                    } else {
+                        format!("({} available)", r.tag_name)
+                    }
+                },
+            );
+
+        let last_self_update_text = text(self_update_text).style(style::Text::Default);
+
+        let progress = update_state
+            .self_update
+            .self_update_progress
+            .filter(|_| update_state.self_update.status == SelfUpdateStatus::Updating)
+            .map(|p| {
+                row![
+                    progress_bar(0.0..=100.0, f32::from(p.percent().unwrap_or_default()))
+                        .height(8)
+                        .width(250),
+                    text(p.to_string()).style(style::Text::Commentary),
+                ]
+                .align_items(Alignment::Center)
+                .spacing(10)
+            });
+        let error = update_state
+            .self_update
+            .self_update_error
+            .as_ref()
+            .filter(|_| update_state.self_update.status == SelfUpdateStatus::Failed)
+            .map(|e| text(format!("Update failed: {e}")).style(style::Text::Danger));
+
+        column![
+            row![uad_version_text]
+                .push_maybe(self_update_btn)
+                .push(last_self_update_text)
+                .align_items(Alignment::Center)
+                .spacing(10)
+                .width(550)
+        ]
+        .push_maybe(progress)
+        .push_maybe(error)
+        .spacing(5)
+        .into()
+    }
+
+    pub fn view<'a>(
+        &'a self,
+        update_state: &UpdateState,
+        phone: &Phone,
+    ) -> Element<'a, Message, Theme, Renderer> {
         let about_text = text(format!(
             "Universal Android Debloater Next Generation ({NAME}) is a free and open-source community project \naiming at simplifying the removal of pre-installed apps on any Android device."
         ));
@@ -46,40 +224,7 @@ impl About {
         let uad_lists_btn = button_primary("Update").on_press(Message::UpdateUadLists);
 
         #[cfg(feature = "self-update")]
-        let self_update_row = {
-            let self_update_btn = button_primary("Update").on_press(Message::DoSelfUpdate);
-
-            let uad_version_text =
-                text(format!("{NAME} version: v{}", env!("CARGO_PKG_VERSION"))).width(250);
-
-            let self_update_text = update_state
-                .self_update
-                .latest_release
-                .as_ref()
-                .map_or_else(
-                    || {
-                        if update_state.self_update.status == SelfUpdateStatus::Done {
-                            "(No update available)".to_string()
-                        } else {
-                            update_state.self_update.status.to_string()
-                        }
-                    },
-                    |r| {
-                        if update_state.self_update.status == SelfUpdateStatus::Updating {
-                            update_state.self_update.status.to_string()
-                        } else {
-                            format!("({} available)", r.tag_name)
-                        }
-                    },
-                );
-
-            let last_self_update_text = text(self_update_text).style(style::Text::Default);
-
-            row![uad_version_text, self_update_btn, last_self_update_text,]
-                .align_items(Alignment::Center)
-                .spacing(10)
-                .width(550)
-        };
+        let self_update_row = Self::self_update_row(update_state);
 
         let uad_list_row = row![uad_list_text, uad_lists_btn, last_update_text,]
             .align_items(Alignment::Center)
@@ -96,7 +241,22 @@ impl About {
         However, the server will still be the "old" version
         until it's killed
         */
-        let adb_version_text = text(match adb::ACommand::new().version() {
+        let adb_version = adb::ACommand::new().version();
+        let fake_adb_warning = adb_version
+            .as_ref()
+            .is_ok_and(|s| !adb::is_genuine_version(s))
+            .then(|| {
+                column![
+                    text(FAKE_ADB).style(style::Text::Danger),
+                    button("Read on how to get started.")
+                        .padding(0)
+                        .style(style::Button::Link)
+                        .on_press(Message::UrlPressed(PathBuf::from(GETTING_STARTED_URL))),
+                ]
+                .spacing(4)
+                .width(550)
+            });
+        let adb_version_text = text(match adb_version {
             Ok(s) => s
                 .lines()
                 .nth(0)
@@ -112,9 +272,12 @@ impl About {
             }
         })
         .width(250);
-        let adb_version_row = row![adb_version_text]
-            .align_items(Alignment::Center)
-            .width(550);
+        let adb_version_row = column![
+            row![adb_version_text]
+                .align_items(Alignment::Center)
+                .width(550)
+        ]
+        .push_maybe(fake_adb_warning);
 
         #[cfg(feature = "self-update")]
         let update_column = column![uad_list_row, self_update_row, adb_version_row];
@@ -129,30 +292,12 @@ impl About {
             .padding(10)
             .style(style::Container::Frame);
 
-        let website_btn =
-            button_primary("GitHub page").on_press(Message::UrlPressed(PathBuf::from(
-                "https://github.com/Universal-Debloater-Alliance/universal-android-debloater",
-            )));
-
-        let issue_btn = button_primary("Have an issue?")
-            .on_press(Message::UrlPressed(PathBuf::from(
-            "https://github.com/Universal-Debloater-Alliance/universal-android-debloater/issues",
-        )));
-
-        let log_btn = button_primary("Locate the logfiles")
-            .on_press(Message::UrlPressed(CACHE_DIR.to_path_buf()));
-
-        let wiki_btn = button_primary("Wiki").on_press(Message::UrlPressed(PathBuf::from(
-            "https://github.com/Universal-Debloater-Alliance/universal-android-debloater/wiki",
-        )));
-
-        let row = row![website_btn, wiki_btn, issue_btn, log_btn,].spacing(20);
-
         let content = column![
-            Space::new(Length::Fill, Length::Shrink),
+            Self::device_header(phone),
             descr_container,
             update_container,
-            row,
+            Self::links_row(),
+            self.adb_devices_view(),
         ]
         .width(Length::Fill)
         .spacing(20)
@@ -1,22 +1,39 @@
-use crate::core::config::DeviceSettings;
+use crate::core::backend::backend;
+use crate::core::config::{Config, DeviceSettings};
 use crate::core::helpers::button_primary;
-use crate::core::sync::{AdbError, Phone, User, adb_shell_command, apply_pkg_state_commands};
+use crate::core::history;
+use crate::core::hooks::{self, Hook};
+use crate::core::save::offline_packages;
+use crate::core::sync::{
+    AdbError, BATCH_SIZE, DeviceStatus, Phone, User, adb_shell_batch, adb_shell_command,
+    apply_pkg_state_commands, disabled_until_used, get_storage_info, leftover_apks,
+    supports_multi_user, verify_package_state,
+};
 use crate::core::theme::Theme;
 use crate::core::uad_lists::{
-    Opposite, PackageHashMap, PackageState, Removal, UadList, UadListState, load_debloat_lists,
+    Opposite, Origin, PackageHashMap, PackageState, Removal, UadList, UadListState,
+    load_debloat_lists,
+};
+use crate::core::utils::{
+    NAME, PATTERN_SYNTAX, Pattern, Segment, export_selection, fetch_packages, format_size, linkify,
+    load_selection, open_url, save_selection,
 };
-use crate::core::utils::{EXPORT_FILE_NAME, NAME, export_selection, fetch_packages, open_url};
 use crate::gui::style;
 use crate::gui::widgets::navigation_menu::ICONS;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::path::PathBuf;
+use std::sync::LazyLock;
 
 use crate::gui::views::settings::Settings;
+use crate::gui::widgets::confirm_modal::confirm_modal;
 use crate::gui::widgets::modal::Modal;
-use crate::gui::widgets::package_row::{Message as RowMessage, PackageRow};
+use crate::gui::widgets::package_row::{
+    Message as RowMessage, PackageRow, UNSAFE_BLOCKED, UNSAFE_WIKI_URL,
+};
 use crate::gui::widgets::text;
 use iced::widget::{
     Column, Space, button, checkbox, column, container, horizontal_space, pick_list, radio, row,
-    scrollable, text_editor, text_input, tooltip, vertical_rule,
+    scrollable, text_input, tooltip, vertical_rule,
 };
 use iced::{Alignment, Command, Element, Length, Renderer, alignment};
 
@@ -27,16 +44,130 @@ pub struct PackageInfo {
     pub removal: String,
 }
 
+/// Packages changed by the last action (of a single package, or a batch),
+/// so it can be undone
+#[derive(Debug, Default, Clone)]
+pub struct AppliedAction {
+    /// `(user_index, pkg_index)` of the packages, with their state before the action
+    pub packages: Vec<((usize, usize), PackageState)>,
+    /// See [`List::unscoped`]
+    pub unscoped: bool,
+}
+
+/// Entry of the user pick-list
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UserScope {
+    /// Commands are sent without any `--user` flag,
+    /// like on devices that don't support multi-user mode.
+    Unscoped,
+    User(User),
+}
+
+impl std::fmt::Display for UserScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unscoped => write!(f, "All users (no --user flag)"),
+            Self::User(user) => write!(f, "{user}"),
+        }
+    }
+}
+
+/// What the search input is matched against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchScope {
+    Name,
+    Description,
+    #[default]
+    Both,
+}
+
+impl SearchScope {
+    pub const ALL: [Self; 3] = [Self::Name, Self::Description, Self::Both];
+
+    /// Whether `package` matches the non-empty `search`
+    fn matches(self, package: &PackageRow, search: &str) -> bool {
+        match self {
+            Self::Name => package.name.contains(search),
+            Self::Description => package.description.contains(search),
+            Self::Both => package.name.contains(search) || package.description.contains(search),
+        }
+    }
+}
+
+impl std::fmt::Display for SearchScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Name => "Name",
+            Self::Description => "Description",
+            Self::Both => "Name & description",
+        })
+    }
+}
+
+/// One-click selections of the current user's enabled packages,
+/// for those who don't want to go through the filters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preset {
+    Recommended,
+    RecommendedAdvanced,
+}
+
+impl Preset {
+    pub const ALL: [Self; 2] = [Self::Recommended, Self::RecommendedAdvanced];
+
+    /// `Removal::Unsafe` must never be part of a preset
+    pub const fn removals(self) -> &'static [Removal] {
+        match self {
+            Self::Recommended => &[Removal::Recommended],
+            Self::RecommendedAdvanced => &[Removal::Recommended, Removal::Advanced],
+        }
+    }
+
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Recommended => "Apply all Recommended",
+            Self::RecommendedAdvanced => "Apply all Recommended + Advanced",
+        }
+    }
+}
+
+/// Which text (of the error modal, or the package table) was just copied
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Copied {
+    Error,
+    Command,
+    Table,
+}
+
 #[derive(Default, Debug, Clone)]
 pub enum LoadingState {
     DownloadingList,
     #[default]
     FindingPhones,
+    /// Devices were found, but none is selected, see
+    /// [`crate::core::config::GeneralSettings::auto_select_device`]
+    SelectingDevice,
     LoadingPackages,
     _UpdatingUad,
     Ready,
     RestoringDevice(String),
     FailedToUpdate,
+    /// The device went offline during an operation
+    DeviceLost,
+    /// Waiting for this device to come back after a reboot
+    Rebooting(String),
+    /// This device didn't come back after a reboot
+    RebootTimedOut(String),
+}
+
+/// Press of the action button of a row, see [`List::row_action`]
+#[derive(Debug, Clone, Copy)]
+struct RowAction {
+    /// `(user_index, pkg_index)`
+    key: (usize, usize),
+    all_users: bool,
+    /// Effective [`DeviceSettings::disable_mode`] when it was pressed
+    disable_mode: bool,
 }
 
 #[derive(Default, Debug)]
@@ -50,20 +181,71 @@ pub struct List {
     filtered_packages: Vec<usize>,
     /// Vec of `(user_index, pkg_index)`
     selected_packages: Vec<(usize, usize)>,
+    /// Changes when the running actions are aborted (or their packages reloaded):
+    /// the results of those already sent to the device are stale.
+    batch: u64,
+    /// `(user_index, pkg_index)` of packages with an ADB action still running,
+    /// with the state it brings them to
+    pending_actions: HashMap<(usize, usize), PackageState>,
+    /// Estimated on-device size (bytes) by package name. Empty if unknown.
+    package_sizes: HashMap<String, u64>,
+    /// `(total, free)` bytes of the device's `/data`, if known
+    pub storage: Option<(u64, u64)>,
+    /// A live state sync is waiting for the device
+    live_state_sync_running: bool,
+    /// Position in `filtered_packages` of the row with the keyboard focus
+    focused_row: Option<usize>,
+    /// Current frame of the [`waiting_view`] spinner
+    spinner_frame: usize,
+    /// What ADB listed when no usable device was found
+    devices_status: Option<Vec<(String, DeviceStatus)>>,
+    /// `(user_index, state)` of every user having the package, by package name
+    users_state: HashMap<String, Vec<(usize, PackageState)>>,
     selected_package_state: Option<PackageState>,
     selected_removal: Option<Removal>,
+    selected_origin: Option<Origin>,
     selected_list: Option<UadList>,
+    /// Only list the selected packages of the current user
+    show_selected_only: bool,
+    /// Pattern of the "Select matching" input
+    select_pattern: String,
+    /// Outcome of the last "Select matching", or why the pattern is invalid
+    select_pattern_report: Option<Result<usize, String>>,
     pub selected_user: Option<User>,
+    /// Omit the `--user` flag from commands.
+    /// Packages of `selected_user` are still the ones displayed.
+    unscoped: bool,
     all_selected: bool,
     pub input_value: String,
+    search_scope: SearchScope,
     description: String,
-    description_content: text_editor::Content,
     selection_modal: bool,
-    error_modal: Option<String>,
-    export_modal: bool,
+    /// Whether the risk of removing the `Unsafe` packages
+    /// of the selection has been acknowledged in the review modal
+    unsafe_acknowledged: bool,
+    error_modal: Option<AdbError>,
+    /// Path of the selection export, while its success modal is shown
+    export_modal: Option<PathBuf>,
+    /// Grows as the results of the last action come in
+    last_action: Option<AppliedAction>,
+    /// Action history of the device, newest first, while it's shown
+    history_modal: Option<Vec<history::Entry>>,
+    /// Row action waiting to be confirmed,
+    /// see [`crate::core::config::GeneralSettings::confirm_every_action`]
+    confirm_action: Option<RowAction>,
+    /// Alt is held: the row actions do the opposite of `disable_mode`
+    invert_mode: bool,
+    /// The selection being applied is to be followed by
+    /// [`crate::core::config::GeneralSettings::post_apply_hook`]
+    post_apply_hook_pending: bool,
+    /// `(user_index, pkg_index)` of a selection left over from a previous session,
+    /// waiting for the user to restore it
+    restore_selection_modal: Option<Vec<(usize, usize)>>,
     current_package_index: usize,
     is_adb_satisfied: bool,
-    copy_confirmation: bool,
+    /// See [`crate::core::sync::is_fake_adb`]
+    fake_adb: bool,
+    copy_confirmation: Option<Copied>,
 }
 
 #[derive(Debug, Clone)]
@@ -72,28 +254,100 @@ pub enum Message {
     LoadPhonePackages((PackageHashMap, UadListState)),
     RestoringDevice(Result<PackageInfo, AdbError>),
     ApplyFilters(Vec<Vec<PackageRow>>),
+    PackageSizesLoaded(HashMap<String, u64>),
+    StorageInfoLoaded(Option<(u64, u64)>),
+    DevicesStatus(Vec<(String, DeviceStatus)>),
+    /// Next frame of the [`waiting_view`] spinner
+    SpinnerTick,
+    /// Move the keyboard focus down (`true`) or up
+    MoveFocus(bool),
+    /// Toggle the selection of the focused row
+    ToggleFocused,
+    /// See [`crate::core::config::GeneralSettings::live_state_sync`]
+    LiveStateTick,
+    /// `(user_index, states by package name)`
+    LiveStatesVerified(usize, Result<HashMap<String, PackageState>, String>),
+    /// Like [`Self::LiveStatesVerified`], for the packages just uninstalled
+    /// States of the packages checked after their uninstall,
+    /// and the ones whose APKs remain, see [`leftover_apks`]
+    UninstallsVerified(
+        usize,
+        Result<(HashMap<String, PackageState>, HashSet<String>), String>,
+    ),
     SearchInputChanged(String),
+    SearchScopeSelected(SearchScope),
     ToggleAllSelected(bool),
     ListSelected(UadList),
     UserSelected(User),
+    UserScopeSelected(UserScope),
     PackageStateSelected(PackageState),
     RemovalSelected(Removal),
+    OriginSelected(Origin),
+    ShowSelectedOnly(bool),
     ApplyActionOnSelection,
+    ApplyPreset(Preset),
+    SelectPatternChanged(String),
+    /// Select every package of the current user matching `select_pattern`
+    SelectMatching,
     List(usize, RowMessage),
-    ChangePackageState(Result<PackageInfo, AdbError>),
+    /// [`List::batch`] of the action, `(user_index, pkg_index)` of the package, and its result
+    ChangePackageState(u64, (usize, usize), Result<PackageInfo, AdbError>),
+    /// Results of a [`adb_shell_batch`], see [`Self::ChangePackageState`]
+    ChangePackageStates(u64, Vec<((usize, usize), Result<PackageInfo, AdbError>)>),
     Nothing,
     ModalHide,
     ModalUserSelected(User),
     ModalValidate,
+    UnsafeAcknowledged(bool),
+    RestorePreviousSelection,
+    DiscardPreviousSelection,
     ClearSelectedPackages,
+    /// See [`crate::core::config::GeneralSettings::pre_apply_hook`]
+    PreApplyHookDone(Result<(), AdbError>),
+    PostApplyHookDone(Result<(), AdbError>),
+    /// Load the packages of the device again, as if it was just selected
+    ReloadPackages,
     ADBSatisfied(bool),
+    /// See [`crate::core::sync::is_fake_adb`]
+    FakeAdb(bool),
     UpdateFailed,
     GoToUrl(PathBuf),
     ExportSelection,
-    SelectionExported(Result<bool, String>),
-    DescriptionEdit(text_editor::Action),
+    SelectionExported(Result<PathBuf, String>),
+    ShowHistory,
+    ClearHistory,
+    /// Revert [`List::last_action`]
+    Undo,
+    /// Run [`List::confirm_action`]
+    ActionConfirmed,
+    /// Open the settings, where the mode can be changed. Handled by the main view.
+    GoToSettings,
+    /// Alt was pressed (`true`) or released, see [`List::invert_mode`]
+    InvertMode(bool),
     CopyError(String),
+    CopyCommand(String),
     HideCopyConfirmation,
+    /// Copy the filtered packages as TSV
+    CopyTable,
+}
+
+impl Message {
+    /// Whether handling it sends commands to the device
+    const fn reaches_device(&self) -> bool {
+        matches!(
+            self,
+            Self::LiveStateTick
+                | Self::ApplyActionOnSelection
+                | Self::ApplyPreset(_)
+                | Self::ModalValidate
+                | Self::Undo
+                | Self::ActionConfirmed
+                | Self::List(
+                    _,
+                    RowMessage::ActionPressed | RowMessage::AllUsersActionPressed
+                )
+        )
+    }
 }
 
 pub struct SummaryEntry {
@@ -113,6 +367,225 @@ impl From<Removal> for SummaryEntry {
 }
 
 impl List {
+    /// Runs the actions of the reviewed selection, see [`Message::ModalValidate`]
+    fn apply_selection(&mut self, settings: &Settings, device: &Phone) -> Command<Message> {
+        let mut planned = vec![];
+        self.selected_packages.sort_unstable();
+        self.selected_packages.dedup();
+        for selection in &self.selected_packages {
+            planned.append(&mut plan_action_pkg(
+                &self.phone_packages,
+                device,
+                &settings.device,
+                *selection,
+                self.unscoped,
+                &mut self.pending_actions,
+            ));
+        }
+        self.start_action();
+        self.post_apply_hook_pending =
+            !planned.is_empty() && Hook::PostApply.script(&settings.general).is_some();
+        Command::batch(build_batched_pkg_commands(device, self.batch, planned))
+    }
+
+    /// Runs the post-apply hook once the last result of the selection came in
+    fn post_apply_hook(&mut self, settings: &Settings, device: &Phone) -> Command<Message> {
+        if !self.post_apply_hook_pending || !self.pending_actions.is_empty() {
+            return Command::none();
+        }
+        self.post_apply_hook_pending = false;
+        if matches!(self.loading_state, LoadingState::DeviceLost) {
+            return Command::none();
+        }
+        let Some(script) = Hook::PostApply.script(&settings.general) else {
+            return Command::none();
+        };
+        let script = script.to_path_buf();
+        let serial = device.adb_id.clone();
+        Command::perform(
+            async move { hooks::run(&script, &serial) },
+            Message::PostApplyHookDone,
+        )
+    }
+
+    /// Applies the outcome of a package state change.
+    /// Returns the name of the package if it got uninstalled, see [`Self::verify_uninstalls`].
+    fn change_package_state(
+        &mut self,
+        settings: &Settings,
+        selected_device: &Phone,
+        key: (usize, usize),
+        res: Result<PackageInfo, AdbError>,
+    ) -> Option<String> {
+        if matches!(self.loading_state, LoadingState::DeviceLost) {
+            // Leftovers of the aborted batch
+            return None;
+        }
+        // The planned state follows the mode the action ran with (e.g. Alt-inverted),
+        // not necessarily the current setting
+        let wanted = self.pending_actions.remove(&key).or_else(|| {
+            let package = self.phone_packages.get(key.0)?.get(key.1)?;
+            Some(package.state.opposite(settings.device.disable_mode))
+        });
+        self.record_action(selected_device, key, wanted, &res);
+        match res {
+            Ok(p) => {
+                let package = &mut self.phone_packages[p.i_user][p.index];
+                if let Some(action) = &mut self.last_action {
+                    action.packages.push((key, package.state));
+                }
+                if let Some(state) = wanted {
+                    package.state = state;
+                }
+                // whatever the system did, the user decided now
+                package.disabled_until_used = false;
+                package.leftover_apks = false;
+                package.selected = false;
+                let uninstalled =
+                    (package.state == PackageState::Uninstalled).then(|| package.name.clone());
+                self.selected_packages.retain(|&x| x != key);
+                Self::filter_package_lists(self);
+                self.persist_selection(settings, selected_device);
+                return uninstalled;
+            }
+            Err(AdbError::DeviceOffline { error, .. }) => {
+                // Report it once, instead of once per remaining package
+                error!("Device lost: {error}");
+                self.loading_state = LoadingState::DeviceLost;
+                self.abort_actions();
+                self.selection_modal = false;
+                self.error_modal = None;
+            }
+            Err(AdbError::NotInstalled { error, .. }) => {
+                // already gone: what was asked is done, or can't be
+                warn!("{error}");
+                let package = &mut self.phone_packages[key.0][key.1];
+                package.state = PackageState::Uninstalled;
+                package.selected = false;
+                self.selected_packages.retain(|&x| x != key);
+                Self::filter_package_lists(self);
+                self.persist_selection(settings, selected_device);
+            }
+            Err(err) if err.is_fatal() => {
+                // the remaining actions would fail the same way
+                self.abort_actions();
+                self.selection_modal = false;
+                self.error_modal = Some(err);
+            }
+            Err(err) => {
+                self.error_modal = Some(err);
+            }
+        }
+        None
+    }
+
+    /// Re-checks that `names` are really gone, as split APKs can survive an uninstall
+    fn verify_uninstalls(
+        device: &Phone,
+        user_index: usize,
+        names: Vec<String>,
+    ) -> Command<Message> {
+        if names.is_empty() {
+            return Command::none();
+        }
+        let serial = device.adb_id.clone();
+        let user_id = (device.user_list.len() > 1)
+            .then(|| device.user_list.iter().find(|u| u.index == user_index))
+            .flatten()
+            .map(|u| u.id);
+        Command::perform(
+            async move {
+                let states = verify_package_state(&serial, user_id, &names)?;
+                let uninstalled: Vec<String> = names
+                    .iter()
+                    .zip(&states)
+                    .filter(|&(_, &state)| state == PackageState::Uninstalled)
+                    .map(|(name, _)| name.clone())
+                    .collect();
+                let leftovers = leftover_apks(&serial, user_id, &uninstalled)?;
+                Ok((names.into_iter().zip(states).collect(), leftovers))
+            },
+            move |states| Message::UninstallsVerified(user_index, states),
+        )
+    }
+
+    /// Update the packages of the user at `user_index` to their `states` on the device.
+    /// With `leftovers`, the verified packages are flagged as [`PackageRow::leftover_apks`] or not.
+    fn apply_verified_states(
+        &mut self,
+        user_index: usize,
+        states: &HashMap<String, PackageState>,
+        leftovers: Option<&HashSet<String>>,
+    ) {
+        let Some(packages) = self.phone_packages.get_mut(user_index) else {
+            return;
+        };
+        let mut changed = false;
+        for (i, package) in packages.iter_mut().enumerate() {
+            // an action may have started since
+            if self.pending_actions.contains_key(&(user_index, i)) {
+                continue;
+            }
+            let Some(&state) = states.get(&package.name) else {
+                continue;
+            };
+            if package.state != state {
+                debug!(
+                    "{} is now {state} (changed outside of UAD-ng)",
+                    package.name
+                );
+                package.state = state;
+                package.leftover_apks = false;
+                changed = true;
+            }
+            if let Some(leftovers) = leftovers {
+                package.leftover_apks = leftovers.contains(&package.name);
+            }
+        }
+        if changed {
+            self.refresh_users_state();
+        }
+    }
+
+    /// A modal is shown over the list, see [`Self::view`]
+    pub fn has_modal(&self) -> bool {
+        self.selection_modal
+            || self.restore_selection_modal.is_some()
+            || self.export_modal.is_some()
+            || self.history_modal.is_some()
+            || self
+                .confirm_action
+                .is_some_and(|a| self.package_at(a.key).is_some())
+            || self.error_modal.is_some()
+    }
+
+    /// The package at `(user_index, pkg_index)`, if it's still there
+    fn package_at(&self, key: (usize, usize)) -> Option<&PackageRow> {
+        self.phone_packages.get(key.0)?.get(key.1)
+    }
+
+    /// Waiting for something that resolves by itself, see [`waiting_view`]
+    pub fn is_busy(&self) -> bool {
+        match self.loading_state {
+            LoadingState::DownloadingList
+            | LoadingState::LoadingPackages
+            | LoadingState::_UpdatingUad
+            | LoadingState::RestoringDevice(_)
+            | LoadingState::Rebooting(_) => true,
+            LoadingState::FindingPhones => self.is_adb_satisfied,
+            LoadingState::Ready
+            | LoadingState::SelectingDevice
+            | LoadingState::FailedToUpdate
+            | LoadingState::DeviceLost
+            | LoadingState::RebootTimedOut(_) => false,
+        }
+    }
+
+    /// Packages selected on every user, pending an action
+    pub fn selected_count(&self) -> usize {
+        self.selected_packages.len()
+    }
+
     #[allow(clippy::too_many_lines)]
     pub fn update(
         &mut self,
@@ -121,36 +594,123 @@ impl List {
         list_update_state: &mut UadListState,
         message: Message,
     ) -> Command<Message> {
-        let i_user = self.selected_user.unwrap_or_default().index;
+        let i_user = self.selected_user.as_ref().map_or(0, |u| u.index);
+        // a backup opened offline is only for crafting a selection to export
+        if selected_device.backup.is_some() && message.reaches_device() {
+            return Command::none();
+        }
         match message {
             Message::ModalHide => {
+                // shown only once the selection modal is closed
+                if !self.selection_modal && self.restore_selection_modal.take().is_some() {
+                    self.persist_selection(settings, selected_device);
+                    return Command::none();
+                }
                 self.selection_modal = false;
+                self.unsafe_acknowledged = false;
                 self.error_modal = None;
-                self.export_modal = false;
+                self.export_modal = None;
+                self.history_modal = None;
+                self.confirm_action = None;
+                Command::none()
+            }
+            Message::ActionConfirmed => match self.confirm_action.take() {
+                Some(action) if self.package_at(action.key).is_some() => {
+                    self.row_action(settings, selected_device, action)
+                }
+                _ => Command::none(),
+            },
+            Message::InvertMode(held) => {
+                self.invert_mode = held;
+                Command::none()
+            }
+            Message::ShowHistory => {
+                let mut entries = history::load(&selected_device.adb_id);
+                entries.reverse();
+                self.history_modal = Some(entries);
+                Command::none()
+            }
+            Message::ClearHistory => {
+                if let Err(e) = history::clear(&selected_device.adb_id) {
+                    error!("Couldn't clear the action history: {e}");
+                }
+                self.history_modal = Some(vec![]);
                 Command::none()
             }
             Message::ModalValidate => {
-                let mut commands = vec![];
-                self.selected_packages.sort_unstable();
-                self.selected_packages.dedup();
-                for selection in &self.selected_packages {
-                    commands.append(&mut build_action_pkg_commands(
-                        &self.phone_packages,
-                        selected_device,
-                        &settings.device,
-                        *selection,
-                    ));
+                if !self.unsafe_acknowledged && !self.selected_unsafe_packages().is_empty() {
+                    return Command::none();
                 }
+                self.unsafe_acknowledged = false;
                 self.selection_modal = false;
-                Command::batch(commands)
+                match Hook::PreApply.script(&settings.general) {
+                    Some(script) => {
+                        let script = script.to_path_buf();
+                        let serial = selected_device.adb_id.clone();
+                        Command::perform(
+                            async move { hooks::run(&script, &serial) },
+                            Message::PreApplyHookDone,
+                        )
+                    }
+                    None => self.apply_selection(settings, selected_device),
+                }
+            }
+            Message::PreApplyHookDone(result) => match result {
+                Ok(()) => self.apply_selection(settings, selected_device),
+                // the batch is aborted
+                Err(err) => {
+                    self.error_modal = Some(err);
+                    Command::none()
+                }
+            },
+            Message::PostApplyHookDone(result) => {
+                if let Err(err) = result {
+                    self.error_modal = Some(err);
+                }
+                Command::none()
+            }
+            Message::Undo => {
+                // the results of a running action would be missing
+                if !self.pending_actions.is_empty() {
+                    return Command::none();
+                }
+                let Some(action) = self.last_action.take() else {
+                    return Command::none();
+                };
+                let planned = plan_undo(
+                    &self.phone_packages,
+                    selected_device,
+                    &settings.device,
+                    &action,
+                    &mut self.pending_actions,
+                );
+                info!("Undoing the last action, on {} package(s)", planned.len());
+                // `last_action` stays empty: an undo can't be undone,
+                // its results are only known to flip the states back.
+                Command::batch(build_batched_pkg_commands(
+                    selected_device,
+                    self.batch,
+                    planned,
+                ))
             }
             Message::RestoringDevice(output) => {
-                if let Ok(p) = output {
-                    self.loading_state = LoadingState::RestoringDevice(
-                        self.phone_packages[i_user][p.index].name.clone(),
-                    );
-                } else {
-                    self.loading_state = LoadingState::RestoringDevice("Error [TODO]".to_string());
+                match output {
+                    Ok(p) => {
+                        self.loading_state = LoadingState::RestoringDevice(
+                            self.phone_packages[i_user][p.index].name.clone(),
+                        );
+                    }
+                    // nothing to restore
+                    Err(AdbError::NotInstalled { error, .. }) => warn!("[RESTORE] {error}"),
+                    Err(AdbError::DeviceOffline { error, .. }) => {
+                        error!("[RESTORE] Device lost: {error}");
+                        self.loading_state = LoadingState::DeviceLost;
+                    }
+                    Err(err) => {
+                        error!("[RESTORE] {}", err.message());
+                        self.loading_state =
+                            LoadingState::RestoringDevice(format!("error, {}", err.message()));
+                    }
                 }
                 Command::none()
             }
@@ -163,7 +723,11 @@ impl List {
                 info!("{:-^65}", "-");
                 self.loading_state = LoadingState::DownloadingList;
                 Command::perform(
-                    Self::init_apps_view(remote, selected_device.clone()),
+                    Self::init_apps_view(
+                        remote,
+                        settings.general.list_url.clone(),
+                        selected_device.clone(),
+                    ),
                     Message::LoadPhonePackages,
                 )
             }
@@ -172,23 +736,157 @@ impl List {
                 self.uad_lists.clone_from(&uad_list);
                 *list_update_state = list_state;
                 Command::perform(
-                    Self::load_packages(
-                        uad_list,
-                        selected_device.adb_id.clone(),
-                        selected_device.user_list.clone(),
-                    ),
+                    Self::load_packages(uad_list, selected_device.clone()),
                     Message::ApplyFilters,
                 )
             }
             Message::ApplyFilters(packages) => {
                 self.phone_packages = packages;
-                self.filtered_packages = (0..self.phone_packages[i_user].len()).collect();
-                self.selected_package_state = Some(PackageState::Enabled);
-                self.selected_removal = Some(Removal::Recommended);
-                self.selected_list = Some(UadList::All);
+                // their keys may point to other packages now
+                self.confirm_action = None;
+                self.abort_actions();
+                let prefs = settings.device.view_prefs;
+                self.selected_package_state = Some(prefs.package_state);
+                self.selected_removal = Some(prefs.removal);
+                self.selected_origin = Some(prefs.origin);
+                self.selected_list = Some(prefs.uad_list);
+                self.show_selected_only = prefs.selected_only;
                 self.selected_user = Some(User::default());
                 Self::filter_package_lists(self);
                 self.loading_state = LoadingState::Ready;
+
+                if settings.general.persist_selection && selected_device.backup.is_none() {
+                    // Names that no longer exist on the device (or its user) are skipped
+                    let previous: Vec<(usize, usize)> = selection_users(selected_device)
+                        .iter()
+                        .flat_map(|u| {
+                            let user_packages = self.phone_packages.get(u.index);
+                            load_selection(&selected_device.adb_id, u.id)
+                                .into_iter()
+                                .filter_map(move |name| {
+                                    let i = user_packages?.iter().position(|p| p.name == name)?;
+                                    Some((u.index, i))
+                                })
+                        })
+                        .collect();
+                    self.restore_selection_modal = (!previous.is_empty()).then_some(previous);
+                }
+
+                if selected_device.backup.is_some() {
+                    return Command::none();
+                }
+                let serial = selected_device.adb_id.clone();
+                Command::batch([
+                    Command::perform(
+                        async move {
+                            backend().package_sizes(&serial).unwrap_or_else(|err| {
+                                warn!("Couldn't fetch package sizes: {err}");
+                                HashMap::new()
+                            })
+                        },
+                        Message::PackageSizesLoaded,
+                    ),
+                    Self::load_storage_info(selected_device),
+                ])
+            }
+            Message::PackageSizesLoaded(sizes) => {
+                self.package_sizes = sizes;
+                Command::none()
+            }
+            Message::StorageInfoLoaded(storage) => {
+                self.storage = storage;
+                Command::none()
+            }
+            Message::MoveFocus(down) => {
+                let Some(last) = self.filtered_packages.len().checked_sub(1) else {
+                    self.focused_row = None;
+                    return Command::none();
+                };
+                let pos = match self.focused_row {
+                    None => 0,
+                    Some(pos) if down => (pos + 1).min(last),
+                    Some(pos) => pos.saturating_sub(1),
+                };
+                self.focused_row = Some(pos);
+                #[expect(clippy::cast_precision_loss, reason = "way below 2^23 rows")]
+                let y = if last == 0 {
+                    0.0
+                } else {
+                    pos as f32 / last as f32
+                };
+                scrollable::snap_to(
+                    PACKAGES_SCROLLABLE.clone(),
+                    scrollable::RelativeOffset { x: 0.0, y },
+                )
+            }
+            Message::ToggleFocused => {
+                let Some(&i) = self
+                    .focused_row
+                    .and_then(|pos| self.filtered_packages.get(pos))
+                else {
+                    return Command::none();
+                };
+                let selected = self.phone_packages[i_user][i].selected;
+                // same path as the checkbox, so the same rules apply
+                self.update(
+                    settings,
+                    selected_device,
+                    list_update_state,
+                    Message::List(i, RowMessage::ToggleSelection(!selected)),
+                )
+            }
+            Message::SpinnerTick => {
+                self.spinner_frame = self.spinner_frame.wrapping_add(1);
+                Command::none()
+            }
+            Message::DevicesStatus(statuses) => {
+                self.devices_status = Some(statuses);
+                Command::none()
+            }
+            Message::LiveStateTick => {
+                if !matches!(self.loading_state, LoadingState::Ready)
+                    || self.live_state_sync_running
+                    || self.phone_packages.is_empty()
+                {
+                    return Command::none();
+                }
+                // Only what's on screen, to bound the cost
+                let names: Vec<String> = self
+                    .filtered_packages
+                    .iter()
+                    .filter(|&&i| !self.pending_actions.contains_key(&(i_user, i)))
+                    .map(|&i| self.phone_packages[i_user][i].name.clone())
+                    .collect();
+                if names.is_empty() {
+                    return Command::none();
+                }
+                self.live_state_sync_running = true;
+                let serial = selected_device.adb_id.clone();
+                let user_id = (selected_device.user_list.len() > 1)
+                    .then(|| self.selected_user.as_ref().map_or(0, |u| u.id));
+                Command::perform(
+                    async move {
+                        verify_package_state(&serial, user_id, &names)
+                            .map(|states| names.into_iter().zip(states).collect())
+                    },
+                    move |states| Message::LiveStatesVerified(i_user, states),
+                )
+            }
+            Message::LiveStatesVerified(user_index, states) => {
+                self.live_state_sync_running = false;
+                match states {
+                    Ok(states) => self.apply_verified_states(user_index, &states, None),
+                    Err(err) => warn!("Live state sync failed: {err}"),
+                }
+                Command::none()
+            }
+            Message::UninstallsVerified(user_index, states) => {
+                match states {
+                    Ok((states, leftovers)) => {
+                        self.apply_verified_states(user_index, &states, Some(&leftovers));
+                    }
+                    Err(err) => warn!("Couldn't verify the uninstalls: {err}"),
+                }
                 Command::none()
             }
             Message::ToggleAllSelected(selected) => {
@@ -203,7 +901,8 @@ impl List {
                         );
                     }
                 }
-                self.all_selected = selected;
+                // Unsafe packages are skipped without expert mode
+                self.all_selected = selected && self.all_selectable_selected(i_user, settings);
                 Command::none()
             }
             Message::SearchInputChanged(letter) => {
@@ -211,19 +910,44 @@ impl List {
                 Self::filter_package_lists(self);
                 Command::none()
             }
+            Message::SearchScopeSelected(scope) => {
+                self.search_scope = scope;
+                Self::filter_package_lists(self);
+                Command::none()
+            }
             Message::ListSelected(list) => {
                 self.selected_list = Some(list);
                 Self::filter_package_lists(self);
+                settings.device.view_prefs.uad_list = list;
+                Config::save_changes(settings, &selected_device.adb_id);
                 Command::none()
             }
             Message::PackageStateSelected(package_state) => {
                 self.selected_package_state = Some(package_state);
                 Self::filter_package_lists(self);
+                settings.device.view_prefs.package_state = package_state;
+                Config::save_changes(settings, &selected_device.adb_id);
                 Command::none()
             }
             Message::RemovalSelected(removal) => {
                 self.selected_removal = Some(removal);
                 Self::filter_package_lists(self);
+                settings.device.view_prefs.removal = removal;
+                Config::save_changes(settings, &selected_device.adb_id);
+                Command::none()
+            }
+            Message::OriginSelected(origin) => {
+                self.selected_origin = Some(origin);
+                Self::filter_package_lists(self);
+                settings.device.view_prefs.origin = origin;
+                Config::save_changes(settings, &selected_device.adb_id);
+                Command::none()
+            }
+            Message::ShowSelectedOnly(toggled) => {
+                self.show_selected_only = toggled;
+                Self::filter_package_lists(self);
+                settings.device.view_prefs.selected_only = toggled;
+                Config::save_changes(settings, &selected_device.adb_id);
                 Command::none()
             }
             Message::List(i_package, row_message) => {
@@ -244,23 +968,22 @@ impl List {
                         }
 
                         if settings.device.multi_user_mode {
+                            // users don't share the order of their package lists
+                            let name = package.name.clone();
                             for u in selected_device.user_list.iter().filter(|&u| !u.protected) {
-                                if let Some(pkg) = self
-                                    .phone_packages
-                                    .get_mut(u.index)
-                                    .and_then(|pkgs| pkgs.get_mut(i_package))
-                                {
-                                    pkg.selected = toggle;
-                                    if toggle
-                                        && !self.selected_packages.contains(&(u.index, i_package))
-                                    {
-                                        self.selected_packages.push((u.index, i_package));
-                                    }
+                                let Some((i, _)) =
+                                    user_package(&self.phone_packages, u.index, &name)
+                                else {
+                                    continue;
+                                };
+                                self.phone_packages[u.index][i].selected = toggle;
+                                let key = (u.index, i);
+                                if !toggle {
+                                    self.selected_packages.retain(|&x| x != key);
+                                } else if !self.selected_packages.contains(&key) {
+                                    self.selected_packages.push(key);
                                 }
                             }
-                            if !toggle {
-                                self.selected_packages.retain(|&x| x.1 != i_package);
-                            }
                         } else {
                             package.selected = toggle;
                             if toggle {
@@ -272,21 +995,31 @@ impl List {
                                     .retain(|&x| x.1 != i_package || x.0 != i_user);
                             }
                         }
+                        self.persist_selection(settings, selected_device);
                         Command::none()
                     }
-                    RowMessage::ActionPressed => {
-                        self.phone_packages[i_user][i_package].selected = true;
-                        Command::batch(build_action_pkg_commands(
-                            &self.phone_packages,
-                            selected_device,
-                            &settings.device,
-                            (i_user, i_package),
-                        ))
+                    RowMessage::ActionPressed | RowMessage::AllUsersActionPressed => {
+                        // A second click while the first action is still running
+                        // would race against it with a stale state.
+                        if self.pending_actions.contains_key(&(i_user, i_package)) {
+                            return Command::none();
+                        }
+                        let action = RowAction {
+                            key: (i_user, i_package),
+                            all_users: matches!(row_message, RowMessage::AllUsersActionPressed),
+                            disable_mode: self.disable_mode(settings, selected_device),
+                        };
+                        // restoring is always harmless
+                        if settings.general.confirm_every_action
+                            && self.phone_packages[i_user][i_package].state == PackageState::Enabled
+                        {
+                            self.confirm_action = Some(action);
+                            return Command::none();
+                        }
+                        self.row_action(settings, selected_device, action)
                     }
                     RowMessage::PackagePressed => {
                         self.description = package.clone().description;
-                        self.description_content =
-                            text_editor::Content::with_text(&package.description);
                         package.current = true;
                         if self.current_package_index != i_package {
                             self.phone_packages[i_user][self.current_package_index].current = false;
@@ -300,30 +1033,163 @@ impl List {
                 self.selection_modal = true;
                 Command::none()
             }
+            Message::UnsafeAcknowledged(ack) => {
+                self.unsafe_acknowledged = ack;
+                Command::none()
+            }
+            Message::RestorePreviousSelection => {
+                // each user gets back its own selection, regardless of `multi_user_mode`
+                for (u, i) in self.restore_selection_modal.take().unwrap_or_default() {
+                    let Some(package) = self.phone_packages.get_mut(u).and_then(|p| p.get_mut(i))
+                    else {
+                        continue;
+                    };
+                    if package.removal == Removal::Unsafe && !settings.general.expert_mode {
+                        continue;
+                    }
+                    package.selected = true;
+                    if !self.selected_packages.contains(&(u, i)) {
+                        self.selected_packages.push((u, i));
+                    }
+                }
+                self.persist_selection(settings, selected_device);
+                Command::none()
+            }
+            Message::DiscardPreviousSelection => {
+                self.restore_selection_modal = None;
+                self.persist_selection(settings, selected_device);
+                Command::none()
+            }
+            Message::ApplyPreset(preset) => {
+                let matching: Vec<usize> = self.phone_packages[i_user]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| {
+                        p.state == PackageState::Enabled
+                            && p.removal != Removal::Unsafe
+                            && preset.removals().contains(&p.removal)
+                    })
+                    .map(|(i, _)| i)
+                    .collect();
+                for i in matching {
+                    #[expect(unused_must_use, reason = "side-effect")]
+                    self.update(
+                        settings,
+                        selected_device,
+                        list_update_state,
+                        Message::List(i, RowMessage::ToggleSelection(true)),
+                    );
+                }
+                self.selection_modal = !self.selected_packages.is_empty();
+                Command::none()
+            }
+            Message::SelectPatternChanged(pattern) => {
+                self.select_pattern = pattern;
+                self.select_pattern_report = None;
+                Command::none()
+            }
+            Message::SelectMatching => {
+                let pattern = match Pattern::new(&self.select_pattern) {
+                    Ok(pattern) => pattern,
+                    Err(err) => {
+                        self.select_pattern_report = Some(Err(err));
+                        return Command::none();
+                    }
+                };
+                let matching: Vec<usize> = self.phone_packages[i_user]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, p)| !p.selected && pattern.is_match(&p.name))
+                    .map(|(i, _)| i)
+                    .collect();
+                let mut selected = 0;
+                for i in matching {
+                    #[expect(unused_must_use, reason = "side-effect")]
+                    self.update(
+                        settings,
+                        selected_device,
+                        list_update_state,
+                        Message::List(i, RowMessage::ToggleSelection(true)),
+                    );
+                    // `Unsafe` packages are refused without expert mode
+                    if self.phone_packages[i_user][i].selected {
+                        selected += 1;
+                    }
+                }
+                self.select_pattern_report = Some(Ok(selected));
+                Command::none()
+            }
             Message::UserSelected(user) => {
-                self.selected_user = Some(user);
+                self.selected_user = Some(user.clone());
+                self.unscoped = false;
                 self.filtered_packages = (0..self.phone_packages[user.index].len()).collect();
                 Self::filter_package_lists(self);
                 Command::none()
             }
-            Message::ChangePackageState(res) => {
-                match res {
-                    Ok(p) => {
-                        let package = &mut self.phone_packages[p.i_user][p.index];
-                        package.state = package.state.opposite(settings.device.disable_mode);
-                        package.selected = false;
-                        self.selected_packages
-                            .retain(|&x| x.1 != p.index && x.0 != p.i_user);
-                        Self::filter_package_lists(self);
-                    }
-                    Err(AdbError::Generic(err)) => {
-                        self.error_modal = Some(err);
+            Message::UserScopeSelected(scope) => match scope {
+                UserScope::User(user) => self.update(
+                    settings,
+                    selected_device,
+                    list_update_state,
+                    Message::UserSelected(user),
+                ),
+                UserScope::Unscoped => {
+                    let user = selected_device
+                        .user_list
+                        .first()
+                        .cloned()
+                        .unwrap_or_default();
+                    #[expect(unused_must_use, reason = "side-effect")]
+                    {
+                        self.update(
+                            settings,
+                            selected_device,
+                            list_update_state,
+                            Message::UserSelected(user),
+                        );
                     }
+                    self.unscoped = true;
+                    Command::none()
                 }
-                Command::none()
+            },
+            Message::ChangePackageState(batch, key, res) => {
+                if batch != self.batch {
+                    debug!("Ignoring the result of an aborted batch for {key:?}");
+                    return Command::none();
+                }
+                let uninstalled = self.change_package_state(settings, selected_device, key, res);
+                Command::batch([
+                    Self::verify_uninstalls(
+                        selected_device,
+                        key.0,
+                        uninstalled.into_iter().collect(),
+                    ),
+                    self.post_apply_hook(settings, selected_device),
+                ])
+            }
+            Message::ChangePackageStates(batch, results) => {
+                if batch != self.batch {
+                    debug!("Ignoring {} results of an aborted batch", results.len());
+                    return Command::none();
+                }
+                // uninstalls free space
+                let storage = Self::load_storage_info(selected_device);
+                // a batch is for a single user
+                let user_index = results.first().map_or(i_user, |(key, _)| key.0);
+                let uninstalled = results
+                    .into_iter()
+                    .filter_map(|(key, res)| {
+                        self.change_package_state(settings, selected_device, key, res)
+                    })
+                    .collect();
+                Command::batch([
+                    storage,
+                    Self::verify_uninstalls(selected_device, user_index, uninstalled),
+                    self.post_apply_hook(settings, selected_device),
+                ])
             }
             Message::ModalUserSelected(user) => {
-                self.selected_user = Some(user);
+                self.selected_user = Some(user.clone());
                 self.update(
                     settings,
                     selected_device,
@@ -331,14 +1197,42 @@ impl List {
                     Message::UserSelected(user),
                 )
             }
+            Message::ReloadPackages => {
+                // the lists and the search stay, the rest is about the previous packages
+                *self = Self {
+                    uad_lists: std::mem::take(&mut self.uad_lists),
+                    input_value: std::mem::take(&mut self.input_value),
+                    search_scope: self.search_scope,
+                    is_adb_satisfied: self.is_adb_satisfied,
+                    fake_adb: self.fake_adb,
+                    ..Self::default()
+                };
+                let list_state = *list_update_state;
+                self.update(
+                    settings,
+                    selected_device,
+                    list_update_state,
+                    Message::LoadPhonePackages((self.uad_lists.clone(), list_state)),
+                )
+            }
             Message::ClearSelectedPackages => {
+                // every user, not only the filtered packages of the current one
+                for package in self.phone_packages.iter_mut().flatten() {
+                    package.selected = false;
+                }
                 self.selected_packages = Vec::new();
+                self.all_selected = false;
+                self.persist_selection(settings, selected_device);
                 Command::none()
             }
             Message::ADBSatisfied(result) => {
                 self.is_adb_satisfied = result;
                 Command::none()
             }
+            Message::FakeAdb(fake) => {
+                self.fake_adb = fake;
+                Command::none()
+            }
             Message::UpdateFailed => {
                 self.loading_state = LoadingState::FailedToUpdate;
                 Command::none()
@@ -348,96 +1242,217 @@ impl List {
                 Command::none()
             }
             Message::ExportSelection => Command::perform(
-                export_selection(self.phone_packages[i_user].clone()),
+                export_selection(
+                    self.phone_packages[i_user].clone(),
+                    settings.general.export_folder.clone(),
+                ),
                 Message::SelectionExported,
             ),
             Message::SelectionExported(export) => {
                 match export {
-                    Ok(_) => self.export_modal = true,
-                    Err(err) => error!("Failed to export current selection: {err:?}"),
-                }
-                Command::none()
-            }
-            Message::Nothing => Command::none(),
-            Message::DescriptionEdit(action) => {
-                match action {
-                    text_editor::Action::Edit(_) => {
-                        // Do nothing - ignore all editing operations
-                    }
-                    text_editor::Action::Scroll { lines } => {}
-                    // Allow all other actions (movement, selection, clicking, scrolling, etc.)
-                    _ => {
-                        self.description_content.perform(action);
+                    Ok(path) => {
+                        open_url(settings.general.export_folder.clone());
+                        self.export_modal = Some(path);
                     }
+                    Err(err) => error!("Failed to export current selection: {err:?}"),
                 }
                 Command::none()
             }
-            Message::CopyError(err) => {
-                self.copy_confirmation = true;
-                Command::batch(vec![
-                    iced::clipboard::write::<Message>(err),
-                    Command::perform(Self::delay_hide_copy_confirmation(), |_| {
-                        Message::HideCopyConfirmation
-                    }),
-                ])
+            // `GoToSettings` is handled by the main view
+            Message::Nothing | Message::GoToSettings => Command::none(),
+            Message::CopyError(err) => self.copy_to_clipboard(Copied::Error, err),
+            Message::CopyCommand(cmd) => self.copy_to_clipboard(Copied::Command, cmd),
+            Message::CopyTable => {
+                let Some(packages) = self.phone_packages.get(i_user) else {
+                    return Command::none();
+                };
+                let tsv = packages_tsv(self.filtered_packages.iter().map(|&i| &packages[i]));
+                self.copy_to_clipboard(Copied::Table, tsv)
             }
             Message::HideCopyConfirmation => {
-                self.copy_confirmation = false;
+                self.copy_confirmation = None;
                 Command::none()
             }
         }
     }
 
+    fn load_storage_info(device: &Phone) -> Command<Message> {
+        let serial = device.adb_id.clone();
+        Command::perform(
+            async move { get_storage_info(&serial) },
+            Message::StorageInfoLoaded,
+        )
+    }
+
+    fn copy_to_clipboard(&mut self, copied: Copied, contents: String) -> Command<Message> {
+        self.copy_confirmation = Some(copied);
+        Command::batch(vec![
+            iced::clipboard::write::<Message>(contents),
+            Command::perform(Self::delay_hide_copy_confirmation(), |_| {
+                Message::HideCopyConfirmation
+            }),
+        ])
+    }
+
+    /// Why no device shows up, if we know
+    fn setup_help(&self) -> Option<Element<'_, Message, Theme, Renderer>> {
+        let (help, url) = self
+            .fake_adb
+            .then_some((FAKE_ADB, Some(GETTING_STARTED_URL)))
+            .or_else(|| detection_help(self.devices_status.as_deref()?))?;
+        Some(waiting_view(
+            help,
+            url.map(|url| {
+                button("Read the setup guide").on_press(Message::GoToUrl(PathBuf::from(url)))
+            }),
+            style::Text::Danger,
+            None,
+        ))
+    }
+
     /// Builds the main view for the app list interface
     pub fn view(
         &self,
         settings: &Settings,
         selected_device: &Phone,
     ) -> Element<Message, Theme, Renderer> {
+        if selected_device.adb_id.is_empty()
+            && let Some(help) = self.setup_help()
+        {
+            return help;
+        }
+        let frame = self.spinner_frame;
         match &self.loading_state {
             LoadingState::DownloadingList => waiting_view(
                 &format!("Downloading latest {NAME} lists from GitHub. Please wait..."),
                 Some(button("No internet?").on_press(Message::LoadUadList(false))),
                 style::Text::Default,
+                Some(frame),
             ),
             LoadingState::FindingPhones => {
                 if self.is_adb_satisfied {
-                    waiting_view("Finding connected devices...", None, style::Text::Default)
+                    waiting_view(
+                        "Finding connected devices...",
+                        None,
+                        style::Text::Default,
+                        Some(frame),
+                    )
                 } else {
                     waiting_view(
                         "ADB is not installed on your system, install ADB and relaunch application.",
-                        Some(button("Read on how to get started.")
-                    .on_press(Message::GoToUrl(PathBuf::from(
-                        "https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/wiki/Getting-started",
-                    )))),
+                        Some(
+                            button("Read on how to get started.")
+                                .on_press(Message::GoToUrl(PathBuf::from(GETTING_STARTED_URL))),
+                        ),
                         style::Text::Danger,
+                        None,
                     )
                 }
             }
+            LoadingState::SelectingDevice => waiting_view(
+                "Select a device in the list above",
+                None,
+                style::Text::Default,
+                None,
+            ),
             LoadingState::LoadingPackages => waiting_view(
                 "Pulling packages from the device. Please wait...",
                 None,
                 style::Text::Default,
+                Some(frame),
             ),
             LoadingState::_UpdatingUad => waiting_view(
                 &format!("Updating {NAME}. Please wait..."),
                 None,
                 style::Text::Default,
+                Some(frame),
             ),
             LoadingState::RestoringDevice(device) => waiting_view(
                 &format!("Restoring device: {device}"),
                 None,
                 style::Text::Default,
+                Some(frame),
             ),
+            // e.g. the device was unplugged while its packages were pulled.
+            // `ready_view` relies on the packages of the selected user to exist.
+            LoadingState::Ready
+                if self
+                    .phone_packages
+                    .get(self.selected_user.as_ref().map_or(0, |u| u.index))
+                    .is_none()
+                    || self.phone_packages.iter().all(Vec::is_empty) =>
+            {
+                waiting_view(
+                    "No packages loaded \u{2014} refresh",
+                    None,
+                    style::Text::Danger,
+                    None,
+                )
+            }
             LoadingState::Ready => self.ready_view(settings, selected_device),
             LoadingState::FailedToUpdate => waiting_view(
                 "Failed to download update",
                 Some(button("Go back").on_press(Message::LoadUadList(false))),
                 style::Text::Danger,
+                None,
+            ),
+            LoadingState::Rebooting(device) => waiting_view(
+                &format!("Rebooting {device}, waiting for it to come back..."),
+                None,
+                style::Text::Default,
+                Some(frame),
+            ),
+            LoadingState::RebootTimedOut(device) => waiting_view(
+                &format!(
+                    "{device} didn't come back after rebooting.\nCheck that it booted and that USB debugging is still allowed, then refresh."
+                ),
+                None,
+                style::Text::Danger,
+                None,
+            ),
+            LoadingState::DeviceLost => waiting_view(
+                "Device disconnected \u{2014} reconnect and refresh\n\
+                Commands it already received may have finished anyway.",
+                None,
+                style::Text::Danger,
+                None,
             ),
         }
     }
 
+    /// "Showing X of Y • Z selected", below the control panel
+    fn counts_line(&self) -> Element<'_, Message, Theme, Renderer> {
+        let total = self
+            .phone_packages
+            .get(self.selected_user.as_ref().map_or(0, |u| u.index))
+            .map_or(0, Vec::len);
+        row![
+            text(counts_summary(
+                self.filtered_packages.len(),
+                total,
+                self.selected_packages.len()
+            ))
+            .style(style::Text::Commentary)
+        ]
+        .width(Length::Fill)
+        .padding([0, 16, 0, 8])
+        .into()
+    }
+
+    /// What the removal category picked in the filters means
+    fn removal_explanation(&self) -> Option<Element<'_, Message, Theme, Renderer>> {
+        let removal = self.selected_removal?;
+        let style = if removal == Removal::Unsafe {
+            style::Text::Warning
+        } else {
+            style::Text::Commentary
+        };
+        let explanation = row![text(format!("{removal}: {}", removal.description()?)).style(style)]
+            .width(Length::Fill)
+            .padding([0, 16, 0, 8]);
+        Some(explanation.into())
+    }
+
     fn control_panel(&self, selected_device: &Phone) -> Element<Message, Theme, Renderer> {
         let search_packages = text_input("Search packages...", &self.input_value)
             .width(Length::Fill)
@@ -464,12 +1479,30 @@ impl List {
         ]
         .padding(8);
 
+        let selected_only_checkbox = checkbox("Selected only", self.show_selected_only)
+            .on_toggle(Message::ShowSelectedOnly)
+            .style(style::CheckBox::SettingsEnabled);
+
+        let user_scopes: Vec<UserScope> = supports_multi_user(selected_device)
+            .then_some(UserScope::Unscoped)
+            .into_iter()
+            .chain(
+                selected_device
+                    .user_list
+                    .iter()
+                    .cloned()
+                    .map(UserScope::User),
+            )
+            .collect();
         let user_picklist = pick_list(
-            selected_device.user_list.clone(),
-            self.selected_user,
-            Message::UserSelected,
-        )
-        .width(85);
+            user_scopes,
+            if self.unscoped {
+                Some(UserScope::Unscoped)
+            } else {
+                self.selected_user.clone().map(UserScope::User)
+            },
+            Message::UserScopeSelected,
+        );
 
         let list_picklist = pick_list(UadList::ALL, self.selected_list, Message::ListSelected);
         let package_state_picklist = pick_list(
@@ -484,13 +1517,30 @@ impl List {
             Message::RemovalSelected,
         );
 
+        let origin_picklist = pick_list(Origin::ALL, self.selected_origin, Message::OriginSelected);
+
+        let search_scope_picklist = tooltip(
+            pick_list(
+                SearchScope::ALL,
+                Some(self.search_scope),
+                Message::SearchScopeSelected,
+            ),
+            "Search in",
+            tooltip::Position::Top,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4);
+
         row![
             col_sel_all,
             search_packages,
+            search_scope_picklist,
+            selected_only_checkbox,
             user_picklist,
             removal_picklist,
             package_state_picklist,
             list_picklist,
+            origin_picklist,
         ]
         .width(Length::Fill)
         .align_items(Alignment::Center)
@@ -505,24 +1555,81 @@ impl List {
         settings: &Settings,
         selected_device: &Phone,
     ) -> Element<Message, Theme, Renderer> {
-        let packages = self
-            .filtered_packages
+        let multi_user = selected_device
+            .user_list
             .iter()
-            .fold(column![].spacing(6), |col, &i| {
+            .filter(|u| !u.protected)
+            .count()
+            > 1;
+        let disable_mode = self.disable_mode(settings, selected_device);
+        let packages = self.filtered_packages.iter().fold(
+            column![].spacing(if settings.general.dense_mode { 1 } else { 6 }),
+            |col, &i| {
+                let package =
+                    &self.phone_packages[self.selected_user.as_ref().map_or(0, |u| u.index)][i];
+                let focused = self
+                    .focused_row
+                    .is_some_and(|pos| self.filtered_packages.get(pos) == Some(&i));
+                let users_state = if multi_user {
+                    self.users_state
+                        .get(&package.name)
+                        .map(|states| {
+                            let label = users_state_label(states, &selected_device.user_list);
+                            match uninstalled_scope(package, states, &selected_device.user_list) {
+                                Some(scope) => format!("{label} {scope}"),
+                                None => label,
+                            }
+                        })
+                        .unwrap_or_default()
+                } else {
+                    String::new()
+                };
                 col.push(
-                    self.phone_packages[self.selected_user.unwrap_or_default().index][i]
-                        .view(settings, selected_device)
+                    package
+                        .view(
+                            settings,
+                            disable_mode,
+                            selected_device,
+                            self.pending_actions.contains_key(&(
+                                self.selected_user.as_ref().map_or(0, |u| u.index),
+                                i,
+                            )),
+                            users_state,
+                            focused,
+                        )
                         .map(move |msg| Message::List(i, msg)),
                 )
-            });
+            },
+        );
 
         let packages_scrollable = scrollable(packages)
+            .id(PACKAGES_SCROLLABLE.clone())
             .height(Length::FillPortion(6))
             .style(style::Scrollable::Packages);
 
-        let description_scroll =
-            scrollable(text_editor(&self.description_content).on_action(Message::DescriptionEdit))
-                .style(style::Scrollable::Description);
+        let current_blocked = self
+            .phone_packages
+            .get(self.selected_user.as_ref().map_or(0, |u| u.index))
+            .and_then(|user_packages| user_packages.get(self.current_package_index))
+            .is_some_and(|p| p.current && p.is_blocked(settings));
+        let unsafe_notice = if current_blocked {
+            row![
+                text(UNSAFE_BLOCKED).style(style::Text::Warning),
+                button(text("Why?"))
+                    .padding(0)
+                    .style(style::Button::Link)
+                    .on_press(Message::GoToUrl(PathBuf::from(UNSAFE_WIKI_URL))),
+            ]
+            .spacing(6)
+            .padding([5, 5, 0, 5])
+        } else {
+            row![]
+        };
+        let description_scroll = scrollable(column![
+            unsafe_notice,
+            description_view(&self.description).padding(5)
+        ])
+        .style(style::Scrollable::Description);
 
         let description_panel = container(description_scroll)
             .padding(6)
@@ -535,7 +1642,7 @@ impl List {
                 "Review selection ({})",
                 self.selected_packages.len()
             ));
-            if self.selected_packages.is_empty() {
+            if self.selected_packages.is_empty() || selected_device.backup.is_some() {
                 button(tmp_widget).padding([5, 10])
             } else {
                 button_primary(tmp_widget).on_press(Message::ApplyActionOnSelection)
@@ -555,9 +1662,91 @@ impl List {
         // lock
         let export_selection = export_selection;
 
+        let copy_table = if self.copy_confirmation == Some(Copied::Table) {
+            button_primary("Copied!")
+        } else {
+            button_primary("Copy list")
+                .on_press_maybe((!self.filtered_packages.is_empty()).then_some(Message::CopyTable))
+        };
+        let copy_table = tooltip(
+            copy_table,
+            "Copy the listed packages as a table, to paste in a chat or an issue",
+            tooltip::Position::Top,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4);
+
+        let preset_btns = Preset::ALL.iter().fold(row![].spacing(10), |row, &preset| {
+            // it ends in the review of the selection, which can't be applied offline
+            row.push(
+                button_primary(preset.as_str()).on_press_maybe(
+                    selected_device
+                        .backup
+                        .is_none()
+                        .then_some(Message::ApplyPreset(preset)),
+                ),
+            )
+        });
+
+        let select_input = tooltip(
+            text_input("Select matching (pattern)", &self.select_pattern)
+                .on_input(Message::SelectPatternChanged)
+                .on_submit(Message::SelectMatching)
+                .padding([5, 10])
+                .width(200),
+            PATTERN_SYNTAX,
+            tooltip::Position::Bottom,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4);
+        let select_matching = row![
+            select_input,
+            button_primary("Select").on_press_maybe(
+                (!self.select_pattern.is_empty()).then_some(Message::SelectMatching)
+            ),
+        ]
+        .spacing(6)
+        .align_items(Alignment::Center);
+        let select_matching = match &self.select_pattern_report {
+            Some(Ok(n)) => select_matching.push(text(format!("{n} selected"))),
+            Some(Err(err)) => select_matching.push(text(err).style(style::Text::Danger)),
+            None => select_matching,
+        };
+
+        let clear_selection = button_primary("Clear selection").on_press_maybe(
+            (!self.selected_packages.is_empty()).then_some(Message::ClearSelectedPackages),
+        );
+
+        // the history is the device's, not the one of a backup opened offline
+        let history_btn = button_primary("History").on_press_maybe(
+            selected_device
+                .backup
+                .is_none()
+                .then_some(Message::ShowHistory),
+        );
+        let undo_btn = tooltip(
+            button_primary("Undo").on_press_maybe(
+                self.last_action
+                    .as_ref()
+                    .filter(|a| !a.packages.is_empty() && self.pending_actions.is_empty())
+                    .map(|_| Message::Undo),
+            ),
+            "Revert the last action (Ctrl+Z)",
+            tooltip::Position::Top,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4);
+
         let action_row = row![
+            clear_selection,
             export_selection,
+            copy_table,
+            history_btn,
+            undo_btn,
+            preset_btns,
+            select_matching,
             Space::new(Length::Fill, Length::Shrink),
+            self.mode_indicator(settings, selected_device),
             review_selection
         ]
         .width(Length::Fill)
@@ -579,9 +1768,19 @@ impl List {
                 .center_x()
                 .style(style::Container::BorderedFrame);
 
+        let offline_notice =
+            match &selected_device.backup {
+                Some(backup) => row![text(format!(
+                "Offline / from backup {}: read-only, export the selection to apply it later",
+                backup.display()
+            ))
+            .style(style::Text::Warning)],
+                None => row![],
+            };
+
         let control_panel = self.control_panel(selected_device);
         let content = if selected_device.user_list.is_empty()
-            || match self.selected_user {
+            || match &self.selected_user {
                 Some(u) => !self.phone_packages[u.index].is_empty(),
                 // If no user has been selected,
                 // then it could be considered as "equivalent"
@@ -590,12 +1789,11 @@ impl List {
                 // because other parts of the code simply use a `default` `User`.
                 None => true,
             } {
-            column![
-                control_panel,
-                packages_scrollable,
-                description_panel,
-                action_row,
-            ]
+            column![offline_notice, control_panel, self.counts_line()]
+                .push_maybe(self.removal_explanation())
+                .push(packages_scrollable)
+                .push(description_panel)
+                .push(action_row)
         } else {
             column![
                 control_panel,
@@ -612,14 +1810,51 @@ impl List {
                 self.apply_selection_modal(
                     selected_device,
                     settings,
-                    &self.phone_packages[self.selected_user.unwrap_or_default().index],
+                    &self.phone_packages[self.selected_user.as_ref().map_or(0, |u| u.index)],
                 ),
             )
             .on_blur(Message::ModalHide)
             .into();
         }
 
-        if self.export_modal {
+        if let Some(previous) = &self.restore_selection_modal {
+            let title = container(
+                row![text("Previous selection found").size(24)].align_items(Alignment::Center),
+            )
+            .width(Length::Fill)
+            .style(style::Container::Frame)
+            .padding([10, 0, 10, 0])
+            .center_y()
+            .center_x();
+
+            let text_box = row![
+                text(format!(
+                    "Restore previous selection of {} packages?",
+                    previous.len()
+                ))
+                .width(Length::Fill),
+            ]
+            .padding(20);
+
+            let modal_btn_row = row![
+                button(text("Discard")).on_press(Message::DiscardPreviousSelection),
+                horizontal_space(),
+                button_primary(text("Restore")).on_press(Message::RestorePreviousSelection),
+            ]
+            .padding([0, 15, 10, 10]);
+
+            let ctn = container(column![title, text_box, modal_btn_row])
+                .height(Length::Shrink)
+                .width(500)
+                .padding(10)
+                .style(style::Container::Frame);
+
+            return Modal::new(content.padding(10), ctn)
+                .on_blur(Message::DiscardPreviousSelection)
+                .into();
+        }
+
+        if let Some(path) = &self.export_modal {
             let title = container(row![text("Success").size(24)].align_items(Alignment::Center))
                 .width(Length::Fill)
                 .style(style::Container::Frame)
@@ -627,11 +1862,11 @@ impl List {
                 .center_y()
                 .center_x();
 
-            let text_box = row![
-                text(format!("Exported current selection into file.\nFile is exported in same directory where {NAME} is located.")).width(Length::Fill),
-            ].padding(20);
+            let text_box = row![text("Exported current selection into file.").width(Length::Fill),]
+                .padding(20);
 
-            let file_row = row![text(EXPORT_FILE_NAME).style(style::Text::Commentary)].padding(20);
+            let file_row =
+                row![text(path.to_string_lossy()).style(style::Text::Commentary)].padding(20);
 
             let modal_btn_row = row![
                 Space::new(Length::Fill, Length::Shrink),
@@ -652,6 +1887,18 @@ impl List {
                 .into();
         }
 
+        if let Some(entries) = &self.history_modal {
+            return history_view(entries, content.padding(10)).into();
+        }
+
+        // a vanished package leaves nothing to confirm
+        if let Some((action, package)) = self
+            .confirm_action
+            .and_then(|a| Some((a, self.package_at(a.key)?)))
+        {
+            return confirm_action_view(package, action, content.padding(10)).into();
+        }
+
         if let Some(err) = &self.error_modal {
             error_view(err, content, self.copy_confirmation).into()
         } else {
@@ -677,29 +1924,43 @@ impl List {
                 _ => summary.discard += 1,
             }
         }
+        let freed = freed_estimate(
+            &self.phone_packages,
+            &self.selected_packages,
+            &self.package_sizes,
+        );
 
         let radio_btn_users = device.user_list.iter().filter(|&u| !u.protected).fold(
             row![].spacing(10),
-            |row, &user| {
+            |row, user| {
+                // `radio` values must be `Copy`
                 row.push(
                     radio(
-                        format!("{}", user.clone()),
-                        user,
-                        self.selected_user,
-                        Message::ModalUserSelected,
+                        user.to_string(),
+                        user.id,
+                        self.selected_user.as_ref().map(|u| u.id),
+                        |_| Message::ModalUserSelected(user.clone()),
                     )
                     .size(24),
                 )
             },
         );
 
-        let title_ctn =
-            container(row![text("Review your selection").size(24)].align_items(Alignment::Center))
-                .width(Length::Fill)
-                .style(style::Container::Frame)
-                .padding([10, 0, 10, 0])
-                .center_y()
-                .center_x();
+        let title = if settings.general.dry_run {
+            row![
+                text("Review your selection").size(24),
+                text("DRY RUN").size(24).style(style::Text::Danger),
+            ]
+            .spacing(20)
+        } else {
+            row![text("Review your selection").size(24)]
+        };
+        let title_ctn = container(title.align_items(Alignment::Center))
+            .width(Length::Fill)
+            .style(style::Container::Frame)
+            .padding([10, 0, 10, 0])
+            .center_y()
+            .center_x();
 
         let users_ctn = container(radio_btn_users)
             .padding(10)
@@ -731,32 +1992,101 @@ impl List {
         .padding(10)
         .style(style::Container::BorderedFrame);
 
+        let unsafe_packages = self.selected_unsafe_packages();
+
         let modal_btn_row = row![
             button(text("Cancel")).on_press(Message::ModalHide),
             horizontal_space(),
-            button(text("Apply")).on_press(Message::ModalValidate),
+            button(text("Apply")).on_press_maybe(
+                (unsafe_packages.is_empty() || self.unsafe_acknowledged)
+                    .then_some(Message::ModalValidate)
+            ),
         ]
         .padding([0, 15, 10, 10]);
 
+        let unsafe_ctn = if unsafe_packages.is_empty() {
+            container(column![])
+        } else {
+            container(
+                column![
+                    text(format!(
+                        "{} Unsafe package(s) will be removed:",
+                        unsafe_packages.len()
+                    ))
+                    .style(style::Text::Danger),
+                    text(unsafe_packages.join(", ")).style(style::Text::Danger),
+                    checkbox(
+                        "These may bootloop your device. I understand the risk.",
+                        self.unsafe_acknowledged,
+                    )
+                    .on_toggle(Message::UnsafeAcknowledged)
+                    .style(style::CheckBox::SettingsEnabled),
+                ]
+                .spacing(6),
+            )
+            .width(Length::Fill)
+            .padding(10)
+            .style(style::Container::BorderedFrame)
+        };
+        let unsafe_ctn = row![unsafe_ctn].padding([0, 10, 0, 10]);
+
+        let system_core_packages = self.selected_system_core_packages(device);
+        let unsafe_ctn = if system_core_packages.is_empty() {
+            column![unsafe_ctn]
+        } else {
+            column![
+                row![
+                    text(format!(
+                        "Core system components will be removed for the primary user: {}. \
+                        They're not marked Unsafe, but double-check you don't rely on them.",
+                        system_core_packages.join(", ")
+                    ))
+                    .style(style::Text::Warning)
+                ]
+                .padding([0, 20, 0, 20]),
+                unsafe_ctn,
+            ]
+            .spacing(10)
+        };
+
         let recap_view = summaries
             .iter()
             .fold(column![].spacing(6).width(Length::Fill), |col, r| {
                 col.push(recap(settings, r))
             });
+        // disabling keeps the data, so only uninstalls free space
+        let recap_view = if settings.device.disable_mode {
+            recap_view
+        } else if freed > 0 {
+            recap_view.push(
+                row![
+                    text(format!(
+                        "~{} will be freed by the uninstalls",
+                        format_size(freed)
+                    ))
+                    .style(style::Text::Ok)
+                ]
+                .padding([0, 10]),
+            )
+        } else {
+            recap_view
+        };
 
         let selected_pkgs_ctn = container(
             container(
                 scrollable(
                     container(
-                        if self
-                            .selected_packages
-                            .iter()
-                            .any(|s| s.0 == self.selected_user.expect(PACK_NO_USER_MSG).index)
-                        {
+                        if self.selected_packages.iter().any(|s| {
+                            s.0 == self.selected_user.as_ref().expect(PACK_NO_USER_MSG).index
+                        }) {
                             self.selected_packages
                                 .iter()
                                 .filter(|s| {
-                                    s.0 == self.selected_user.expect(PACK_NO_USER_MSG).index
+                                    s.0 == self
+                                        .selected_user
+                                        .as_ref()
+                                        .expect(PACK_NO_USER_MSG)
+                                        .index
                                 })
                                 .fold(
                                     column![].spacing(6).width(Length::Fill),
@@ -834,6 +2164,7 @@ impl List {
                     row![explaination_ctn].padding([0, 10, 0, 10]),
                     container(recap_view).padding(10),
                     selected_pkgs_ctn,
+                    unsafe_ctn,
                     modal_btn_row,
                 ]
                 .spacing(10)
@@ -844,6 +2175,7 @@ impl List {
                     users_ctn,
                     container(recap_view).padding(10),
                     selected_pkgs_ctn,
+                    unsafe_ctn,
                     modal_btn_row,
                 ]
                 .spacing(10)
@@ -853,6 +2185,7 @@ impl List {
                     title_ctn,
                     container(recap_view).padding(10),
                     selected_pkgs_ctn,
+                    unsafe_ctn,
                     modal_btn_row,
                 ]
                 .spacing(10)
@@ -865,6 +2198,44 @@ impl List {
         .style(style::Container::Background)
         .into()
     }
+    /// Names of the selected `Unsafe` packages that are going to be removed,
+    /// across all users
+    fn selected_unsafe_packages(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .selected_packages
+            .iter()
+            .filter_map(|&(u, i)| self.phone_packages.get(u)?.get(i))
+            .filter(|p| p.removal == Removal::Unsafe && p.state == PackageState::Enabled)
+            .map(|p| p.name.as_str())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
+    /// Like [`Self::selected_unsafe_packages`], for the [`is_system_core`] ones
+    /// about to be removed from the primary user
+    fn selected_system_core_packages(&self, device: &Phone) -> Vec<&str> {
+        let Some(primary) = device.user_list.iter().find(|u| u.id == 0) else {
+            return vec![];
+        };
+        let mut names: Vec<&str> = self
+            .selected_packages
+            .iter()
+            .filter(|&&(u, _)| u == primary.index)
+            .filter_map(|&(u, i)| self.phone_packages.get(u)?.get(i))
+            .filter(|p| {
+                p.removal != Removal::Unsafe
+                    && p.state == PackageState::Enabled
+                    && is_system_core(p)
+            })
+            .map(|p| p.name.as_str())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
     fn filter_package_lists(&mut self) {
         let list_filter: UadList = self.selected_list.expect("UAD-list type must be selected");
         let package_filter: PackageState = self
@@ -873,10 +2244,19 @@ impl List {
         let removal_filter: Removal = self
             .selected_removal
             .expect("removal recommendation must be selected");
+        let origin_filter = self.selected_origin.unwrap_or_default();
 
-        self.filtered_packages = self.phone_packages
-            [self.selected_user.expect("User must be selected").index]
-            .iter()
+        // after a failed pull, there may be no package for the user
+        self.filtered_packages = self
+            .phone_packages
+            .get(
+                self.selected_user
+                    .as_ref()
+                    .expect("User must be selected")
+                    .index,
+            )
+            .into_iter()
+            .flatten()
             // we must filter the indices associated with pack-rows,
             // that's why `enumerate` is before `filter`.
             .enumerate()
@@ -884,33 +2264,237 @@ impl List {
                 (list_filter == UadList::All || p.uad_list == list_filter)
                     && (package_filter == PackageState::All || p.state == package_filter)
                     && (removal_filter == Removal::All || p.removal == removal_filter)
+                    && (origin_filter == Origin::All || p.origin == origin_filter)
+                    && (!self.show_selected_only || p.selected)
                     && (self.input_value.is_empty()
-                        || p.name.contains(&self.input_value)
-                        || p.description.contains(&self.input_value))
+                        || self.search_scope.matches(p, &self.input_value))
             })
             .map(|(i, _)| i)
             .collect();
+        let len = self.filtered_packages.len();
+        self.focused_row = self.focused_row.filter(|&pos| pos < len);
+        self.refresh_users_state();
     }
+
+    /// Whether every filtered package of `i_user` that can be selected is,
+    /// and there is at least one
+    fn all_selectable_selected(&self, i_user: usize, settings: &Settings) -> bool {
+        let mut selectable = self
+            .filtered_packages
+            .iter()
+            .filter_map(|&i| self.phone_packages.get(i_user)?.get(i))
+            .filter(|p| p.removal != Removal::Unsafe || settings.general.expert_mode)
+            .peekable();
+        selectable.peek().is_some() && selectable.all(|p| p.selected)
+    }
+
+    /// Whether packages get uninstalled or disabled, which only shows in the settings otherwise
+    fn mode_indicator(
+        &self,
+        settings: &Settings,
+        device: &Phone,
+    ) -> Element<'_, Message, Theme, Renderer> {
+        let mode = |disable| match (disable, settings.device.keep_data) {
+            (true, _) => "Disable",
+            (false, true) => "Uninstall, keep data",
+            (false, false) => "Uninstall",
+        };
+        let rows_mode = self.disable_mode(settings, device);
+        let label = if rows_mode == settings.device.disable_mode {
+            format!("Mode: {}", mode(rows_mode))
+        } else {
+            // Alt is held
+            format!(
+                "Mode: {} (rows: {})",
+                mode(settings.device.disable_mode),
+                mode(rows_mode)
+            )
+        };
+        tooltip(
+            button(text(label))
+                .padding([5, 10])
+                .style(style::Button::Primary)
+                .on_press(Message::GoToSettings),
+            "Change it in the settings of the device",
+            tooltip::Position::Top,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4)
+        .into()
+    }
+
+    /// `disable_mode` of the device settings, unless [`Self::invert_mode`].
+    /// Only devices supporting it can disable packages.
+    fn disable_mode(&self, settings: &Settings, device: &Phone) -> bool {
+        settings.device.disable_mode != (self.invert_mode && device.android_sdk >= 23)
+    }
+
+    /// Action button of a package, for its user or every user
+    fn row_action(
+        &mut self,
+        settings: &Settings,
+        selected_device: &Phone,
+        action: RowAction,
+    ) -> Command<Message> {
+        let device_settings = DeviceSettings {
+            disable_mode: action.disable_mode,
+            ..settings.device.clone()
+        };
+        let key = action.key;
+        self.start_action();
+        if action.all_users {
+            return Command::batch(build_all_users_pkg_commands(
+                &self.phone_packages,
+                selected_device,
+                &device_settings,
+                key,
+                self.batch,
+                &mut self.pending_actions,
+            ));
+        }
+        self.phone_packages[key.0][key.1].selected = true;
+        Command::batch(build_action_pkg_commands(
+            &self.phone_packages,
+            selected_device,
+            &device_settings,
+            key,
+            self.unscoped,
+            self.batch,
+            &mut self.pending_actions,
+        ))
+    }
+
+    /// Forgets the running actions. Those already sent to the device still run,
+    /// but their results are ignored, see [`Self::batch`].
+    fn abort_actions(&mut self) {
+        self.pending_actions.clear();
+        self.post_apply_hook_pending = false;
+        self.batch += 1;
+    }
+
+    /// The results of the next package actions make up the new [`Self::last_action`]
+    fn start_action(&mut self) {
+        self.last_action = Some(AppliedAction {
+            packages: vec![],
+            unscoped: self.unscoped,
+        });
+    }
+
+    /// Keeps the outcome of the action bringing the package at `key` `to` a state, see [`history`]
+    fn record_action(
+        &self,
+        device: &Phone,
+        key: (usize, usize),
+        to: Option<PackageState>,
+        res: &Result<PackageInfo, AdbError>,
+    ) {
+        if device.adb_id.is_empty() {
+            return;
+        }
+        let (Some(package), Some(to)) = (
+            self.phone_packages.get(key.0).and_then(|p| p.get(key.1)),
+            to,
+        ) else {
+            return;
+        };
+        let user = device
+            .user_list
+            .iter()
+            .find(|u| u.index == key.0)
+            .map_or(0, |u| u.id);
+        let entry = history::Entry::new(
+            user,
+            &package.name,
+            package.state,
+            to,
+            res.as_ref().err().map(|e| e.message().to_string()),
+        );
+        if let Err(e) = history::record(&device.adb_id, &entry) {
+            error!("Couldn't record the action in the history: {e}");
+        }
+    }
+
+    fn refresh_users_state(&mut self) {
+        self.users_state.clear();
+        for (i_user, packages) in self.phone_packages.iter().enumerate() {
+            for p in packages {
+                self.users_state
+                    .entry(p.name.clone())
+                    .or_default()
+                    .push((i_user, p.state));
+            }
+        }
+    }
+    /// Keep the selection on disk, so it survives a crash or a restart.
+    /// Only done once packages are loaded, as switching devices
+    /// transiently clears the selection.
+    /// A backup opened offline has the `adb_id` of its device: its selection isn't kept,
+    /// so it doesn't overwrite the one of the device.
+    fn persist_selection(&self, settings: &Settings, device: &Phone) {
+        if !settings.general.persist_selection
+            || device.backup.is_some()
+            || !matches!(self.loading_state, LoadingState::Ready)
+            || device.adb_id.is_empty()
+        {
+            return;
+        }
+        for user in selection_users(device) {
+            if let Err(e) =
+                save_selection(&device.adb_id, user.id, &self.selection_names(user.index))
+            {
+                error!("Failed to save current selection of user {}: {e}", user.id);
+            }
+        }
+    }
+
+    /// Sorted names of the selected packages of the user at `user_index`, as persisted
+    fn selection_names(&self, user_index: usize) -> Vec<String> {
+        let mut names: Vec<String> = self
+            .selected_packages
+            .iter()
+            .filter(|&&(u, _)| u == user_index)
+            .filter_map(|&(u, i)| self.phone_packages.get(u)?.get(i))
+            .map(|p| p.name.clone())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+
     #[expect(clippy::unused_async, reason = "1 call-site")]
-    async fn load_packages<S: AsRef<str>>(
-        uad_list: PackageHashMap,
-        device_serial: S,
-        user_list: Vec<User>,
-    ) -> Vec<Vec<PackageRow>> {
-        let serial = device_serial.as_ref();
-        if user_list.len() <= 1 {
-            vec![fetch_packages(&uad_list, serial, None)]
+    async fn load_packages(uad_list: PackageHashMap, device: Phone) -> Vec<Vec<PackageRow>> {
+        if let Some(backup) = &device.backup {
+            return offline_packages(backup, &uad_list).unwrap_or_else(|err| {
+                error!("[BACKUP] Cannot open {}: {err}", backup.display());
+                vec![vec![]]
+            });
+        }
+        let serial = &device.adb_id;
+        // a single dump for all users, once per refresh
+        let until_used = disabled_until_used(serial);
+        let none = HashSet::new();
+        let until_used_of = |user_id| until_used.get(&user_id).unwrap_or(&none);
+        if device.user_list.len() <= 1 {
+            // single-user devices only have the owner
+            vec![fetch_packages(&uad_list, serial, None, until_used_of(0))]
         } else {
-            user_list
+            device
+                .user_list
                 .iter()
-                .map(|user| fetch_packages(&uad_list, serial, Some(user.id)))
+                .map(|user| {
+                    fetch_packages(&uad_list, serial, Some(user.id), until_used_of(user.id))
+                })
                 .collect()
         }
     }
 
     #[expect(clippy::unused_async, reason = "1 call-site")]
-    async fn init_apps_view(remote: bool, phone: Phone) -> (PackageHashMap, UadListState) {
-        let uad_lists = load_debloat_lists(remote);
+    async fn init_apps_view(
+        remote: bool,
+        list_url: Option<String>,
+        phone: Phone,
+    ) -> (PackageHashMap, UadListState) {
+        let uad_lists = load_debloat_lists(remote, list_url.as_deref());
         match uad_lists {
             Ok(list) => {
                 if phone.adb_id.is_empty() {
@@ -918,11 +2502,11 @@ impl List {
                 }
                 (list, UadListState::Done)
             }
-            Err(local_list) => {
+            Err((local_list, err)) => {
                 error!(
-                    "Error loading remote debloat list for the phone. Fallback to embedded (and outdated) list"
+                    "Error loading remote debloat list for the phone ({err}). Fallback to embedded (and outdated) list"
                 );
-                (local_list, UadListState::Failed)
+                (local_list, err.into())
             }
         }
     }
@@ -933,9 +2517,9 @@ impl List {
 }
 
 fn error_view<'a>(
-    error: &'a str,
+    error: &'a AdbError,
     content: Column<'a, Message, Theme, Renderer>,
-    copy_confirmation: bool,
+    copy_confirmation: Option<Copied>,
 ) -> Modal<'a, Message, Theme, Renderer> {
     let title_ctn = container(
         row![text("Failed to perform ADB operation").size(24)].align_items(Alignment::Center),
@@ -946,27 +2530,33 @@ fn error_view<'a>(
     .center_y()
     .center_x();
 
-    let modal_btn_row = row![
+    let copy_btn = |copied: Copied, label: &'static str, msg: Message| {
+        let done = copy_confirmation == Some(copied);
         button(
-            text(if copy_confirmation {
-                "Copied!"
-            } else {
-                "Copy error"
-            })
-            .width(Length::Fill)
-            .horizontal_alignment(alignment::Horizontal::Center),
+            text(if done { "Copied!" } else { label })
+                .width(Length::Fill)
+                .horizontal_alignment(alignment::Horizontal::Center),
         )
         .width(Length::Fill)
-        .on_press_maybe(if copy_confirmation {
-            None
-        } else {
-            Some(Message::CopyError(error.to_string()))
-        })
-        .style(if copy_confirmation {
+        .on_press_maybe(if done { None } else { Some(msg) })
+        .style(if done {
             style::Button::Primary
         } else {
             style::Button::default()
-        }),
+        })
+    };
+
+    let modal_btn_row = row![
+        copy_btn(
+            Copied::Error,
+            "Copy error",
+            Message::CopyError(error.message().to_string())
+        ),
+        copy_btn(
+            Copied::Command,
+            "Copy command",
+            Message::CopyCommand(error.command().to_string())
+        ),
         button(
             text("Close")
                 .width(Length::Fill)
@@ -977,27 +2567,213 @@ fn error_view<'a>(
     ]
     .padding([10, 0, 0, 0]);
 
-    let text_box = scrollable(text(error).width(Length::Fill)).height(400);
+    let text_box = scrollable(text(error.message()).width(Length::Fill)).height(400);
+    let exit_code = text(match error.exit_code() {
+        Some(code) => format!("Exit code: {code}"),
+        None => "The command didn't complete".to_string(),
+    })
+    .style(style::Text::Commentary);
+    // see `List::abort_actions`
+    let aborted = error.is_fatal().then(|| {
+        text(
+            "The remaining actions were cancelled. Those already sent to the device finish anyway: \
+            refresh to see their outcome.",
+        )
+        .style(style::Text::Warning)
+    });
+
+    let ctn = container(
+        column![title_ctn, text_box, exit_code]
+            .push_maybe(aborted)
+            .push(modal_btn_row),
+    )
+    .height(Length::Shrink)
+    .max_height(700)
+    .padding(10)
+    .style(style::Container::Frame);
+
+    Modal::new(content, ctn).on_blur(Message::ModalHide)
+}
+
+/// The action history of the device, see [`history`]
+fn history_view<'a>(
+    entries: &'a [history::Entry],
+    content: Column<'a, Message, Theme, Renderer>,
+) -> Modal<'a, Message, Theme, Renderer> {
+    let title_ctn = container(row![text("Action history").size(24)].align_items(Alignment::Center))
+        .width(Length::Fill)
+        .style(style::Container::Frame)
+        .padding([10, 0, 10, 0])
+        .center_y()
+        .center_x();
+
+    let list: Element<_, _, _> = if entries.is_empty() {
+        text("No action recorded for this device")
+            .style(style::Text::Commentary)
+            .into()
+    } else {
+        scrollable(
+            entries
+                .iter()
+                .fold(column![].spacing(4).padding([0, 15, 0, 0]), |col, e| {
+                    let outcome = match &e.error {
+                        None => text("done"),
+                        Some(err) => text(format!("failed: {err}")).style(style::Text::Danger),
+                    };
+                    col.push(
+                        row![
+                            text(&e.timestamp).style(style::Text::Commentary),
+                            text(format!("user {}", e.user)).style(style::Text::Commentary),
+                            text(&e.package).width(Length::Fill),
+                            text(format!("{} → {}", e.from, e.to)),
+                            outcome,
+                        ]
+                        .spacing(10),
+                    )
+                }),
+        )
+        .height(400)
+        .into()
+    };
+
+    let modal_btn_row = row![
+        button("Clear history")
+            .padding([5, 10])
+            .style(style::Button::UninstallPackage)
+            .on_press_maybe((!entries.is_empty()).then_some(Message::ClearHistory)),
+        horizontal_space(),
+        button_primary("Close").on_press(Message::ModalHide),
+    ]
+    .padding([10, 0, 0, 0]);
 
-    let ctn = container(column![title_ctn, text_box, modal_btn_row])
+    let ctn = container(column![title_ctn, list, modal_btn_row].spacing(10))
         .height(Length::Shrink)
-        .max_height(700)
+        .width(900)
         .padding(10)
         .style(style::Container::Frame);
 
     Modal::new(content, ctn).on_blur(Message::ModalHide)
 }
 
+fn confirm_action_view<'a>(
+    package: &'a PackageRow,
+    row_action: RowAction,
+    content: Column<'a, Message, Theme, Renderer>,
+) -> Modal<'a, Message, Theme, Renderer> {
+    let (action, button_style) = package.action(row_action.disable_mode);
+    let body = text(format!(
+        "{action} {}{}?",
+        package.name,
+        if row_action.all_users {
+            " for every user"
+        } else {
+            ""
+        }
+    ));
+    confirm_modal(action, body, Message::ActionConfirmed, Message::ModalHide)
+        .confirm_label(action)
+        .confirm_style(button_style)
+        .over(content)
+}
+
+/// Read-only rendering of a package description, with clickable URLs
+fn description_view(description: &str) -> Column<'_, Message, Theme, Renderer> {
+    description
+        .lines()
+        .fold(column![].width(Length::Fill), |col, line| {
+            match linkify(line).as_slice() {
+                [] => col.push(text(" ")),
+                [Segment::Text(t)] => col.push(text(*t)),
+                segments => col.push(segments.iter().fold(
+                    row![].align_items(Alignment::Center),
+                    |row, segment| {
+                        match *segment {
+                            Segment::Text(t) => row.push(text(t)),
+                            Segment::Link(url) => row.push(
+                                button(text(url))
+                                    .padding(0)
+                                    .style(style::Button::Link)
+                                    .on_press(Message::GoToUrl(PathBuf::from(url))),
+                            ),
+                        }
+                    },
+                )),
+            }
+        })
+}
+
+pub const GETTING_STARTED_URL: &str = "https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/wiki/Getting-started";
+
+pub const FAKE_ADB: &str = "The detected adb may not be the real Android Platform Tools.\nIt doesn't report its version as expected: install the Platform Tools, and make sure they come first in your PATH.";
+
+/// Why no usable device was found, and the relevant setup guide.
+///
+/// `None` if there's nothing more specific to say than "no devices".
+fn detection_help(
+    statuses: &[(String, DeviceStatus)],
+) -> Option<(&'static str, Option<&'static str>)> {
+    const WINDOWS_DRIVER: &str = "https://developer.android.com/studio/run/win-usb";
+    const LINUX_UDEV: &str = "https://developer.android.com/studio/run/device#setting-up";
+
+    let has = |wanted: &DeviceStatus| statuses.iter().any(|(_, status)| status == wanted);
+    if has(&DeviceStatus::NoPermissions) {
+        return Some(if cfg!(target_os = "windows") {
+            (
+                "ADB has no permission to use the device.\nThis is most likely a USB driver issue: install the driver of your device (or Google's USB driver), then reconnect it.",
+                Some(WINDOWS_DRIVER),
+            )
+        } else {
+            (
+                "ADB has no permission to use the device.\nYour user is likely missing a udev rule for it.",
+                Some(LINUX_UDEV),
+            )
+        });
+    }
+    if has(&DeviceStatus::Unauthorized) {
+        return Some((
+            "The device didn't authorize this computer.\nUnlock it and accept the \"Allow USB debugging\" prompt, then refresh.",
+            None,
+        ));
+    }
+    if has(&DeviceStatus::Offline) {
+        return Some((
+            "The device is offline.\nReconnect it (or toggle USB debugging), then refresh.",
+            None,
+        ));
+    }
+    // Windows doesn't list devices without a driver at all
+    (statuses.is_empty() && cfg!(target_os = "windows")).then_some((
+        "No device found.\nOn Windows, this is usually a missing USB driver: install the driver of your device (or Google's USB driver), then reconnect it.",
+        Some(WINDOWS_DRIVER),
+    ))
+}
+
+/// Frames of the [`waiting_view`] spinner
+const SPINNER: [&str; 8] = [
+    "[=   ]", "[==  ]", "[ == ]", "[  ==]", "[   =]", "[  ==]", "[ == ]", "[==  ]",
+];
+
+/// `frame` animates a spinner, for states that resolve by themselves
 fn waiting_view<'a>(
     displayed_text: &(impl ToString + ?Sized),
     btn: Option<button::Button<'a, Message, Theme, Renderer>>,
     text_style: style::Text,
+    frame: Option<usize>,
 ) -> Element<'a, Message, Theme, Renderer> {
     let col = column![]
         .spacing(10)
         .align_items(Alignment::Center)
         .push(text(displayed_text.to_string()).style(text_style).size(20));
 
+    let col = match frame {
+        Some(frame) => col.push(
+            text(SPINNER[frame % SPINNER.len()])
+                .font(iced::Font::MONOSPACE)
+                .style(style::Text::Commentary),
+        ),
+        None => col,
+    };
+
     let col = match btn {
         Some(btn) => col.push(btn.style(style::Button::Primary).padding([5, 10])),
         None => col,
@@ -1012,58 +2788,381 @@ fn waiting_view<'a>(
         .into()
 }
 
+/// The ADB actions to run for a package of a user, identified by `(i_user, index)`
+type PkgActions = ((usize, usize), Removal, Vec<String>);
+
 fn build_action_pkg_commands(
     packages: &[Vec<PackageRow>],
     device: &Phone,
     settings: &DeviceSettings,
     selection: (usize, usize),
+    unscoped: bool,
+    batch: u64,
+    pending_actions: &mut HashMap<(usize, usize), PackageState>,
+) -> Vec<Command<Message>> {
+    plan_action_pkg(
+        packages,
+        device,
+        settings,
+        selection,
+        unscoped,
+        pending_actions,
+    )
+    .into_iter()
+    .flat_map(|(key, removal, actions)| perform_pkg_actions(device, batch, key, removal, actions))
+    .collect()
+}
+
+/// Like [`build_action_pkg_commands`] for several packages,
+/// with a single `adb shell` call per user (and [`BATCH_SIZE`] packages)
+/// instead of one per command.
+fn build_batched_pkg_commands(
+    device: &Phone,
+    batch: u64,
+    planned: Vec<PkgActions>,
 ) -> Vec<Command<Message>> {
+    let mut per_user: BTreeMap<usize, Vec<PkgActions>> = BTreeMap::new();
+    for p in planned {
+        per_user.entry(p.0.0).or_default().push(p);
+    }
+    per_user
+        .into_values()
+        .flat_map(|user_actions| {
+            user_actions
+                .chunks(BATCH_SIZE)
+                .map(|chunk| {
+                    let keys: Vec<(usize, usize)> = chunk.iter().map(|(key, ..)| *key).collect();
+                    let pkg_actions = chunk
+                        .iter()
+                        .map(|(key, removal, actions)| {
+                            let p_info = PackageInfo {
+                                i_user: key.0,
+                                index: key.1,
+                                removal: removal.to_string(),
+                            };
+                            (p_info, actions.clone())
+                        })
+                        .collect();
+                    Command::perform(
+                        adb_shell_batch(device.adb_id.clone(), pkg_actions),
+                        move |results| {
+                            Message::ChangePackageStates(
+                                batch,
+                                keys.into_iter().zip(results).collect(),
+                            )
+                        },
+                    )
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Users whose selection is kept separately, see [`List::persist_selection`]
+fn selection_users(device: &Phone) -> Vec<User> {
+    if device.user_list.is_empty() {
+        vec![User::default()]
+    } else {
+        device.user_list.clone()
+    }
+}
+
+/// Decide what to run for the package at `selection`, for every concerned user.
+/// Those are marked as pending in `pending_actions`.
+fn plan_action_pkg(
+    packages: &[Vec<PackageRow>],
+    device: &Phone,
+    settings: &DeviceSettings,
+    selection: (usize, usize),
+    unscoped: bool,
+    pending_actions: &mut HashMap<(usize, usize), PackageState>,
+) -> Vec<PkgActions> {
     let pkg = &packages[selection.0][selection.1];
     let wanted_state = pkg.state.opposite(settings.disable_mode);
 
-    let mut commands = vec![];
-    for u in device.user_list.iter().filter(|&&u| {
-        !u.protected
-            && packages
-                .get(u.index)
-                .and_then(|user_pkgs| user_pkgs.get(selection.1))
-                .is_some_and(|pkg| pkg.selected || settings.multi_user_mode)
-    }) {
-        let u_pkg = &packages[u.index][selection.1];
+    // Without `--user`, a single command covers whatever the device decides,
+    // so only the displayed user is acted upon.
+    let users: Vec<User> = if unscoped {
+        device
+            .user_list
+            .iter()
+            .filter(|u| u.index == selection.0)
+            .cloned()
+            .collect()
+    } else {
+        device.user_list.clone()
+    };
+
+    let mut planned = vec![];
+    for u in users.iter().filter(|u| !u.protected) {
+        let Some((i, u_pkg)) = user_package(packages, u.index, &pkg.name)
+            .filter(|(_, u_pkg)| u_pkg.selected || settings.multi_user_mode)
+        else {
+            continue;
+        };
         let wanted_state = if settings.multi_user_mode {
             wanted_state
         } else {
             u_pkg.state.opposite(settings.disable_mode)
         };
 
-        let actions = apply_pkg_state_commands(&u_pkg.into(), wanted_state, *u, device);
-        for (j, action) in actions.into_iter().enumerate() {
-            let p_info = PackageInfo {
-                i_user: u.index,
-                index: selection.1,
-                removal: pkg.removal.to_string(),
+        let key = (u.index, i);
+        if pending_actions.contains_key(&key) {
+            continue;
+        }
+
+        let actions = apply_pkg_state_commands(
+            &u_pkg.into(),
+            wanted_state,
+            (!unscoped).then(|| u.clone()),
+            device,
+            settings.keep_data,
+        );
+        if !actions.is_empty() {
+            pending_actions.insert(key, wanted_state);
+            planned.push((key, pkg.removal, actions));
+        }
+    }
+    planned
+}
+
+/// The inverse of `action`: what brings its packages back to their previous state.
+/// Packages whose state changed since are left alone.
+fn plan_undo(
+    packages: &[Vec<PackageRow>],
+    device: &Phone,
+    settings: &DeviceSettings,
+    action: &AppliedAction,
+    pending_actions: &mut HashMap<(usize, usize), PackageState>,
+) -> Vec<PkgActions> {
+    let mut planned = vec![];
+    for &(key, previous) in &action.packages {
+        let Some(pkg) = packages.get(key.0).and_then(|p| p.get(key.1)) else {
+            continue;
+        };
+        if pkg.state == previous || pending_actions.contains_key(&key) {
+            continue;
+        }
+        let user = device
+            .user_list
+            .iter()
+            .find(|u| u.index == key.0)
+            .filter(|_| !action.unscoped)
+            .cloned();
+        let actions =
+            apply_pkg_state_commands(&pkg.into(), previous, user, device, settings.keep_data);
+        if !actions.is_empty() {
+            pending_actions.insert(key, previous);
+            planned.push((key, pkg.removal, actions));
+        }
+    }
+    planned
+}
+
+/// Packages the rest of the system relies on
+const SYSTEM_CORE_PACKAGES: [&str; 12] = [
+    "android",
+    "com.android.systemui",
+    "com.android.settings",
+    "com.android.phone",
+    "com.android.providers",
+    "com.android.server",
+    "com.android.packageinstaller",
+    "com.android.permissioncontroller",
+    "com.google.android.gms",
+    "com.google.android.gsf",
+    "com.google.android.packageinstaller",
+    "com.google.android.permissioncontroller",
+];
+
+/// Heuristic: an AOSP or Google core component (or one of its sub-packages),
+/// which can break the device in subtle ways even when not `Unsafe`.
+fn is_system_core(package: &PackageRow) -> bool {
+    matches!(package.uad_list, UadList::Aosp | UadList::Google)
+        && SYSTEM_CORE_PACKAGES.iter().any(|core| {
+            package
+                .name
+                .strip_prefix(core)
+                .is_some_and(|rest| rest.is_empty() || rest.starts_with('.'))
+        })
+}
+
+/// Compact per-user state of a package, like `0:E 10:D`
+/// (Enabled, Disabled, Uninstalled). Users without the package are omitted.
+fn users_state_label(states: &[(usize, PackageState)], users: &[User]) -> String {
+    states
+        .iter()
+        .filter_map(|&(i_user, state)| {
+            let user = users.iter().find(|u| u.index == i_user)?;
+            let letter = match state {
+                PackageState::Enabled => 'E',
+                PackageState::Disabled => 'D',
+                PackageState::Uninstalled => 'U',
+                PackageState::All => '?',
             };
-            // In the end there is only one package state change
-            // even if we run multiple adb commands
-            commands.push(Command::perform(
-                adb_shell_command(
-                    // this is typically small,
-                    // so it's fine.
-                    device.adb_id.clone(),
-                    action,
-                    p_info,
-                ),
-                if j == 0 {
-                    Message::ChangePackageState
-                } else {
-                    |_| Message::Nothing
-                },
-            ));
+            Some(format!("{}:{letter}", user.id))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// So the focused row can be scrolled into view
+static PACKAGES_SCROLLABLE: LazyLock<scrollable::Id> = LazyLock::new(scrollable::Id::unique);
+
+/// Packages shown by the filters, out of the user's `total`, and how many are selected
+fn counts_summary(shown: usize, total: usize, selected: usize) -> String {
+    format!("Showing {shown} of {total} • {selected} selected")
+}
+
+/// Estimated bytes freed by uninstalling the `selection` of `packages`.
+///
+/// A package selected for several users is only counted once,
+/// as `sizes` are per package, not per user.
+fn freed_estimate(
+    packages: &[Vec<PackageRow>],
+    selection: &[(usize, usize)],
+    sizes: &HashMap<String, u64>,
+) -> u64 {
+    let names: BTreeSet<&str> = selection
+        .iter()
+        .filter_map(|&(u, i)| packages.get(u)?.get(i))
+        .filter(|p| !matches!(p.state, PackageState::Uninstalled | PackageState::Disabled))
+        .map(|p| p.name.as_str())
+        .collect();
+    names
+        .into_iter()
+        .map(|name| sizes.get(name).copied().unwrap_or(0))
+        .sum()
+}
+
+/// Tab-separated `name, state, removal` of `packages`, with a header
+fn packages_tsv<'a>(packages: impl Iterator<Item = &'a PackageRow>) -> String {
+    std::iter::once("Package\tState\tRemoval".to_string())
+        .chain(packages.map(|p| format!("{}\t{}\t{}", p.name, p.state, p.removal)))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// For a package uninstalled for the current user, whether it's gone for every user:
+/// `(uninstalled on all users)` or `(still on user 10)`.
+fn uninstalled_scope(
+    package: &PackageRow,
+    states: &[(usize, PackageState)],
+    users: &[User],
+) -> Option<String> {
+    if package.state != PackageState::Uninstalled {
+        return None;
+    }
+    let still_on: Vec<String> = states
+        .iter()
+        .filter(|(_, state)| *state != PackageState::Uninstalled)
+        .filter_map(|&(i_user, _)| users.iter().find(|u| u.index == i_user && !u.protected))
+        .map(|u| u.id.to_string())
+        .collect();
+    Some(match still_on.len() {
+        0 => "(uninstalled on all users)".to_string(),
+        1 => format!("(still on user {})", still_on[0]),
+        _ => format!("(still on users {})", still_on.join(", ")),
+    })
+}
+
+/// Find the package called `name` among the packages of user `i_user`.
+///
+/// Users don't have the same packages, so a package index
+/// for a user is meaningless for the others.
+fn user_package<'a>(
+    packages: &'a [Vec<PackageRow>],
+    i_user: usize,
+    name: &str,
+) -> Option<(usize, &'a PackageRow)> {
+    packages
+        .get(i_user)?
+        .iter()
+        .enumerate()
+        .find(|(_, p)| p.name == name)
+}
+
+/// Act on a package for every non-protected user that has it,
+/// regardless of [`DeviceSettings::multi_user_mode`].
+/// The wanted state is the opposite of the package's state for the displayed user.
+fn build_all_users_pkg_commands(
+    packages: &[Vec<PackageRow>],
+    device: &Phone,
+    settings: &DeviceSettings,
+    selection: (usize, usize),
+    batch: u64,
+    pending_actions: &mut HashMap<(usize, usize), PackageState>,
+) -> Vec<Command<Message>> {
+    let pkg = &packages[selection.0][selection.1];
+    let wanted_state = pkg.state.opposite(settings.disable_mode);
+
+    let mut commands = vec![];
+    for u in device.user_list.iter().filter(|u| !u.protected) {
+        let Some((i, u_pkg)) = user_package(packages, u.index, &pkg.name) else {
+            continue;
+        };
+        let key = (u.index, i);
+        if pending_actions.contains_key(&key) {
+            continue;
+        }
+
+        let actions = apply_pkg_state_commands(
+            &u_pkg.into(),
+            wanted_state,
+            Some(u.clone()),
+            device,
+            settings.keep_data,
+        );
+        if !actions.is_empty() {
+            pending_actions.insert(key, wanted_state);
         }
+        commands.extend(perform_pkg_actions(
+            device,
+            batch,
+            key,
+            pkg.removal,
+            actions,
+        ));
     }
     commands
 }
 
+/// Run the `actions` of a single package state change, as part of `batch`.
+/// `key` is the `(user_index, pkg_index)` of the package.
+fn perform_pkg_actions(
+    device: &Phone,
+    batch: u64,
+    key: (usize, usize),
+    removal: Removal,
+    actions: Vec<String>,
+) -> Vec<Command<Message>> {
+    actions
+        .into_iter()
+        .enumerate()
+        .map(|(j, action)| {
+            let p_info = PackageInfo {
+                i_user: key.0,
+                index: key.1,
+                removal: removal.to_string(),
+            };
+            let cmd = adb_shell_command(
+                // this is typically small,
+                // so it's fine.
+                device.adb_id.clone(),
+                action,
+                p_info,
+            );
+            // In the end there is only one package state change
+            // even if we run multiple adb commands
+            if j == 0 {
+                Command::perform(cmd, move |res| Message::ChangePackageState(batch, key, res))
+            } else {
+                Command::perform(cmd, |_| Message::Nothing)
+            }
+        })
+        .collect()
+}
+
 fn recap<'a>(settings: &Settings, recap: &SummaryEntry) -> Element<'a, Message, Theme, Renderer> {
     container(
         row![
@@ -1102,3 +3201,430 @@ fn recap<'a>(settings: &Settings, recap: &SummaryEntry) -> Element<'a, Message,
     .style(style::Container::Frame)
     .into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::adb::Transport;
+
+    fn row(name: &str, selected: bool) -> PackageRow {
+        PackageRow::new(
+            name,
+            PackageState::Enabled,
+            "",
+            UadList::Oem,
+            Removal::Recommended,
+            selected,
+            false,
+        )
+    }
+
+    #[test]
+    fn system_core_packages() {
+        let pkg = |name: &str, uad_list| PackageRow {
+            uad_list,
+            ..row(name, false)
+        };
+        assert!(is_system_core(&pkg("android", UadList::Aosp)));
+        assert!(is_system_core(&pkg(
+            "com.android.providers.contacts",
+            UadList::Aosp
+        )));
+        assert!(is_system_core(&pkg(
+            "com.google.android.gms",
+            UadList::Google
+        )));
+        assert!(!is_system_core(&pkg(
+            "com.google.android.gmsx",
+            UadList::Google
+        )));
+        assert!(!is_system_core(&pkg("com.android.settings", UadList::Oem)));
+        assert!(!is_system_core(&pkg("com.android.chrome", UadList::Google)));
+    }
+
+    #[test]
+    fn search_scopes() {
+        let pkg = PackageRow {
+            description: "Google Photos backup".to_string(),
+            ..row("com.google.android.apps.photos", false)
+        };
+        assert!(SearchScope::Name.matches(&pkg, "apps.photos"));
+        assert!(!SearchScope::Name.matches(&pkg, "backup"));
+        assert!(SearchScope::Description.matches(&pkg, "backup"));
+        assert!(!SearchScope::Description.matches(&pkg, "apps.photos"));
+        assert!(SearchScope::Both.matches(&pkg, "backup"));
+        assert!(SearchScope::Both.matches(&pkg, "apps.photos"));
+        assert!(!SearchScope::Both.matches(&pkg, "camera"));
+    }
+
+    #[test]
+    fn counts_line() {
+        assert_eq!(counts_summary(48, 612, 5), "Showing 48 of 612 • 5 selected");
+        assert_eq!(counts_summary(0, 0, 0), "Showing 0 of 0 • 0 selected");
+    }
+
+    #[test]
+    fn freed_counts_packages_once() {
+        let mut disabled = row("c", true);
+        disabled.state = PackageState::Disabled;
+        let packages = vec![
+            vec![row("a", true), row("b", true), disabled],
+            vec![row("b", true), row("a", true)],
+        ];
+        let sizes = HashMap::from([
+            ("a".to_string(), 100),
+            ("b".to_string(), 20),
+            ("c".to_string(), 3),
+        ]);
+        let selection = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1)];
+        assert_eq!(freed_estimate(&packages, &selection, &sizes), 120);
+        assert_eq!(freed_estimate(&packages, &[(1, 0)], &sizes), 20);
+        assert_eq!(freed_estimate(&packages, &[(0, 2), (5, 0)], &sizes), 0);
+    }
+
+    #[test]
+    fn tsv_table() {
+        let mut disabled = row("b", false);
+        disabled.state = PackageState::Disabled;
+        assert_eq!(
+            packages_tsv([row("a", true), disabled].iter()),
+            "Package\tState\tRemoval\na\tEnabled\tRecommended\nb\tDisabled\tRecommended"
+        );
+        assert_eq!(packages_tsv(std::iter::empty()), "Package\tState\tRemoval");
+    }
+
+    #[test]
+    fn plan_matches_packages_by_name_across_users() {
+        let device = Phone {
+            model: "test".to_string(),
+            android_sdk: 34,
+            user_list: vec![
+                User {
+                    id: 0,
+                    index: 0,
+                    protected: false,
+                    ..User::default()
+                },
+                User {
+                    id: 10,
+                    index: 1,
+                    protected: false,
+                    ..User::default()
+                },
+            ],
+            adb_id: "serial".to_string(),
+            transport: Transport::Usb,
+            backup: None,
+            favorite: false,
+        };
+        // user 10 has fewer packages, in another order
+        let packages = vec![
+            vec![row("a", true), row("b", false), row("c", false)],
+            vec![row("c", false), row("a", false)],
+        ];
+        let settings = DeviceSettings {
+            multi_user_mode: true,
+            ..DeviceSettings::default()
+        };
+
+        let planned = plan_action_pkg(
+            &packages,
+            &device,
+            &settings,
+            (0, 0),
+            false,
+            &mut HashMap::new(),
+        );
+        let keys: Vec<_> = planned.iter().map(|(key, ..)| *key).collect();
+        assert_eq!(keys, vec![(0, 0), (1, 1)]);
+        assert_eq!(planned[0].2, vec!["pm uninstall --user 0 a".to_string()]);
+        assert_eq!(planned[1].2, vec!["pm uninstall --user 10 a".to_string()]);
+
+        let users_state = |name: &str| {
+            let states: Vec<_> = (0..packages.len())
+                .filter_map(|i| Some((i, user_package(&packages, i, name)?.1.state)))
+                .collect();
+            users_state_label(&states, &device.user_list)
+        };
+        assert_eq!(users_state("a"), "0:E 10:E");
+        assert_eq!(users_state("b"), "0:E");
+
+        let states = [(0, PackageState::Uninstalled), (1, PackageState::Disabled)];
+        let mut uninstalled = row("c", false);
+        uninstalled.state = PackageState::Uninstalled;
+        assert_eq!(
+            uninstalled_scope(&uninstalled, &states, &device.user_list).as_deref(),
+            Some("(still on user 10)")
+        );
+        assert_eq!(
+            uninstalled_scope(&uninstalled, &states[..1], &device.user_list).as_deref(),
+            Some("(uninstalled on all users)")
+        );
+        assert_eq!(
+            uninstalled_scope(&row("c", false), &states, &device.user_list),
+            None
+        );
+
+        // `b` at index 1 has no counterpart for user 10
+        let planned_b = plan_action_pkg(
+            &packages,
+            &device,
+            &settings,
+            (0, 1),
+            false,
+            &mut HashMap::new(),
+        );
+        let keys_b: Vec<_> = planned_b.iter().map(|(key, ..)| *key).collect();
+        assert_eq!(keys_b, vec![(0, 1)]);
+    }
+
+    #[test]
+    fn undo_restores_previous_states() {
+        let device = Phone {
+            model: "test".to_string(),
+            android_sdk: 34,
+            user_list: vec![User {
+                id: 0,
+                index: 0,
+                protected: false,
+                ..User::default()
+            }],
+            adb_id: "serial".to_string(),
+            transport: Transport::Usb,
+            backup: None,
+            favorite: false,
+        };
+        let mut packages = vec![vec![row("a", false), row("b", false), row("c", false)]];
+        packages[0][0].state = PackageState::Uninstalled;
+        packages[0][1].state = PackageState::Disabled;
+        let action = AppliedAction {
+            packages: vec![
+                ((0, 0), PackageState::Enabled),
+                ((0, 1), PackageState::Enabled),
+                // restored by hand since
+                ((0, 2), PackageState::Enabled),
+            ],
+            unscoped: false,
+        };
+
+        let mut pending = HashMap::new();
+        let settings = DeviceSettings::default();
+        let planned = plan_undo(&packages, &device, &settings, &action, &mut pending);
+        let keys: Vec<_> = planned.iter().map(|(key, ..)| *key).collect();
+        assert_eq!(keys, vec![(0, 0), (0, 1)]);
+        assert_eq!(planned[1].2, vec!["pm enable --user 0 b".to_string()]);
+        assert_eq!(pending.get(&(0, 0)), Some(&PackageState::Enabled));
+        assert_eq!(pending.get(&(0, 1)), Some(&PackageState::Enabled));
+        // already running
+        assert!(plan_undo(&packages, &device, &settings, &action, &mut pending).is_empty());
+    }
+
+    #[test]
+    fn inverted_row_action() {
+        let device = Phone {
+            android_sdk: 34,
+            user_list: vec![User {
+                id: 0,
+                index: 0,
+                protected: false,
+                ..User::default()
+            }],
+            ..Phone::default()
+        };
+        let mut settings = Settings::default();
+        settings.device.disable_mode = true;
+        let mut list = List {
+            phone_packages: vec![vec![row("a", false)]],
+            filtered_packages: vec![0],
+            selected_list: Some(UadList::All),
+            selected_package_state: Some(PackageState::All),
+            selected_removal: Some(Removal::All),
+            selected_user: device.user_list.first().cloned(),
+            ..List::default()
+        };
+
+        // Alt-pressed: uninstall, although the device setting is to disable
+        let action = RowAction {
+            key: (0, 0),
+            all_users: false,
+            disable_mode: false,
+        };
+        let _ = list.row_action(&settings, &device, action);
+        assert_eq!(
+            list.pending_actions.get(&(0, 0)),
+            Some(&PackageState::Uninstalled)
+        );
+
+        let info = PackageInfo {
+            i_user: 0,
+            index: 0,
+            removal: Removal::Recommended.to_string(),
+        };
+        let uninstalled = list.change_package_state(&settings, &device, (0, 0), Ok(info));
+        assert_eq!(uninstalled.as_deref(), Some("a"));
+        assert_eq!(list.phone_packages[0][0].state, PackageState::Uninstalled);
+        assert!(list.pending_actions.is_empty());
+    }
+
+    #[test]
+    fn done_package_leaves_the_rest_selected() {
+        let settings = Settings::default();
+        let device = Phone::default();
+        let mut list = List {
+            phone_packages: vec![
+                vec![row("a", true), row("b", true), row("c", true)],
+                vec![row("a", true)],
+            ],
+            selected_packages: vec![(0, 0), (0, 1), (0, 2), (1, 0)],
+            selected_list: Some(UadList::All),
+            selected_package_state: Some(PackageState::All),
+            selected_removal: Some(Removal::All),
+            selected_user: Some(User::default()),
+            ..List::default()
+        };
+        let info = PackageInfo {
+            i_user: 0,
+            index: 1,
+            removal: Removal::Recommended.to_string(),
+        };
+        let _ = list.change_package_state(&settings, &device, (0, 1), Ok(info));
+        assert_eq!(list.selected_packages, vec![(0, 0), (0, 2), (1, 0)]);
+        assert_eq!(list.selection_names(0), ["a", "c"]);
+        assert_eq!(list.selection_names(1), ["a"]);
+    }
+
+    #[test]
+    fn aborted_batch_results_are_ignored() {
+        let mut settings = Settings::default();
+        let mut device = Phone::default();
+        let mut list = List {
+            phone_packages: vec![vec![row("a", true)]],
+            ..List::default()
+        };
+        let stale = list.batch;
+        list.abort_actions();
+        let info = PackageInfo {
+            i_user: 0,
+            index: 0,
+            removal: Removal::Recommended.to_string(),
+        };
+        let _ = list.update(
+            &mut settings,
+            &mut device,
+            &mut UadListState::Done,
+            Message::ChangePackageState(stale, (0, 0), Ok(info)),
+        );
+        assert_eq!(list.phone_packages[0][0].state, PackageState::Enabled);
+        assert!(list.phone_packages[0][0].selected);
+    }
+
+    #[test]
+    fn ready_without_packages() {
+        let mut list = List::default();
+        let mut settings = Settings::default();
+        let mut device = Phone::default();
+        for packages in [vec![], vec![vec![]]] {
+            let _ = list.update(
+                &mut settings,
+                &mut device,
+                &mut UadListState::Done,
+                Message::ApplyFilters(packages),
+            );
+            assert!(matches!(list.loading_state, LoadingState::Ready));
+            assert!(list.filtered_packages.is_empty());
+            let _ = list.view(&settings, &device);
+        }
+        // a user without a package list, e.g. created meanwhile
+        let user = User {
+            index: 1,
+            ..User::default()
+        };
+        device.user_list = vec![User::default(), user.clone()];
+        list.phone_packages = vec![vec![row("a", false)]];
+        list.selected_user = Some(user);
+        let _ = list.view(&settings, &device);
+    }
+
+    #[test]
+    fn multi_user_toggle_matches_by_name() {
+        let mut settings = Settings::default();
+        settings.device.multi_user_mode = true;
+        let mut device = Phone {
+            user_list: vec![
+                User::default(),
+                User {
+                    id: 10,
+                    index: 1,
+                    ..User::default()
+                },
+            ],
+            ..Phone::default()
+        };
+        let mut list = List {
+            // user 10 has its packages in another order
+            phone_packages: vec![
+                vec![row("a", false), row("b", false)],
+                vec![row("b", true), row("a", false)],
+            ],
+            selected_packages: vec![(1, 0)],
+            selected_user: device.user_list.first().cloned(),
+            ..List::default()
+        };
+        let mut toggle = |view: &mut List, selected| {
+            let _ = view.update(
+                &mut settings,
+                &mut device,
+                &mut UadListState::Done,
+                Message::List(0, RowMessage::ToggleSelection(selected)),
+            );
+        };
+
+        toggle(&mut list, true);
+        assert!(list.phone_packages[0][0].selected);
+        assert!(list.phone_packages[1][1].selected);
+        list.selected_packages.sort_unstable();
+        assert_eq!(list.selected_packages, vec![(0, 0), (1, 0), (1, 1)]);
+
+        // `b` of user 10 stays selected
+        toggle(&mut list, false);
+        assert!(!list.phone_packages[0][0].selected);
+        assert!(!list.phone_packages[1][1].selected);
+        assert!(list.phone_packages[1][0].selected);
+        assert_eq!(list.selected_packages, vec![(1, 0)]);
+    }
+
+    #[test]
+    fn select_all_skips_unsafe() {
+        let mut settings = Settings::default();
+        settings.general.expert_mode = false;
+        settings.device.multi_user_mode = false;
+        let unsafe_row = |name| PackageRow {
+            removal: Removal::Unsafe,
+            ..row(name, false)
+        };
+        let mut list = List {
+            phone_packages: vec![vec![row("a", false), unsafe_row("b")]],
+            filtered_packages: vec![0, 1],
+            ..List::default()
+        };
+        let mut select_all = |view: &mut List| {
+            let _ = view.update(
+                &mut settings,
+                &mut Phone::default(),
+                &mut UadListState::Done,
+                Message::ToggleAllSelected(true),
+            );
+        };
+
+        select_all(&mut list);
+        assert!(list.phone_packages[0][0].selected);
+        assert!(!list.phone_packages[0][1].selected);
+        assert!(list.all_selected);
+
+        // nothing selectable is filtered
+        list.filtered_packages = vec![1];
+        select_all(&mut list);
+        assert!(!list.phone_packages[0][1].selected);
+        assert!(!list.all_selected);
+    }
+}
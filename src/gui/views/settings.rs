@@ -1,29 +1,63 @@
 use crate::core::{
-    config::{BackupSettings, Config, DeviceSettings, GeneralSettings},
+    adb,
+    config::{BackupSettings, Config, DeviceSettings, GeneralSettings, ViewPrefs},
     helpers::button_primary,
-    save::{backup_phone, list_available_backup_user, list_available_backups, restore_backup},
-    sync::{AdbError, Phone, User, adb_shell_command, get_android_sdk, supports_multi_user},
-    theme::Theme,
+    hooks::Hook,
+    save::{
+        backup_phone, delete_backup, list_available_backup_user, list_available_backups_in,
+        rename_backup, restore_backup,
+    },
+    snapshot::{self, Change, compare, snapshot_state},
+    sync::{
+        AdbError, Phone, User, adb_install_apk, adb_shell_command, create_user, get_android_sdk,
+        remove_user, set_dry_run, supports_multi_user,
+    },
+    theme::{Theme, set_color_overrides},
     utils::{
-        DisplayablePath, Error, NAME, export_packages, generate_backup_name, open_folder, open_url,
-        string_to_theme,
+        DisplayablePath, Error, NAME, check_writable, export_inventory_csv, export_packages,
+        export_uncategorized_report, format_diff_time_from_now, format_eta, open_backup_file,
+        open_folder, open_script_file, open_url, string_to_theme,
     },
 };
 use crate::gui::{
     style,
     views::list::{List as AppsView, PackageInfo},
+    widgets::confirm_modal::{ConfirmModal, confirm_modal},
     widgets::modal::Modal,
     widgets::navigation_menu::ICONS,
     widgets::package_row::PackageRow,
     widgets::text,
 };
-use iced::widget::{Space, button, checkbox, column, container, pick_list, radio, row, scrollable};
+use iced::futures::StreamExt;
+use iced::widget::{
+    Space, button, checkbox, column, container, pick_list, radio, row, scrollable, text_input,
+};
 use iced::{Alignment, Element, Length, Renderer, alignment};
 use std::path::PathBuf;
+use std::sync::LazyLock;
+use std::time::Instant;
+
+/// So the current theme can be scrolled into view, see [`Settings::scroll_to_theme`]
+static THEMES_SCROLLABLE: LazyLock<scrollable::Id> = LazyLock::new(scrollable::Id::unique);
 
 #[derive(Debug, Clone)]
 pub enum PopUpModal {
-    ExportUninstalled,
+    /// Path of the exported list
+    ExportUninstalled(PathBuf),
+    /// Path of the exported inventory
+    ExportInventory(PathBuf),
+    /// Path of the exported report of uncategorized packages
+    ExportReport(PathBuf),
+    /// Confirm deleting this backup
+    DeleteBackup(DisplayablePath),
+    /// Acknowledge the risks of expert mode, and whether to keep it for the next sessions
+    ExpertMode { keep: bool },
+    /// Confirm removing this user, with all its data
+    RemoveUser(User),
+    /// Packages changed since the snapshot
+    SnapshotChanges(Vec<Change>),
+    /// Confirm resetting the settings of the current device
+    ResetDevice,
 }
 
 #[derive(Debug, Clone)]
@@ -32,15 +66,70 @@ pub struct Settings {
     pub device: DeviceSettings,
     is_loading: bool,
     modal: Option<PopUpModal>,
+    /// `(processed, total, started)` packages of the backup being created, if any
+    backup_progress: Option<(usize, usize, Instant)>,
+    /// Why the last backup (or backup folder change) failed, if it did
+    backup_error: Option<String>,
+    /// New name being typed for the selected backup, while renaming it
+    backup_rename: Option<String>,
+    /// Why the last export (or export folder change) failed, if it did
+    export_error: Option<String>,
+    /// Name of the user to create
+    new_user_name: String,
+    /// Why the last user creation or removal failed, if it did
+    users_error: Option<String>,
+    /// Why the last backup opened offline couldn't be, if it did
+    offline_error: Option<String>,
+    /// When the snapshot of the device was taken, if there's one
+    snapshot_taken: Option<String>,
+    /// A snapshot is being taken or compared
+    snapshot_running: bool,
+    /// Why the last snapshot (or comparison) failed, if it did
+    snapshot_error: Option<String>,
+    /// ADB server being typed, see [`GeneralSettings::adb_server`]
+    adb_server_input: String,
+    /// Why `adb_server_input` isn't used, if it isn't
+    adb_server_error: Option<String>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
+        let mut general = Config::load_configuration_file().general;
+        let default_folder = GeneralSettings::default().backup_folder;
+        if !general.backup_folder.exists() && general.backup_folder != default_folder {
+            // e.g. an unplugged external drive.
+            // The config is left as-is, so it's used again once the drive is back.
+            warn!(
+                "Backup folder {} is missing, falling back to {}",
+                general.backup_folder.display(),
+                default_folder.display()
+            );
+            general.backup_folder = default_folder;
+        }
+        if general.expert_mode && !general.keep_expert_mode {
+            info!("Expert mode was enabled for the previous session only, disabling it");
+            general.expert_mode = false;
+        }
+        set_dry_run(general.dry_run);
+        adb::set_server(general.parsed_adb_server());
+        set_color_overrides(general.color_blind, &general.theme_colors);
         Self {
-            general: Config::load_configuration_file().general,
+            adb_server_input: general.adb_server.clone().unwrap_or_default(),
+            adb_server_error: None,
+            general,
             device: DeviceSettings::default(),
             is_loading: false,
             modal: None,
+            backup_progress: None,
+            backup_error: None,
+            backup_rename: None,
+            export_error: None,
+            new_user_name: String::new(),
+            users_error: None,
+            offline_error: None,
+            snapshot_taken: None,
+            snapshot_running: false,
+            snapshot_error: None,
         }
     }
 }
@@ -49,23 +138,143 @@ impl Default for Settings {
 pub enum Message {
     LoadDeviceSettings,
     ExpertMode(bool),
+    ExpertModeKeep(bool),
+    ExpertModeConfirmed,
+    DryRun(bool),
+    LiveStateSync(bool),
+    AutoSelectDevice(bool),
+    ConfirmEveryAction(bool),
+    PersistSelection(bool),
+    ColorBlind(bool),
+    DenseMode(bool),
     DisableMode(bool),
+    KeepData(bool),
     MultiUserMode(bool),
+    FavoriteDevice(bool),
     ApplyTheme(Theme),
     UrlPressed(PathBuf),
     BackupSelected(DisplayablePath),
+    /// Start (or cancel) renaming the selected backup
+    RenameBackup,
+    BackupNameInput(String),
+    BackupRenamed,
+    /// Ask to confirm deleting the selected backup
+    DeleteBackup,
+    BackupDeleted(DisplayablePath),
     BackupDevice,
+    BackupProgress(usize, usize),
+    /// The backup thread is done, whether it reported its result or not
+    BackupEnded,
     RestoreDevice,
     RestoringDevice(Result<PackageInfo, AdbError>),
+    /// Choose a backup to view as an offline device
+    OpenBackupOffline,
+    /// Opened by the main view, which owns the selected device
+    BackupFileChosen(Result<PathBuf, Error>),
+    OfflineBackupFailed(String),
+    TakeSnapshot,
+    /// Timestamp of the new snapshot
+    SnapshotTaken(Result<String, String>),
+    CompareSnapshot,
+    SnapshotCompared(Result<Vec<Change>, String>),
     DeviceBackedUp(Result<bool, String>),
     ChooseBackUpFolder,
     FolderChosen(Result<PathBuf, Error>),
+    AddSecondaryBackUpFolder,
+    SecondaryFolderChosen(Result<PathBuf, Error>),
+    RemoveSecondaryBackUpFolder(usize),
     ExportPackages,
-    PackagesExported(Result<bool, String>),
+    PackagesExported(Result<PathBuf, String>),
+    ChooseExportFolder,
+    ExportFolderChosen(Result<PathBuf, Error>),
+    ChooseHook(Hook),
+    HookChosen(Hook, Result<PathBuf, Error>),
+    ClearHook(Hook),
+    AdbServerInput(String),
+    ExportInventory,
+    InventoryExported(Result<PathBuf, String>),
+    UncategorizedReport(bool),
+    SelfUpdateEnabled(bool),
+    ExportReport,
+    ReportExported(Result<PathBuf, String>),
+    NewUserNameInput(String),
+    CreateUser,
+    /// Ask to confirm removing this user
+    RemoveUser(User),
+    RemoveUserConfirmed(User),
+    /// The users of the device changed, they must be listed again
+    UsersChanged(Result<String, String>),
+    /// Ask to confirm resetting the settings of the current device
+    ResetDeviceSettings,
+    /// Its settings were reset to the defaults, see [`Config::forget_device`]
+    DeviceSettingsReset,
     ModalHide,
 }
 
 impl Settings {
+    /// Scrolls the theme radios to the current theme, when they don't all fit
+    pub fn scroll_to_theme(&self) -> iced::Command<Message> {
+        let current = string_to_theme(&self.general.theme);
+        let pos = Theme::ALL.iter().position(|t| *t == current).unwrap_or(0);
+        #[expect(clippy::cast_precision_loss, reason = "a handful of themes")]
+        let x = pos as f32 / (Theme::ALL.len() - 1).max(1) as f32;
+        scrollable::snap_to(
+            THEMES_SCROLLABLE.clone(),
+            scrollable::RelativeOffset { x, y: 0.0 },
+        )
+    }
+
+    fn set_hook(&mut self, hook: Hook, script: Option<PathBuf>) {
+        match hook {
+            Hook::PreApply => self.general.pre_apply_hook = script,
+            Hook::PostApply => self.general.post_apply_hook = script,
+        }
+    }
+
+    /// A row per [`Hook`], to choose or clear its script
+    fn hook_rows(&self) -> Element<'_, Message, Theme, Renderer> {
+        let rows = Hook::ALL.iter().fold(column![].spacing(10), |col, &hook| {
+            let script = hook.script(&self.general);
+            col.push(
+                row![
+                    button(text("\u{E930}").font(ICONS))
+                        .padding([5, 10])
+                        .on_press(Message::ChooseHook(hook))
+                        .style(style::Button::Primary),
+                    text(format!("{hook}: ")),
+                    text(script.map_or("no script".into(), |s| s.to_string_lossy()))
+                        .style(style::Text::Commentary),
+                    Space::new(Length::Fill, Length::Shrink),
+                ]
+                .push_maybe(script.is_some().then(|| {
+                    button(text("Remove"))
+                        .padding([5, 10])
+                        .on_press(Message::ClearHook(hook))
+                        .style(style::Button::UninstallPackage)
+                }))
+                .spacing(10)
+                .align_items(Alignment::Center),
+            )
+        });
+        column![
+            rows,
+            text(
+                "Hooks run a script of yours with the device serial as argument, \
+                 before and after a reviewed selection is applied. \
+                 A failing \"before\" script cancels the actions.",
+            )
+            .style(style::Text::Commentary),
+            text("Scripts can do anything on your computer: only set ones you trust.")
+                .style(style::Text::Danger),
+        ]
+        .spacing(10)
+        .into()
+    }
+
+    pub const fn has_modal(&self) -> bool {
+        self.modal.is_some()
+    }
+
     #[allow(clippy::too_many_lines)]
     pub fn update(
         &mut self,
@@ -80,8 +289,79 @@ impl Settings {
                 self.modal = None;
                 iced::Command::none()
             }
-            Message::ExpertMode(toggled) => {
-                self.general.expert_mode = toggled;
+            Message::ExpertMode(true) => {
+                self.modal = Some(PopUpModal::ExpertMode {
+                    keep: self.general.keep_expert_mode,
+                });
+                iced::Command::none()
+            }
+            Message::ExpertMode(false) => {
+                self.general.expert_mode = false;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::ExpertModeKeep(keep) => {
+                if let Some(PopUpModal::ExpertMode { keep: k }) = &mut self.modal {
+                    *k = keep;
+                }
+                iced::Command::none()
+            }
+            Message::ExpertModeConfirmed => {
+                if let Some(PopUpModal::ExpertMode { keep }) = self.modal.take() {
+                    self.general.expert_mode = true;
+                    self.general.keep_expert_mode = keep;
+                    debug!("Config change: {self:?}");
+                    Config::save_changes(self, &phone.adb_id);
+                }
+                iced::Command::none()
+            }
+            Message::DryRun(toggled) => {
+                self.general.dry_run = toggled;
+                set_dry_run(toggled);
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::DenseMode(toggled) => {
+                self.general.dense_mode = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::ColorBlind(toggled) => {
+                self.general.color_blind = toggled;
+                set_color_overrides(toggled, &self.general.theme_colors);
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::LiveStateSync(toggled) => {
+                self.general.live_state_sync = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::AutoSelectDevice(toggled) => {
+                self.general.auto_select_device = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::ConfirmEveryAction(toggled) => {
+                self.general.confirm_every_action = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::PersistSelection(toggled) => {
+                self.general.persist_selection = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::FavoriteDevice(toggled) => {
+                self.device.favorite = toggled;
                 debug!("Config change: {self:?}");
                 Config::save_changes(self, &phone.adb_id);
                 iced::Command::none()
@@ -94,6 +374,14 @@ impl Settings {
                 }
                 iced::Command::none()
             }
+            Message::KeepData(toggled) => {
+                if phone.android_sdk >= 23 {
+                    self.device.keep_data = toggled;
+                    debug!("Config change: {self:?}");
+                    Config::save_changes(self, &phone.adb_id);
+                }
+                iced::Command::none()
+            }
             Message::MultiUserMode(toggled) => {
                 self.device.multi_user_mode = toggled;
                 debug!("Config change: {self:?}");
@@ -112,14 +400,16 @@ impl Settings {
             }
             Message::LoadDeviceSettings => {
                 let backups =
-                    list_available_backups(&self.general.backup_folder.join(&phone.adb_id));
-                let backup = BackupSettings {
-                    backups: backups.clone(),
+                    list_available_backups_in(self.general.backup_folders(), &phone.adb_id);
+                let mut backup = BackupSettings {
                     selected: backups.first().cloned(),
                     users: phone.user_list.clone(),
-                    selected_user: phone.user_list.first().copied(),
-                    backup_state: String::default(),
+                    selected_user: phone.user_list.first().cloned(),
+                    ..BackupSettings::default()
                 };
+                backup.set_backups(backups);
+                self.snapshot_taken = snapshot::load(&phone.adb_id).map(|s| s.timestamp);
+                self.snapshot_error = None;
                 match Config::load_configuration_file()
                     .devices
                     .iter()
@@ -134,6 +424,9 @@ impl Settings {
                             device_id: phone.adb_id.clone(),
                             multi_user_mode: supports_multi_user(phone),
                             disable_mode: false,
+                            favorite: false,
+                            keep_data: false,
+                            view_prefs: ViewPrefs::default(),
                             backup,
                         }
                     }
@@ -145,25 +438,152 @@ impl Settings {
                 self.device.backup.users = list_available_backup_user(d_path);
                 iced::Command::none()
             }
-            Message::BackupDevice => iced::Command::perform(
-                backup_phone(
-                    phone.user_list.clone(),
-                    self.device.device_id.clone(),
-                    packages.to_vec(),
-                ),
-                Message::DeviceBackedUp,
-            ),
+            Message::RenameBackup => {
+                self.backup_rename = match (&self.backup_rename, &self.device.backup.selected) {
+                    (None, Some(selected)) => Some(selected.to_string()),
+                    _ => None,
+                };
+                iced::Command::none()
+            }
+            Message::BackupNameInput(name) => {
+                if self.backup_rename.is_some() {
+                    self.backup_rename = Some(name);
+                }
+                iced::Command::none()
+            }
+            Message::BackupRenamed => {
+                let (Some(new_name), Some(selected)) = (
+                    self.backup_rename.take(),
+                    self.device.backup.selected.clone(),
+                ) else {
+                    return iced::Command::none();
+                };
+                match rename_backup(&selected, &new_name) {
+                    Ok(renamed) => {
+                        info!("[BACKUP] Renamed {selected} to {renamed}");
+                        self.device.backup.set_backups(list_available_backups_in(
+                            self.general.backup_folders(),
+                            &phone.adb_id,
+                        ));
+                        self.device.backup.selected = Some(renamed);
+                        self.device.backup.backup_state = String::default();
+                    }
+                    Err(err) => {
+                        error!("[BACKUP] Cannot rename {selected}: {err}");
+                        self.device.backup.backup_state = err;
+                    }
+                }
+                iced::Command::none()
+            }
+            Message::DeleteBackup => {
+                self.modal = self
+                    .device
+                    .backup
+                    .selected
+                    .clone()
+                    .map(PopUpModal::DeleteBackup);
+                iced::Command::none()
+            }
+            Message::BackupDeleted(backup) => {
+                self.modal = None;
+                // only restores use this counter
+                if *nb_running_async_adb_commands > 0 {
+                    self.device.backup.backup_state =
+                        "Cannot delete a backup while restoring".to_string();
+                    return iced::Command::none();
+                }
+                // also delete its copies, or they would show up in its place
+                let file_name = backup.path.file_name().unwrap_or_default();
+                let copies = self
+                    .general
+                    .backup_folders()
+                    .map(|dir| DisplayablePath {
+                        path: dir.join(&phone.adb_id).join(file_name),
+                    })
+                    .filter(|b| b.path.exists() && b.path != backup.path);
+                let mut result = delete_backup(&backup);
+                for copy in copies {
+                    result = result.and(delete_backup(&copy));
+                }
+                match result {
+                    Ok(()) => {
+                        info!("[BACKUP] Deleted {backup}");
+                        self.device.backup.backup_state = String::default();
+                    }
+                    Err(err) => {
+                        error!("[BACKUP] {err}");
+                        self.device.backup.backup_state = err;
+                    }
+                }
+                self.device.backup.set_backups(list_available_backups_in(
+                    self.general.backup_folders(),
+                    &phone.adb_id,
+                ));
+                self.device.backup.selected = self.device.backup.backups.first().cloned();
+                if let Some(selected) = self.device.backup.selected.clone() {
+                    self.device.backup.users = list_available_backup_user(selected);
+                }
+                iced::Command::none()
+            }
+            Message::BackupDevice => {
+                if self.backup_progress.is_some() {
+                    return iced::Command::none();
+                }
+                self.backup_progress = Some((0, 0, Instant::now()));
+                self.backup_error = None;
+
+                let users = phone.user_list.clone();
+                let device_id = self.device.device_id.clone();
+                let backup_dir = self.general.backup_folder.clone();
+                let secondary_dirs = self.general.secondary_backup_folders.clone();
+                let packages = packages.to_vec();
+                let (tx, rx) = iced::futures::channel::mpsc::unbounded();
+                // `backup_phone` never yields, so it gets its own thread
+                // to let progress messages reach the GUI while it runs.
+                std::thread::spawn(move || {
+                    let res = backup_phone(
+                        users,
+                        device_id,
+                        &backup_dir,
+                        &secondary_dirs,
+                        &packages,
+                        |done, total| {
+                            let _ = tx.unbounded_send(Message::BackupProgress(done, total));
+                        },
+                    );
+                    let _ = tx.unbounded_send(Message::DeviceBackedUp(res));
+                });
+                // the channel also closes if the thread panics
+                let ended = iced::futures::stream::once(async { Message::BackupEnded });
+                iced::Command::run(rx.chain(ended), std::convert::identity)
+            }
+            Message::BackupProgress(done, total) => {
+                if let Some(progress) = &mut self.backup_progress {
+                    (progress.0, progress.1) = (done, total);
+                }
+                iced::Command::none()
+            }
+            Message::BackupEnded => {
+                if self.backup_progress.take().is_some() {
+                    error!("[BACKUP FAILED] Backup stopped without a result");
+                    self.backup_error = Some("Backup failed unexpectedly".to_string());
+                }
+                iced::Command::none()
+            }
             Message::DeviceBackedUp(is_backed_up) => {
+                self.backup_progress = None;
                 match is_backed_up {
                     Ok(_) => {
                         info!("[BACKUP] Backup successfully created");
-                        self.device.backup.backups = list_available_backups(
-                            &self.general.backup_folder.join(phone.adb_id.clone()),
-                        );
+                        self.device.backup.set_backups(list_available_backups_in(
+                            self.general.backup_folders(),
+                            &phone.adb_id,
+                        ));
                         self.device.backup.selected = self.device.backup.backups.first().cloned();
                     }
                     Err(err) => {
                         error!("[BACKUP FAILED] Backup creation failed: {err:?}");
+                        self.backup_error = Some(err);
                     }
                 }
                 iced::Command::none()
@@ -178,6 +598,20 @@ impl Settings {
                             index: p.index,
                             removal: "RESTORE".to_string(),
                         };
+                        if let Some(apk) = &p.apk {
+                            *nb_running_async_adb_commands += 1;
+                            commands.push(iced::Command::perform(
+                                adb_install_apk(
+                                    phone.adb_id.clone(),
+                                    apk.clone(),
+                                    self.device.backup.selected_user.as_ref().map(|u| u.id),
+                                    p.commands.clone(),
+                                    p_info,
+                                ),
+                                Message::RestoringDevice,
+                            ));
+                            continue;
+                        }
                         for command in p.commands.clone() {
                             *nb_running_async_adb_commands += 1;
                             commands.push(iced::Command::perform(
@@ -214,6 +648,11 @@ impl Settings {
                 self.is_loading = false;
 
                 if let Ok(path) = result {
+                    if let Err(err) = check_writable(&path) {
+                        self.backup_error = Some(err);
+                        return iced::Command::none();
+                    }
+                    self.backup_error = None;
                     self.general.backup_folder = path;
                     Config::save_changes(self, &phone.adb_id);
                     #[expect(unused_must_use, reason = "side-effect")]
@@ -237,25 +676,387 @@ impl Settings {
                     iced::Command::perform(open_folder(), Message::FolderChosen)
                 }
             }
+            Message::OpenBackupOffline => {
+                if self.is_loading {
+                    iced::Command::none()
+                } else {
+                    self.is_loading = true;
+                    iced::Command::perform(
+                        open_backup_file(self.general.backup_folder.clone()),
+                        Message::BackupFileChosen,
+                    )
+                }
+            }
+            Message::BackupFileChosen(_) => {
+                self.is_loading = false;
+                self.offline_error = None;
+                iced::Command::none()
+            }
+            Message::OfflineBackupFailed(err) => {
+                self.offline_error = Some(err);
+                iced::Command::none()
+            }
+            Message::TakeSnapshot => {
+                if self.snapshot_running {
+                    return iced::Command::none();
+                }
+                self.snapshot_running = true;
+                let phone = phone.clone();
+                iced::Command::perform(
+                    async move {
+                        let snapshot = snapshot_state(&phone)?;
+                        snapshot::save(&phone.adb_id, &snapshot)?;
+                        Ok(snapshot.timestamp)
+                    },
+                    Message::SnapshotTaken,
+                )
+            }
+            Message::SnapshotTaken(res) => {
+                self.snapshot_running = false;
+                match res {
+                    Ok(timestamp) => {
+                        info!("[SNAPSHOT] Package states saved");
+                        self.snapshot_taken = Some(timestamp);
+                        self.snapshot_error = None;
+                    }
+                    Err(err) => self.snapshot_error = Some(err),
+                }
+                iced::Command::none()
+            }
+            Message::CompareSnapshot => {
+                if self.snapshot_running {
+                    return iced::Command::none();
+                }
+                let Some(snapshot) = snapshot::load(&phone.adb_id) else {
+                    self.snapshot_taken = None;
+                    return iced::Command::none();
+                };
+                self.snapshot_running = true;
+                let phone = phone.clone();
+                iced::Command::perform(
+                    async move { compare(&phone, &snapshot) },
+                    Message::SnapshotCompared,
+                )
+            }
+            Message::SnapshotCompared(res) => {
+                self.snapshot_running = false;
+                match res {
+                    Ok(changes) => {
+                        self.snapshot_error = None;
+                        self.modal = Some(PopUpModal::SnapshotChanges(changes));
+                    }
+                    Err(err) => self.snapshot_error = Some(err),
+                }
+                iced::Command::none()
+            }
+            Message::AddSecondaryBackUpFolder => {
+                if self.is_loading {
+                    iced::Command::none()
+                } else {
+                    self.is_loading = true;
+                    iced::Command::perform(open_folder(), Message::SecondaryFolderChosen)
+                }
+            }
+            Message::SecondaryFolderChosen(result) => {
+                self.is_loading = false;
+
+                if let Ok(path) = result {
+                    if let Err(err) = check_writable(&path) {
+                        self.backup_error = Some(err);
+                        return iced::Command::none();
+                    }
+                    self.backup_error = None;
+                    if !self.general.backup_folders().any(|f| *f == path) {
+                        self.general.secondary_backup_folders.push(path);
+                        Config::save_changes(self, &phone.adb_id);
+                    }
+                }
+                iced::Command::none()
+            }
+            Message::RemoveSecondaryBackUpFolder(i) => {
+                if i < self.general.secondary_backup_folders.len() {
+                    self.general.secondary_backup_folders.remove(i);
+                    Config::save_changes(self, &phone.adb_id);
+                }
+                iced::Command::none()
+            }
             Message::ExportPackages => iced::Command::perform(
-                export_packages(selected_user.unwrap_or_default(), packages.to_vec()),
+                export_packages(
+                    selected_user.unwrap_or_default(),
+                    packages.to_vec(),
+                    self.general.export_folder.clone(),
+                ),
                 Message::PackagesExported,
             ),
             Message::PackagesExported(exported) => {
                 match exported {
-                    Ok(_) => self.modal = Some(PopUpModal::ExportUninstalled),
-                    Err(err) => error!("Failed to export list of uninstalled packages: {err:?}"),
+                    Ok(path) => self.exported(PopUpModal::ExportUninstalled(path)),
+                    Err(err) => {
+                        error!("Failed to export list of uninstalled packages: {err:?}");
+                        self.export_error = Some(err);
+                    }
+                }
+                iced::Command::none()
+            }
+            Message::ChooseExportFolder => {
+                if self.is_loading {
+                    iced::Command::none()
+                } else {
+                    self.is_loading = true;
+                    iced::Command::perform(open_folder(), Message::ExportFolderChosen)
+                }
+            }
+            Message::ExportFolderChosen(result) => {
+                self.is_loading = false;
+                if let Ok(path) = result {
+                    if let Err(err) = check_writable(&path) {
+                        self.export_error = Some(err);
+                        return iced::Command::none();
+                    }
+                    self.export_error = None;
+                    self.general.export_folder = path;
+                    Config::save_changes(self, &phone.adb_id);
+                }
+                iced::Command::none()
+            }
+            Message::ChooseHook(hook) => {
+                if self.is_loading {
+                    iced::Command::none()
+                } else {
+                    self.is_loading = true;
+                    iced::Command::perform(open_script_file(), move |result| {
+                        Message::HookChosen(hook, result)
+                    })
+                }
+            }
+            Message::HookChosen(hook, result) => {
+                self.is_loading = false;
+                if let Ok(path) = result {
+                    self.set_hook(hook, Some(path));
+                    Config::save_changes(self, &phone.adb_id);
+                }
+                iced::Command::none()
+            }
+            Message::ClearHook(hook) => {
+                self.set_hook(hook, None);
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::AdbServerInput(input) => {
+                let server = match input.trim() {
+                    "" => Ok(None),
+                    server => adb::AdbServer::parse(server).map(Some),
+                };
+                self.adb_server_input = input;
+                let server = match server {
+                    Ok(server) => server,
+                    Err(e) => {
+                        // still being typed, most likely
+                        self.adb_server_error = Some(e);
+                        return iced::Command::none();
+                    }
+                };
+                self.adb_server_error = None;
+                self.general.adb_server = server
+                    .is_some()
+                    .then(|| self.adb_server_input.trim().to_string());
+                adb::set_server(server);
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::UncategorizedReport(toggled) => {
+                self.general.uncategorized_report = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::SelfUpdateEnabled(toggled) => {
+                // the release check is started (or dropped) by the caller
+                self.general.self_update_enabled = toggled;
+                debug!("Config change: {self:?}");
+                Config::save_changes(self, &phone.adb_id);
+                iced::Command::none()
+            }
+            Message::ExportReport => iced::Command::perform(
+                export_uncategorized_report(packages.to_vec(), self.general.export_folder.clone()),
+                Message::ReportExported,
+            ),
+            Message::ReportExported(exported) => {
+                match exported {
+                    Ok(path) => self.exported(PopUpModal::ExportReport(path)),
+                    Err(err) => {
+                        error!("Failed to export the report of uncategorized packages: {err}");
+                        self.export_error = Some(err);
+                    }
+                }
+                iced::Command::none()
+            }
+            Message::NewUserNameInput(name) => {
+                self.new_user_name = name;
+                iced::Command::none()
+            }
+            Message::CreateUser => {
+                let name = self.new_user_name.trim().to_string();
+                if name.is_empty() || !self.general.expert_mode {
+                    return iced::Command::none();
+                }
+                self.new_user_name.clear();
+                iced::Command::perform(
+                    create_user(phone.adb_id.clone(), name),
+                    Message::UsersChanged,
+                )
+            }
+            Message::RemoveUser(user) => {
+                if user.is_removable() && self.general.expert_mode {
+                    self.modal = Some(PopUpModal::RemoveUser(user));
+                }
+                iced::Command::none()
+            }
+            Message::ResetDeviceSettings => {
+                self.modal = Some(PopUpModal::ResetDevice);
+                iced::Command::none()
+            }
+            Message::DeviceSettingsReset => {
+                self.modal = None;
+                Config::forget_device(&phone.adb_id);
+                info!("Settings of {} reset to the defaults", phone.adb_id);
+                self.update(
+                    phone,
+                    packages,
+                    nb_running_async_adb_commands,
+                    Message::LoadDeviceSettings,
+                    selected_user,
+                )
+            }
+            Message::RemoveUserConfirmed(user) => {
+                self.modal = None;
+                iced::Command::perform(
+                    remove_user(phone.adb_id.clone(), user),
+                    Message::UsersChanged,
+                )
+            }
+            Message::UsersChanged(result) => {
+                match result {
+                    Ok(out) => {
+                        info!("[USERS] {out}");
+                        self.users_error = None;
+                    }
+                    Err(err) => {
+                        error!("[USERS] {err}");
+                        self.users_error = Some(err);
+                    }
+                }
+                iced::Command::none()
+            }
+            Message::ExportInventory => {
+                let user = selected_user.unwrap_or_default();
+                let user_packages = packages.get(user.index).cloned().unwrap_or_default();
+                iced::Command::perform(
+                    export_inventory_csv(user, user_packages, self.general.export_folder.clone()),
+                    Message::InventoryExported,
+                )
+            }
+            Message::InventoryExported(exported) => {
+                match exported {
+                    Ok(path) => self.exported(PopUpModal::ExportInventory(path)),
+                    Err(err) => {
+                        error!("Failed to export the package inventory: {err:?}");
+                        self.export_error = Some(err);
+                    }
                 }
                 iced::Command::none()
             }
         }
     }
 
+    /// Show where the export went
+    fn exported(&mut self, modal: PopUpModal) {
+        self.export_error = None;
+        open_url(self.general.export_folder.clone());
+        self.modal = Some(modal);
+    }
+
+    /// Take a snapshot of the package states, or compare the device to it
+    fn snapshot_row(&self) -> Element<'_, Message, Theme, Renderer> {
+        let idle = !self.snapshot_running;
+        let descr = match (&self.snapshot_error, &self.snapshot_taken) {
+            (Some(err), _) => text(err).style(style::Text::Danger),
+            _ if self.snapshot_running => text("Reading the package states..."),
+            (None, Some(timestamp)) => text(format!(
+                "Snapshot of {timestamp}: find the packages changed outside of {NAME} since"
+            )),
+            (None, None) => text(format!(
+                "Remember the package states, to find what changes outside of {NAME}"
+            )),
+        };
+        row![
+            button_primary(text("Snapshot").horizontal_alignment(alignment::Horizontal::Center))
+                .on_press_maybe(idle.then_some(Message::TakeSnapshot))
+                .width(77),
+            descr,
+            Space::new(Length::Fill, Length::Shrink),
+            button_primary("Compare to snapshot").on_press_maybe(
+                (idle && self.snapshot_taken.is_some()).then_some(Message::CompareSnapshot)
+            ),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center)
+        .into()
+    }
+
+    /// Creation and removal of the users of the device, for testing multi-user setups
+    fn users_ctn(&self, phone: &Phone) -> Element<'_, Message, Theme, Renderer> {
+        let create_row = row![
+            text_input("New user name", &self.new_user_name)
+                .on_input(Message::NewUserNameInput)
+                .on_submit(Message::CreateUser)
+                .padding(6)
+                .width(200),
+            button_primary("Create user").on_press_maybe(
+                (!self.new_user_name.trim().is_empty()).then_some(Message::CreateUser)
+            ),
+            Space::new(Length::Fill, Length::Shrink),
+            text(self.users_error.as_deref().unwrap_or_default()).style(style::Text::Danger),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let users = phone
+            .user_list
+            .iter()
+            .fold(column![].spacing(5), |col, user| {
+                let remove_btn = button(text("Remove").size(14))
+                    .padding([5, 8])
+                    .style(style::Button::UninstallPackage)
+                    .on_press_maybe(
+                        user.is_removable()
+                            .then(|| Message::RemoveUser(user.clone())),
+                    );
+                col.push(
+                    row![
+                        text(user.to_string()),
+                        Space::new(Length::Fill, Length::Shrink),
+                        remove_btn,
+                    ]
+                    .align_items(Alignment::Center),
+                )
+            });
+
+        container(column![create_row, users].spacing(10))
+            .padding(10)
+            .width(Length::Fill)
+            .height(Length::Shrink)
+            .style(style::Container::Frame)
+            .into()
+    }
+
     #[allow(clippy::too_many_lines)]
     pub fn view(&self, phone: &Phone, apps_view: &AppsView) -> Element<Message, Theme, Renderer> {
-        let radio_btn_theme = Theme::ALL
-            .iter()
-            .fold(row![].spacing(10), |column, option| {
+        let radio_btn_theme = scrollable(Theme::ALL.iter().fold(
+            row![].spacing(10).padding([0, 0, 12, 0]),
+            |column, option| {
                 column.push(
                     radio(
                         format!("{}", option.clone()),
@@ -265,12 +1066,31 @@ impl Settings {
                     )
                     .size(24),
                 )
-            });
-        let theme_ctn = container(radio_btn_theme)
-            .padding(10)
-            .width(Length::Fill)
-            .height(Length::Shrink)
-            .style(style::Container::Frame);
+            },
+        ))
+        .direction(scrollable::Direction::Horizontal(
+            scrollable::Properties::new().width(4).scroller_width(4),
+        ))
+        .id(THEMES_SCROLLABLE.clone());
+        let color_blind_checkbox = checkbox(
+            "Color-blind friendly colors (blue/orange instead of green/red)",
+            self.general.color_blind,
+        )
+        .on_toggle(Message::ColorBlind)
+        .style(style::CheckBox::SettingsEnabled);
+        let dense_mode_checkbox = checkbox(
+            "Compact package list (smaller rows)",
+            self.general.dense_mode,
+        )
+        .on_toggle(Message::DenseMode)
+        .style(style::CheckBox::SettingsEnabled);
+        let theme_ctn = container(
+            column![radio_btn_theme, color_blind_checkbox, dense_mode_checkbox].spacing(10),
+        )
+        .padding(10)
+        .width(Length::Fill)
+        .height(Length::Shrink)
+        .style(style::Container::Frame);
 
         let expert_mode_checkbox = checkbox(
             "Allow to uninstall packages marked as \"unsafe\" (I KNOW WHAT I AM DOING)",
@@ -283,6 +1103,57 @@ impl Settings {
             text("Most unsafe packages are known to bootloop the device if removed.")
                 .style(style::Text::Commentary);
 
+        let dry_run_checkbox = checkbox(
+            "Dry run: log the commands instead of running them",
+            self.general.dry_run,
+        )
+        .on_toggle(Message::DryRun)
+        .style(style::CheckBox::SettingsEnabled);
+
+        let dry_run_descr = text(
+            "Nothing is changed on the device, the package list only pretends it is until refreshed.",
+        )
+        .style(style::Text::Commentary);
+
+        let live_state_sync_checkbox = checkbox(
+            "Keep the package states in sync with the device",
+            self.general.live_state_sync,
+        )
+        .on_toggle(Message::LiveStateSync)
+        .style(style::CheckBox::SettingsEnabled);
+
+        let live_state_sync_descr = text(
+            "Picks up changes made outside of UAD-ng. The visible packages are re-checked every few seconds, at the cost of some ADB calls.",
+        )
+        .style(style::Text::Commentary);
+
+        let auto_select_device_checkbox = checkbox(
+            "Select the first connected device automatically",
+            self.general.auto_select_device,
+        )
+        .on_toggle(Message::AutoSelectDevice)
+        .style(style::CheckBox::SettingsEnabled);
+
+        let confirm_every_action_checkbox =
+            checkbox("Confirm every uninstall", self.general.confirm_every_action)
+                .on_toggle(Message::ConfirmEveryAction)
+                .style(style::CheckBox::SettingsEnabled);
+
+        let confirm_every_action_descr = text(
+            "Also ask before uninstalling (or disabling) a single package from its row, not only a reviewed selection.",
+        )
+        .style(style::Text::Commentary);
+
+        let persist_selection_checkbox =
+            checkbox("Remember the selection", self.general.persist_selection)
+                .on_toggle(Message::PersistSelection)
+                .style(style::CheckBox::SettingsEnabled);
+
+        let persist_selection_descr = text(
+            "Keep the selected packages of each device and user, to restore them after a restart or a crash.",
+        )
+        .style(style::Text::Commentary);
+
         let choose_backup_descr = text("Note: If you have previous backups, you will need to transfer them manually to newly changed backup folder to be able to use Restore functionality")
             .style(style::Text::Commentary);
 
@@ -301,13 +1172,111 @@ impl Settings {
         .spacing(10)
         .align_items(Alignment::Center);
 
+        let secondary_backup_rows = self
+            .general
+            .secondary_backup_folders
+            .iter()
+            .enumerate()
+            .fold(column![].spacing(10), |col, (i, folder)| {
+                col.push(
+                    row![
+                        button(text("Remove"))
+                            .padding([5, 10])
+                            .on_press(Message::RemoveSecondaryBackUpFolder(i))
+                            .style(style::Button::UninstallPackage),
+                        Space::new(Length::Fill, Length::Shrink),
+                        "Copy folder: ",
+                        text(folder.to_string_lossy()),
+                    ]
+                    .spacing(10)
+                    .align_items(Alignment::Center),
+                )
+            });
+
+        let add_secondary_backup_row = row![
+            button_primary("Add a copy folder").on_press(Message::AddSecondaryBackUpFolder),
+            text("Backups are also copied into these folders, in case the main one is lost")
+                .style(style::Text::Commentary),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let choose_export_row = row![
+            button(text("\u{E930}").font(ICONS))
+                .padding([5, 10])
+                .on_press(Message::ChooseExportFolder)
+                .style(style::Button::Primary),
+            "Choose export folder",
+            Space::new(Length::Fill, Length::Shrink),
+            "Current folder: ",
+            text(self.general.export_folder.to_string_lossy())
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let adb_server_row = row![
+            "ADB server",
+            text_input("5037", &self.adb_server_input)
+                .on_input(Message::AdbServerInput)
+                .padding(6)
+                .width(180),
+            if let Some(err) = &self.adb_server_error {
+                text(err).style(style::Text::Danger)
+            } else {
+                text("A port, e.g. for a server in a container, or host:port for a remote one. Empty follows ADB_SERVER_SOCKET, if set. Rescan the devices after changing it.")
+                    .style(style::Text::Commentary)
+            },
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        let uncategorized_report_checkbox = checkbox(
+            "Allow exporting a report of the removed packages that aren't in the lists",
+            self.general.uncategorized_report,
+        )
+        .on_toggle(Message::UncategorizedReport)
+        .style(style::CheckBox::SettingsEnabled);
+        let uncategorized_report_descr = text(
+            "Only package names, nothing identifying your device. Attach it to an issue to help improve the lists.",
+        )
+        .style(style::Text::Commentary);
+
+        #[cfg(feature = "self-update")]
+        let self_update_checkbox = Some(
+            checkbox(
+                format!("Check for {} updates", crate::core::utils::NAME),
+                self.general.self_update_enabled,
+            )
+            .on_toggle(Message::SelfUpdateEnabled)
+            .style(style::CheckBox::SettingsEnabled),
+        );
+        #[cfg(not(feature = "self-update"))]
+        let self_update_checkbox = None::<iced::widget::Checkbox<'_, Message, Theme, Renderer>>;
+
         let general_ctn = container(
             column![
                 expert_mode_checkbox,
                 expert_mode_descr,
+                dry_run_checkbox,
+                dry_run_descr,
+                live_state_sync_checkbox,
+                live_state_sync_descr,
+                confirm_every_action_checkbox,
+                confirm_every_action_descr,
+                persist_selection_checkbox,
+                persist_selection_descr,
+                auto_select_device_checkbox,
+                uncategorized_report_checkbox,
+                uncategorized_report_descr,
                 choose_backup_row,
                 choose_backup_descr,
+                secondary_backup_rows,
+                add_secondary_backup_row,
+                choose_export_row,
+                adb_server_row,
+                self.hook_rows(),
             ]
+            .push_maybe(self_update_checkbox)
             .spacing(10),
         )
         .padding(10)
@@ -337,7 +1306,10 @@ impl Settings {
                     .user_list
                     .iter()
                     .filter(|&u| u.protected)
-                    .map(|u| u.id.to_string())
+                    .map(|u| match &u.protected_reason {
+                        Some(reason) => format!("{} ({reason})", u.id),
+                        None => u.id.to_string(),
+                    })
                     .collect::<Vec<String>>()
                     .join(", ")
             )
@@ -351,6 +1323,10 @@ impl Settings {
         .on_toggle(Message::MultiUserMode)
         .style(style::CheckBox::SettingsEnabled);
 
+        let favorite_checkbox = checkbox("Favorite: list this device first", self.device.favorite)
+            .on_toggle(Message::FavoriteDevice)
+            .style(style::CheckBox::SettingsEnabled);
+
         let disable_checkbox_style = if phone.android_sdk >= 23 {
             style::CheckBox::SettingsEnabled
         } else {
@@ -393,12 +1369,41 @@ impl Settings {
             .width(Length::Fill)
         };
 
+        let reset_device_row = row![
+            button(text("Reset"))
+                .padding([5, 10])
+                .on_press(Message::ResetDeviceSettings)
+                .style(style::Button::UninstallPackage),
+            text("Reset this device's settings (and package list filters) to the defaults")
+                .style(style::Text::Commentary),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
+        // `pm uninstall -k` needs Android Marshmallow (6.0), like `pm uninstall`
+        let keep_data_checkbox = checkbox(
+            "Keep the data of the uninstalled packages",
+            self.device.keep_data,
+        )
+        .on_toggle_maybe((phone.android_sdk >= 23).then_some(Message::KeepData))
+        .style(disable_checkbox_style);
+        let keep_data_descr = text(
+            "The data and caches stay on the device (and keep taking space): \
+             a package restored later comes back with them, as if it was never removed. \
+             Packages uninstalled before this is enabled are restored empty.",
+        )
+        .style(style::Text::Commentary);
+
         let device_specific_ctn = container(
             column![
+                favorite_checkbox,
                 multi_user_mode_checkbox,
                 multi_user_mode_descr,
                 disable_setting_row,
                 disable_mode_descr,
+                keep_data_checkbox,
+                keep_data_descr,
+                reset_device_row,
             ]
             .spacing(10),
         )
@@ -407,18 +1412,62 @@ impl Settings {
         .height(Length::Shrink)
         .style(style::Container::Frame);
 
-        let backup_pick_list = pick_list(
-            self.device.backup.backups.clone(),
-            self.device.backup.selected.clone(),
-            Message::BackupSelected,
+        let backup_picker: Element<'_, Message, Theme, Renderer> = match &self.backup_rename {
+            Some(name) => text_input("New backup name", name)
+                .on_input(Message::BackupNameInput)
+                .on_submit(Message::BackupRenamed)
+                .padding(6)
+                .width(200)
+                .into(),
+            None => pick_list(
+                self.device.backup.backups.clone(),
+                self.device.backup.selected.clone(),
+                Message::BackupSelected,
+            )
+            .padding(6)
+            .into(),
+        };
+
+        let rename_backup_btn = button(
+            text(if self.backup_rename.is_some() {
+                "Cancel"
+            } else {
+                "Rename"
+            })
+            .size(14),
         )
-        .padding(6);
+        .padding([5, 8])
+        .on_press(Message::RenameBackup);
+
+        let delete_backup_btn = button(text("Delete").size(14))
+            .padding([5, 8])
+            .style(style::Button::UninstallPackage)
+            .on_press_maybe(
+                self.backup_rename
+                    .is_none()
+                    .then_some(Message::DeleteBackup),
+            );
 
         let backup_btn =
             button_primary(text("Backup").horizontal_alignment(alignment::Horizontal::Center))
-                .on_press(Message::BackupDevice)
+                .on_press_maybe(
+                    self.backup_progress
+                        .is_none()
+                        .then_some(Message::BackupDevice),
+                )
                 .width(77);
 
+        let backup_descr = match (self.backup_progress, &self.backup_error) {
+            (Some((done, total, started)), _) => {
+                text(match format_eta(done, total, started.elapsed()) {
+                    Some(eta) => format!("Backing up {done}/{total}, {eta}"),
+                    None => format!("Backing up {done}/{total}"),
+                })
+            }
+            (None, Some(err)) => text(err).style(style::Text::Danger),
+            (None, None) => text("Backup the current state of the phone"),
+        };
+
         let restore_btn = |enabled| {
             if enabled {
                 button(text("Restore").horizontal_alignment(alignment::Horizontal::Center))
@@ -445,11 +1494,19 @@ impl Settings {
         };
 
         let export_btn = button_primary("Export").on_press(Message::ExportPackages);
+        let export_inventory_btn = button_primary("Export all").on_press(Message::ExportInventory);
+
+        let last_backup = text(self.device.backup.last_backup.map_or_else(
+            || "No backups yet".to_string(),
+            |t| format!("Last backup: {}", format_diff_time_from_now(t)),
+        ))
+        .style(style::Text::Commentary);
 
         let backup_row = row![
             backup_btn,
-            "Backup the current state of the phone",
+            backup_descr,
             Space::new(Length::Fill, Length::Shrink),
+            last_backup,
             locate_backup_btn,
         ]
         .spacing(10)
@@ -463,20 +1520,38 @@ impl Settings {
                 "Restore the state of the device",
                 Space::new(Length::Fill, Length::Shrink),
                 text(self.device.backup.backup_state.clone()).style(style::Text::Danger),
-                backup_pick_list,
+                backup_picker,
+                rename_backup_btn,
+                delete_backup_btn,
             ]
             .spacing(10)
             .align_items(Alignment::Center)
         };
 
+        let open_offline_row = row![
+            button_primary("Open backup").on_press(Message::OpenBackupOffline),
+            "View a backup as an offline device, to prepare a selection",
+            Space::new(Length::Fill, Length::Shrink),
+            text(self.offline_error.as_deref().unwrap_or_default()).style(style::Text::Danger),
+        ]
+        .spacing(10)
+        .align_items(Alignment::Center);
+
         let no_device_ctn = || {
-            container(text("No device detected").style(style::Text::Danger))
-                .padding(10)
-                .width(Length::Fill)
-                .style(style::Container::BorderedFrame)
+            container(
+                text(if phone.backup.is_some() {
+                    "Offline / from backup: nothing can be sent to the device"
+                } else {
+                    "No device detected"
+                })
+                .style(style::Text::Danger),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .style(style::Container::BorderedFrame)
         };
 
-        let content = if phone.adb_id.is_empty() {
+        let content = if phone.adb_id.is_empty() || phone.backup.is_some() {
             column![
                 text("Theme").size(26),
                 theme_ctn,
@@ -486,6 +1561,10 @@ impl Settings {
                 no_device_ctn(),
                 text("Backup / Restore").size(26),
                 no_device_ctn(),
+                container(open_offline_row)
+                    .padding(10)
+                    .width(Length::Fill)
+                    .style(style::Container::Frame),
             ]
             .width(Length::Fill)
             .spacing(20)
@@ -496,18 +1575,46 @@ impl Settings {
                 Space::new(Length::Fill, Length::Shrink),
                 text(format!(
                     "Selected: user {}",
-                    apps_view.selected_user.unwrap_or_default().id
+                    apps_view.selected_user.as_ref().map_or(0, |u| u.id)
                 )),
             ]
             .spacing(10)
             .align_items(Alignment::Center);
 
-            let backup_restore_ctn =
-                container(column![backup_row, restore_row, export_row].spacing(10))
-                    .padding(10)
-                    .width(Length::Fill)
-                    .height(Length::Shrink)
-                    .style(style::Container::Frame);
+            let export_inventory_row = row![
+                export_inventory_btn,
+                "Export all packages (state, removal, list, description) as CSV",
+                Space::new(Length::Fill, Length::Shrink),
+                text(self.export_error.as_deref().unwrap_or_default()).style(style::Text::Danger),
+            ]
+            .spacing(10)
+            .align_items(Alignment::Center);
+
+            let export_report_row = self.general.uncategorized_report.then(|| {
+                row![
+                    button_primary("Export report").on_press(Message::ExportReport),
+                    "Export the removed packages that aren't in the lists",
+                ]
+                .spacing(10)
+                .align_items(Alignment::Center)
+            });
+
+            let backup_restore_ctn = container(
+                column![
+                    backup_row,
+                    restore_row,
+                    self.snapshot_row(),
+                    export_row,
+                    export_inventory_row
+                ]
+                .push_maybe(export_report_row)
+                .push(open_offline_row)
+                .spacing(10),
+            )
+            .padding(10)
+            .width(Length::Fill)
+            .height(Length::Shrink)
+            .style(style::Container::Frame);
 
             column![
                 text("Theme").size(26),
@@ -517,47 +1624,61 @@ impl Settings {
                 text("Current device").size(26),
                 warning_ctn,
                 device_specific_ctn,
-                text("Backup / Restore").size(26),
-                backup_restore_ctn,
             ]
+            .push_maybe(self.general.expert_mode.then(|| text("Users").size(26)))
+            .push_maybe(self.general.expert_mode.then(|| self.users_ctn(phone)))
+            .push(text("Backup / Restore").size(26))
+            .push(backup_restore_ctn)
             .width(Length::Fill)
             .spacing(20)
         };
 
-        if let Some(PopUpModal::ExportUninstalled) = self.modal {
-            let title = container(row![text("Success").size(24)].align_items(Alignment::Center))
-                .width(Length::Fill)
-                .style(style::Container::Frame)
-                .padding([10, 0, 10, 0])
-                .center_y()
-                .center_x();
-
-            let text_box = row![
-                text(format!("Exported uninstalled packages into file.\nFile is exported in same directory where {NAME} is located.")).width(Length::Fill),
-            ].padding(20);
-
-            let file_row = row![
-                text(generate_backup_name(chrono::Local::now())).style(style::Text::Commentary)
-            ]
-            .padding(20);
-
-            let modal_btn_row = row![
-                Space::new(Length::Fill, Length::Shrink),
-                button(text("Close").width(Length::Shrink))
-                    .width(Length::Shrink)
-                    .on_press(Message::ModalHide),
-                Space::new(Length::Fill, Length::Shrink),
-            ];
-
-            let ctn = container(column![title, text_box, file_row, modal_btn_row])
-                .height(Length::Shrink)
-                .width(500)
-                .padding(10)
-                .style(style::Container::Frame);
-
-            return Modal::new(content.padding(10), ctn)
+        match &self.modal {
+            Some(PopUpModal::ExportUninstalled(path)) => {
+                return Modal::new(
+                    content.padding(10),
+                    export_modal("uninstalled packages", path.to_string_lossy().into_owned()),
+                )
                 .on_blur(Message::ModalHide)
                 .into();
+            }
+            Some(PopUpModal::ExportInventory(path)) => {
+                return Modal::new(
+                    content.padding(10),
+                    export_modal("all packages", path.to_string_lossy().into_owned()),
+                )
+                .on_blur(Message::ModalHide)
+                .into();
+            }
+            Some(PopUpModal::ExportReport(path)) => {
+                return Modal::new(
+                    content.padding(10),
+                    export_modal(
+                        "uncategorized packages",
+                        path.to_string_lossy().into_owned(),
+                    ),
+                )
+                .on_blur(Message::ModalHide)
+                .into();
+            }
+            Some(PopUpModal::DeleteBackup(backup)) => {
+                return delete_backup_modal(backup).over(content.padding(10)).into();
+            }
+            Some(PopUpModal::RemoveUser(user)) => {
+                return remove_user_modal(user).over(content.padding(10)).into();
+            }
+            Some(PopUpModal::SnapshotChanges(changes)) => {
+                return Modal::new(content.padding(10), snapshot_changes_modal(changes))
+                    .on_blur(Message::ModalHide)
+                    .into();
+            }
+            Some(PopUpModal::ExpertMode { keep }) => {
+                return expert_mode_modal(*keep).over(content.padding(10)).into();
+            }
+            Some(PopUpModal::ResetDevice) => {
+                return reset_device_modal(phone).over(content.padding(10)).into();
+            }
+            None => {}
         }
 
         container(scrollable(content))
@@ -567,3 +1688,137 @@ impl Settings {
             .into()
     }
 }
+
+fn export_modal(what: &str, file: String) -> Element<'static, Message, Theme, Renderer> {
+    let title = container(row![text("Success").size(24)].align_items(Alignment::Center))
+        .width(Length::Fill)
+        .style(style::Container::Frame)
+        .padding([10, 0, 10, 0])
+        .center_y()
+        .center_x();
+
+    let text_box =
+        row![text(format!("Exported {what} into file.")).width(Length::Fill)].padding(20);
+
+    let file_row = row![text(file).style(style::Text::Commentary)].padding(20);
+
+    let modal_btn_row = row![
+        Space::new(Length::Fill, Length::Shrink),
+        button(text("Close").width(Length::Shrink))
+            .width(Length::Shrink)
+            .on_press(Message::ModalHide),
+        Space::new(Length::Fill, Length::Shrink),
+    ];
+
+    container(column![title, text_box, file_row, modal_btn_row])
+        .height(Length::Shrink)
+        .width(500)
+        .padding(10)
+        .style(style::Container::Frame)
+        .into()
+}
+
+fn expert_mode_modal(keep: bool) -> ConfirmModal<'static, Message> {
+    let body = column![
+        text("Expert mode allows uninstalling the packages marked as \"Unsafe\". Most of them are known to bootloop the device, which may then need a factory reset (and lose its data)."),
+        text("Only continue if you know exactly what each of these packages does, and have a backup."),
+        checkbox("Keep expert mode enabled after closing UAD-ng", keep)
+            .on_toggle(Message::ExpertModeKeep)
+            .style(style::CheckBox::SettingsEnabled),
+    ]
+    .spacing(10);
+
+    confirm_modal(
+        "Enable expert mode",
+        body,
+        Message::ExpertModeConfirmed,
+        Message::ModalHide,
+    )
+    .title_style(style::Text::Danger)
+    .confirm_label("I understand the risks")
+}
+
+fn snapshot_changes_modal(changes: &[Change]) -> Element<'_, Message, Theme, Renderer> {
+    let title =
+        container(row![text("Changes since the snapshot").size(24)].align_items(Alignment::Center))
+            .width(Length::Fill)
+            .style(style::Container::Frame)
+            .padding([10, 0, 10, 0])
+            .center_y()
+            .center_x();
+
+    let list: Element<'_, Message, Theme, Renderer> = if changes.is_empty() {
+        text("No package changed since the snapshot")
+            .style(style::Text::Commentary)
+            .into()
+    } else {
+        scrollable(
+            changes
+                .iter()
+                .fold(column![].spacing(4).padding([0, 15, 0, 0]), |col, c| {
+                    col.push(
+                        row![
+                            text(format!("user {}", c.user)).style(style::Text::Commentary),
+                            text(&c.package).width(Length::Fill),
+                            text(format!("{} \u{2192} {}", c.before, c.now)),
+                        ]
+                        .spacing(10),
+                    )
+                }),
+        )
+        .height(400)
+        .into()
+    };
+
+    let modal_btn_row = row![
+        Space::new(Length::Fill, Length::Shrink),
+        button_primary("Close").on_press(Message::ModalHide),
+    ]
+    .padding([10, 0, 0, 0]);
+
+    container(column![title, container(list).padding(20), modal_btn_row])
+        .height(Length::Shrink)
+        .width(700)
+        .padding(10)
+        .style(style::Container::Frame)
+        .into()
+}
+
+fn delete_backup_modal(backup: &DisplayablePath) -> ConfirmModal<'_, Message> {
+    confirm_modal(
+        "Delete backup",
+        text(format!(
+            "The backup \"{backup}\" will be permanently deleted, along with its copies."
+        )),
+        Message::BackupDeleted(backup.clone()),
+        Message::ModalHide,
+    )
+    .confirm_label("Delete")
+}
+
+fn reset_device_modal(phone: &Phone) -> ConfirmModal<'static, Message> {
+    confirm_modal(
+        "Reset device settings",
+        text(format!(
+            "The settings of {} will be reset to the defaults: \
+             disable mode, multi-user mode, keep data, favorite and the package list filters. \
+             Backups are kept.",
+            phone.model
+        )),
+        Message::DeviceSettingsReset,
+        Message::ModalHide,
+    )
+    .confirm_label("Reset")
+}
+
+fn remove_user_modal(user: &User) -> ConfirmModal<'_, Message> {
+    confirm_modal(
+        "Remove user",
+        text(format!(
+            "{user} will be removed from the device, along with all its apps and data."
+        )),
+        Message::RemoveUserConfirmed(user.clone()),
+        Message::ModalHide,
+    )
+    .confirm_label("Remove")
+}
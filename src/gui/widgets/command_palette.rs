@@ -0,0 +1,92 @@
+use crate::core::theme::Theme;
+use crate::gui::{style, widgets::modal::Modal, widgets::text};
+use iced::widget::{button, column, container, scrollable, text_input};
+use iced::{Element, Length, Renderer};
+use std::sync::LazyLock;
+
+/// Search input of the palette, focused when it opens
+pub static PALETTE_INPUT: LazyLock<text_input::Id> = LazyLock::new(text_input::Id::unique);
+
+/// Entries of `commands` whose label contains every word of `query`, ignoring case
+pub fn matching<'a, Message>(
+    commands: &'a [(String, Message)],
+    query: &str,
+) -> impl Iterator<Item = &'a (String, Message)> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    commands.iter().filter(move |(label, _)| {
+        let label = label.to_lowercase();
+        words.iter().all(|w| label.contains(w.as_str()))
+    })
+}
+
+/// A searchable list of `(label, message)` `commands`, centered over `base`.
+///
+/// `on_run` wraps the message of the chosen command (clicked, or the first match on Enter),
+/// `on_close` is sent when clicking outside of the palette.
+pub fn command_palette<'a, Message: Clone + 'a>(
+    base: impl Into<Element<'a, Message, Theme, Renderer>>,
+    query: &str,
+    commands: &[(String, Message)],
+    on_input: fn(String) -> Message,
+    on_run: fn(Message) -> Message,
+    on_close: Message,
+) -> Modal<'a, Message, Theme, Renderer> {
+    let matches: Vec<_> = matching(commands, query).collect();
+
+    let mut input = text_input("Type a command...", query)
+        .id(PALETTE_INPUT.clone())
+        .on_input(on_input)
+        .padding([5, 10]);
+    if let Some((_, msg)) = matches.first() {
+        input = input.on_submit(on_run(msg.clone()));
+    }
+
+    let list: Element<'a, Message, Theme, Renderer> = if matches.is_empty() {
+        text("No matching command")
+            .style(style::Text::Commentary)
+            .into()
+    } else {
+        let entries = matches
+            .into_iter()
+            .fold(column![].spacing(2), |col, (label, msg)| {
+                col.push(
+                    button(text(label.clone()))
+                        .style(style::Button::Primary)
+                        .width(Length::Fill)
+                        .on_press(on_run(msg.clone())),
+                )
+            });
+        scrollable(entries).into()
+    };
+
+    let palette = container(column![input, list].spacing(10))
+        .width(500)
+        .max_height(400)
+        .padding(10)
+        .style(style::Container::Frame);
+
+    Modal::new(base, palette).on_blur(on_close)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_commands() {
+        let commands = [
+            ("Rescan devices".to_string(), 1),
+            ("Reload packages".to_string(), 2),
+            ("Open backup folder".to_string(), 3),
+        ];
+        let found = |query| {
+            matching(&commands, query)
+                .map(|(_, m)| *m)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(found(""), [1, 2, 3]);
+        assert_eq!(found("re"), [1, 2]);
+        assert_eq!(found("FOLDER backup"), [3]);
+        assert_eq!(found("update"), Vec::<i32>::new());
+    }
+}
@@ -0,0 +1,118 @@
+use crate::core::theme::Theme;
+use crate::gui::{style, widgets::modal::Modal, widgets::text};
+use iced::widget::{Space, button, column, container, row};
+use iced::{Alignment, Element, Length, Renderer};
+
+/// A dialog asking to confirm an action, see [`confirm_modal`]
+pub struct ConfirmModal<'a, Message> {
+    title: String,
+    title_style: style::Text,
+    body: Element<'a, Message, Theme, Renderer>,
+    confirm_label: String,
+    confirm_style: style::Button,
+    on_confirm: Message,
+    on_cancel: Message,
+}
+
+/// A yes/no dialog: `on_cancel` is also sent when clicking outside of it.
+///
+/// The confirm button says "Confirm" and looks destructive,
+/// see [`ConfirmModal::confirm_label`] and [`ConfirmModal::confirm_style`].
+pub fn confirm_modal<'a, Message>(
+    title: impl Into<String>,
+    body: impl Into<Element<'a, Message, Theme, Renderer>>,
+    on_confirm: Message,
+    on_cancel: Message,
+) -> ConfirmModal<'a, Message> {
+    ConfirmModal {
+        title: title.into(),
+        title_style: style::Text::Default,
+        body: body.into(),
+        confirm_label: "Confirm".to_string(),
+        confirm_style: style::Button::UninstallPackage,
+        on_confirm,
+        on_cancel,
+    }
+}
+
+impl<'a, Message: Clone + 'a> ConfirmModal<'a, Message> {
+    #[must_use]
+    pub fn title_style(self, title_style: style::Text) -> Self {
+        Self {
+            title_style,
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn confirm_label(self, label: impl Into<String>) -> Self {
+        Self {
+            confirm_label: label.into(),
+            ..self
+        }
+    }
+
+    #[must_use]
+    pub fn confirm_style(self, confirm_style: style::Button) -> Self {
+        Self {
+            confirm_style,
+            ..self
+        }
+    }
+
+    /// The dialog, centered over `base`
+    pub fn over(
+        self,
+        base: impl Into<Element<'a, Message, Theme, Renderer>>,
+    ) -> Modal<'a, Message, Theme, Renderer> {
+        let title = container(
+            row![text(self.title).size(24).style(self.title_style)].align_items(Alignment::Center),
+        )
+        .width(Length::Fill)
+        .style(style::Container::Frame)
+        .padding([10, 0, 10, 0])
+        .center_y()
+        .center_x();
+
+        let body = container(self.body).width(Length::Fill).padding(20);
+
+        let modal_btn_row = row![
+            button(text("Cancel")).on_press(self.on_cancel.clone()),
+            Space::new(Length::Fill, Length::Shrink),
+            button(text(self.confirm_label))
+                .style(self.confirm_style)
+                .on_press(self.on_confirm),
+        ]
+        .padding([0, 20, 10, 20]);
+
+        let dialog = container(column![title, body, modal_btn_row])
+            .height(Length::Shrink)
+            .width(500)
+            .padding(10)
+            .style(style::Container::Frame);
+
+        Modal::new(base, dialog).on_blur(self.on_cancel)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use iced::widget::button::StyleSheet;
+
+    #[test]
+    fn confirm_stands_out_in_every_theme() {
+        let modal: Element<'_, (), Theme, Renderer> =
+            confirm_modal("Delete", text("Sure?"), (), ())
+                .confirm_label("Delete")
+                .over(text("base"))
+                .into();
+        drop(modal);
+
+        for theme in [Theme::Lupin, Theme::Dark, Theme::Light] {
+            let cancel = theme.active(&style::Button::Primary);
+            let confirm = theme.active(&style::Button::UninstallPackage);
+            assert_ne!(cancel.text_color, confirm.text_color, "{theme}");
+        }
+    }
+}
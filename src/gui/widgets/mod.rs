@@ -1,3 +1,5 @@
+pub mod command_palette;
+pub mod confirm_modal;
 pub mod modal;
 pub mod navigation_menu;
 pub mod package_row;
@@ -2,6 +2,7 @@ use crate::core::helpers::button_primary;
 pub use crate::core::sync::Phone;
 use crate::core::theme::Theme;
 use crate::core::update::{SelfUpdateState, SelfUpdateStatus};
+use crate::core::utils::format_size;
 pub use crate::gui::views::about::Message as AboutMessage;
 pub use crate::gui::views::list::{List as AppsView, LoadingState as ListLoadingState};
 use crate::gui::{Message, style, widgets::text};
@@ -14,27 +15,69 @@ pub const ICONS: Font = Font {
     ..Font::DEFAULT
 };
 
-pub fn nav_menu<'a>(
-    device_list: &'a [Phone],
-    selected_device: Option<Phone>,
-    apps_view: &AppsView,
-    self_update_state: &SelfUpdateState,
-) -> Element<'a, Message, Theme, Renderer> {
-    let apps_refresh_btn = button_primary(
-        text("\u{E900}")
-            .font(ICONS)
-            .width(22)
-            .horizontal_alignment(alignment::Horizontal::Center),
+/// With a badge of the `selected` packages, as a reminder from the other views
+fn apps_button(selected: usize) -> Element<'static, Message, Theme, Renderer> {
+    match selected {
+        0 => button_primary("Apps"),
+        n => button_primary(
+            row![
+                text("Apps"),
+                container(text(n.to_string()).size(12))
+                    .padding([0, 5])
+                    .style(style::Container::Badge)
+            ]
+            .align_items(Alignment::Center)
+            .spacing(5),
+        ),
+    }
+    .on_press(Message::AppsPress)
+    .into()
+}
+
+fn reboot_wait_button(online: bool) -> Element<'static, Message, Theme, Renderer> {
+    tooltip(
+        button_primary("Reboot & wait")
+            .on_press_maybe(online.then_some(Message::RebootAndWaitPressed)),
+        "Reboot, and select the device again once it's back",
+        tooltip::Position::Bottom,
     )
-    .on_press(Message::RefreshButtonPressed);
+    .style(style::Container::Tooltip)
+    .gap(4)
+    .into()
+}
 
-    let apps_refresh_tooltip = tooltip(apps_refresh_btn, "Refresh apps", tooltip::Position::Bottom)
-        .style(style::Container::Tooltip)
-        .gap(4);
+/// Cheaper than [`rescan_button`], for changes made on the device
+fn reload_device_button() -> Element<'static, Message, Theme, Renderer> {
+    tooltip(
+        button_primary(
+            text("\u{E900}")
+                .font(ICONS)
+                .width(22)
+                .horizontal_alignment(alignment::Horizontal::Center),
+        )
+        .on_press(Message::ReloadDevicePressed),
+        "Reload the packages of this device",
+        tooltip::Position::Bottom,
+    )
+    .style(style::Container::Tooltip)
+    .gap(4)
+    .into()
+}
 
-    let reboot_btn = button_primary("Reboot").on_press(Message::RebootButtonPressed);
+fn rescan_button() -> Element<'static, Message, Theme, Renderer> {
+    tooltip(
+        button_primary("Rescan").on_press(Message::RefreshButtonPressed),
+        "Look for connected devices (USB and network) again",
+        tooltip::Position::Bottom,
+    )
+    .style(style::Container::Tooltip)
+    .gap(4)
+    .into()
+}
 
-    let uad_version_text = if let Some(r) = &self_update_state.latest_release {
+/// The version, or the state of the self-update
+fn version_text(self_update_state: &SelfUpdateState) -> Element<'static, Message, Theme, Renderer> {
+    let version = if let Some(r) = &self_update_state.latest_release {
         match self_update_state.status {
             SelfUpdateStatus::Failed => text(format!("Failed to update to {}", r.tag_name)),
             SelfUpdateStatus::Checking => text(SelfUpdateStatus::Checking.to_string()),
@@ -44,10 +87,28 @@ pub fn nav_menu<'a>(
                 r.tag_name
             )),
             SelfUpdateStatus::Updating => text("Updating please wait..."),
+            // there's no release without a check
+            SelfUpdateStatus::Disabled => text(format!("v{}", env!("CARGO_PKG_VERSION"))),
         }
     } else {
         text(format!("v{}", env!("CARGO_PKG_VERSION")))
     };
+    version.into()
+}
+
+pub fn nav_menu<'a>(
+    device_list: &'a [Phone],
+    selected_device: Option<Phone>,
+    apps_view: &AppsView,
+    self_update_state: &SelfUpdateState,
+) -> Element<'a, Message, Theme, Renderer> {
+    // a backup opened offline isn't connected
+    let online = selected_device.as_ref().is_none_or(|d| d.backup.is_none());
+    let reboot_btn =
+        button_primary("Reboot").on_press_maybe(online.then_some(Message::RebootButtonPressed));
+    let reboot_wait_btn = reboot_wait_button(online);
+
+    let uad_version_text = version_text(self_update_state);
 
     let update_btn = if self_update_state.latest_release.is_some() {
         button("Update")
@@ -58,7 +119,7 @@ pub fn nav_menu<'a>(
         button("").height(0).width(0).style(style::Button::Hidden)
     };
 
-    let apps_btn = button_primary("Apps").on_press(Message::AppsPress);
+    let apps_btn = apps_button(apps_view.selected_count());
 
     let about_btn = button_primary("About").on_press(Message::AboutPressed);
 
@@ -75,11 +136,39 @@ pub fn nav_menu<'a>(
         _ => text("No devices/emulators found"),
     };
 
+    let storage_text = match apps_view.storage {
+        Some((total, free)) => text(format!(
+            "{} free of {}",
+            format_size(free),
+            format_size(total)
+        ))
+        .style(style::Text::Commentary),
+        None => text(""),
+    };
+
     let row = match selected_device {
         Some(phone) => row![
             reboot_btn,
-            apps_refresh_tooltip,
+            reboot_wait_btn,
+            reload_device_button(),
             pick_list(device_list, Some(phone), Message::DeviceSelected,),
+            rescan_button(),
+            storage_text,
+            Space::new(Length::Fill, Length::Shrink),
+            uad_version_text,
+            update_btn,
+            apps_btn,
+            about_btn,
+            settings_btn,
+        ]
+        .width(Length::Fill)
+        .align_items(Alignment::Center)
+        .spacing(10),
+        // see `GeneralSettings::auto_select_device`
+        None if !device_list.is_empty() => row![
+            rescan_button(),
+            pick_list(device_list, None::<Phone>, Message::DeviceSelected)
+                .placeholder("Select a device"),
             Space::new(Length::Fill, Length::Shrink),
             uad_version_text,
             update_btn,
@@ -92,7 +181,7 @@ pub fn nav_menu<'a>(
         .spacing(10),
         None => row![
             reboot_btn,
-            apps_refresh_tooltip,
+            rescan_button(),
             device_list_text,
             Space::new(Length::Fill, Length::Shrink),
             uad_version_text,
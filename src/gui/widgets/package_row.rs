@@ -1,20 +1,34 @@
 use crate::core::sync::Phone;
 use crate::core::theme::Theme;
-use crate::core::uad_lists::{PackageState, Removal, UadList};
+use crate::core::uad_lists::{Origin, PackageState, Removal, UadList};
 use crate::gui::style;
 use crate::gui::views::settings::Settings;
 use crate::gui::widgets::text;
 
-use iced::widget::{Space, button, checkbox, row};
+use iced::widget::{Space, button, checkbox, container, row, tooltip};
 use iced::{Alignment, Command, Element, Length, Renderer, alignment};
 
+/// Shown on rows whose action is blocked by [`PackageRow::is_blocked`]
+pub const UNSAFE_BLOCKED: &str = "Marked unsafe — enable Expert Mode to act on this";
+/// Why some packages are marked unsafe
+pub const UNSAFE_WIKI_URL: &str = "https://github.com/Universal-Debloater-Alliance/universal-android-debloater-next-generation/wiki/FAQ";
+
 #[derive(Clone, Debug)]
+#[allow(clippy::struct_excessive_bools, reason = "Independent flags")]
 pub struct PackageRow {
     pub name: String,
     pub state: PackageState,
     pub description: String,
     pub uad_list: UadList,
     pub removal: Removal,
+    /// See [`Origin::classify`]
+    pub origin: Origin,
+    /// Disabled by the system rather than the user,
+    /// see [`crate::core::sync::DISABLED_UNTIL_USED`]
+    pub disabled_until_used: bool,
+    /// Uninstalled, but some of its APKs remain,
+    /// see [`crate::core::sync::leftover_apks`]
+    pub leftover_apks: bool,
     pub selected: bool,
     pub current: bool,
 }
@@ -23,6 +37,8 @@ pub struct PackageRow {
 pub enum Message {
     PackagePressed,
     ActionPressed,
+    /// Same as `ActionPressed`, but for every user of the device
+    AllUsersActionPressed,
     ToggleSelection(bool),
 }
 
@@ -42,83 +58,163 @@ impl PackageRow {
             description: description.to_string(),
             uad_list,
             removal,
+            origin: Origin::Other,
+            disabled_until_used: false,
+            leftover_apks: false,
             selected,
             current,
         }
     }
 
-    pub fn update(&mut self, _message: &Message) -> Command<Message> {
-        Command::none()
+    /// Removal of unsafe packages needs expert mode
+    pub fn is_blocked(&self, settings: &Settings) -> bool {
+        self.removal == Removal::Unsafe
+            && self.state == PackageState::Enabled
+            && !settings.general.expert_mode
     }
 
-    pub fn view(&self, settings: &Settings, _phone: &Phone) -> Element<Message, Theme, Renderer> {
-        //let trash_svg = format!("{}/resources/assets/trash.svg", env!("CARGO_MANIFEST_DIR"));
-        //let restore_svg = format!("{}/resources/assets/rotate.svg", env!("CARGO_MANIFEST_DIR"));
-        let button_style;
-        let action_text;
-        let action_btn;
-        let selection_checkbox;
-
+    /// Label and style of the action button
+    pub fn action(&self, disable_mode: bool) -> (&'static str, style::Button) {
         match self.state {
-            PackageState::Enabled => {
-                action_text = if settings.device.disable_mode {
-                    "Disable"
-                } else {
-                    "Uninstall"
-                };
-                button_style = style::Button::UninstallPackage;
-            }
-            PackageState::Disabled => {
-                action_text = "Enable";
-                button_style = style::Button::RestorePackage;
-            }
-            PackageState::Uninstalled => {
-                action_text = "Restore";
-                button_style = style::Button::RestorePackage;
-            }
+            PackageState::Enabled if disable_mode => ("Disable", style::Button::UninstallPackage),
+            PackageState::Enabled => ("Uninstall", style::Button::UninstallPackage),
+            PackageState::Disabled => ("Enable", style::Button::RestorePackage),
+            PackageState::Uninstalled => ("Restore", style::Button::RestorePackage),
             PackageState::All => {
-                action_text = "Error";
-                button_style = style::Button::RestorePackage;
                 warn!("Incredible! Something impossible happened!");
+                ("Error", style::Button::RestorePackage)
             }
         }
-        // Disable any removal action for unsafe packages if expert_mode is disabled
-        if self.removal != Removal::Unsafe
-            || self.state != PackageState::Enabled
-            || settings.general.expert_mode
-        {
-            selection_checkbox = checkbox("", self.selected)
-                .on_toggle(Message::ToggleSelection)
-                .style(style::CheckBox::PackageEnabled);
+    }
 
-            action_btn = button(
-                text(action_text)
-                    .horizontal_alignment(alignment::Horizontal::Center)
-                    .width(100),
-            )
-            .on_press(Message::ActionPressed);
-        } else {
-            selection_checkbox = checkbox("", self.selected)
-                .on_toggle(Message::ToggleSelection)
-                .style(style::CheckBox::PackageDisabled);
+    /// Tells the packages the system disabled apart from the ones the user did
+    fn until_used_label(&self, size: u16) -> Element<'_, Message, Theme, Renderer> {
+        if !(self.disabled_until_used && self.state == PackageState::Disabled) {
+            return row![].into();
+        }
+        tooltip(
+            text("disabled until used")
+                .size(size)
+                .style(style::Text::Warning),
+            "Disabled by the system, not by you: it's enabled again when an app needs it",
+            tooltip::Position::Left,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4)
+        .into()
+    }
 
-            action_btn = button(
-                text(action_text)
-                    .horizontal_alignment(alignment::Horizontal::Center)
-                    .width(100),
-            );
+    /// Tells the packages whose uninstall left some APKs behind
+    fn leftover_label(&self, size: u16) -> Element<'_, Message, Theme, Renderer> {
+        if !(self.leftover_apks && self.state == PackageState::Uninstalled) {
+            return row![].into();
         }
+        tooltip(
+            text("not fully uninstalled")
+                .size(size)
+                .style(style::Text::Warning),
+            "Some APKs of this package survived its uninstall: restore it, then uninstall it again",
+            tooltip::Position::Left,
+        )
+        .style(style::Container::Tooltip)
+        .gap(4)
+        .into()
+    }
 
-        row![
+    pub fn update(&mut self, _message: &Message) -> Command<Message> {
+        Command::none()
+    }
+
+    /// `pending` greys out the action button while a previous action
+    /// on this package hasn't finished yet.
+    /// `users_state` is the state of the package for every user, like `0:E 10:D`.
+    /// `focused` outlines the row that has the keyboard focus.
+    /// `disable_mode` is the effective one, which Alt inverts.
+    pub fn view(
+        &self,
+        settings: &Settings,
+        disable_mode: bool,
+        phone: &Phone,
+        pending: bool,
+        users_state: String,
+        focused: bool,
+    ) -> Element<'_, Message, Theme, Renderer> {
+        //let trash_svg = format!("{}/resources/assets/trash.svg", env!("CARGO_MANIFEST_DIR"));
+        //let restore_svg = format!("{}/resources/assets/rotate.svg", env!("CARGO_MANIFEST_DIR"));
+        let dense = settings.general.dense_mode;
+        let text_size = if dense { 13 } else { 16 };
+        let button_padding = if dense { [2, 8] } else { [5, 10] };
+        let (action_text, button_style) = self.action(disable_mode);
+        let actionable = !self.is_blocked(settings);
+        let selection_checkbox = checkbox("", self.selected)
+            .on_toggle(Message::ToggleSelection)
+            .style(if actionable {
+                style::CheckBox::PackageEnabled
+            } else {
+                style::CheckBox::PackageDisabled
+            });
+        let clickable = actionable && !pending && phone.backup.is_none();
+        let action_btn = button(
+            text(action_text)
+                .size(text_size)
+                .horizontal_alignment(alignment::Horizontal::Center)
+                .width(100),
+        )
+        .padding(button_padding)
+        .on_press_maybe(clickable.then_some(Message::ActionPressed));
+        let all_users_btn = button(text("All users").size(text_size - 2))
+            .padding(button_padding)
+            .on_press_maybe(clickable.then_some(Message::AllUsersActionPressed));
+
+        // a disabled button gives no feedback by itself
+        let action_btn: Element<'_, Message, Theme, Renderer> = if actionable {
+            action_btn.style(button_style).into()
+        } else {
+            tooltip(
+                action_btn.style(button_style),
+                text(UNSAFE_BLOCKED).size(14),
+                tooltip::Position::Left,
+            )
+            .style(style::Container::Tooltip)
+            .gap(4)
+            .into()
+        };
+
+        let multi_user_action = if phone.user_list.iter().filter(|u| !u.protected).count() > 1 {
+            row![
+                tooltip(
+                    all_users_btn.style(button_style),
+                    text(format!("{action_text} for all users")),
+                    tooltip::Position::Left,
+                )
+                .style(style::Container::Tooltip)
+                .gap(4)
+            ]
+            .padding([0, 6, 0, 0])
+        } else {
+            row![]
+        };
+
+        let row = row![
             button(
                 row![
                     selection_checkbox,
-                    text(&self.name).width(Length::FillPortion(8)),
-                    action_btn.style(button_style)
+                    text(&self.name)
+                        .size(text_size)
+                        .width(Length::FillPortion(8)),
+                    text(users_state)
+                        .size(text_size - 2)
+                        .style(style::Text::Commentary),
+                    self.until_used_label(text_size - 2),
+                    self.leftover_label(text_size - 2),
+                    Space::with_width(10),
+                    multi_user_action,
+                    action_btn
                 ]
                 .align_items(Alignment::Center)
             )
-            .padding(8)
+            // the checkbox keeps its size, so it's still easy to hit
+            .padding(if dense { 2 } else { 8 })
             .style(if self.current {
                 style::Button::SelectedPackage
             } else {
@@ -128,7 +224,15 @@ impl PackageRow {
             .on_press(Message::PackagePressed),
             Space::with_width(15)
         ]
-        .align_items(Alignment::Center)
-        .into()
+        .align_items(Alignment::Center);
+
+        container(row)
+            .padding(2)
+            .style(if focused {
+                style::Container::Focused
+            } else {
+                style::Container::Invisible
+            })
+            .into()
     }
 }
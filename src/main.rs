@@ -2,24 +2,26 @@
 #[macro_use]
 extern crate log;
 
-use crate::core::utils::setup_uad_dir;
+use crate::core::utils::{data_dir, fallback_data_dir, setup_uad_dir};
 use fern::{
     FormatCallback,
     colors::{Color, ColoredLevelConfig},
 };
 use log::Record;
 use std::sync::LazyLock;
-use std::{fmt::Arguments, fs::OpenOptions, path::PathBuf};
+use std::{fmt::Arguments, fs::OpenOptions, path::PathBuf, process::ExitCode};
 
+mod cli;
 mod core;
 mod gui;
 
+/// In portable mode, these are beside the executable (see [`core::utils::PORTABLE_FLAG`])
 static CONFIG_DIR: LazyLock<PathBuf> =
-    LazyLock::new(|| setup_uad_dir(&dirs::config_dir().expect("Can't detect config dir")));
+    LazyLock::new(|| setup_uad_dir(&data_dir(dirs::config_dir(), "config")));
 static CACHE_DIR: LazyLock<PathBuf> =
-    LazyLock::new(|| setup_uad_dir(&dirs::cache_dir().expect("Can't detect cache dir")));
+    LazyLock::new(|| setup_uad_dir(&data_dir(dirs::cache_dir(), "cache")));
 
-fn main() -> iced::Result {
+fn main() -> ExitCode {
     // Safety: This function is safe to call in a single-threaded program.
     // The exact requirement is: you must ensure that there are no other threads concurrently writing or
     // reading(!) the environment through functions or global variables other than the ones in this module.
@@ -30,7 +32,49 @@ fn main() -> iced::Result {
     }
 
     setup_logger().expect("setup logging");
-    gui::UadGui::start()
+    // the log file is in `CACHE_DIR`, so this can't be logged any earlier
+    for (dir, fallback) in [(&*CONFIG_DIR, "config"), (&*CACHE_DIR, "cache")] {
+        if dir.starts_with(fallback_data_dir(fallback)) {
+            warn!("Can't detect the {fallback} dir, using {}", dir.display());
+        }
+    }
+
+    // the previous version, if we were just relaunched by a self-update
+    #[cfg(feature = "self-update")]
+    core::update::cleanup_after_update(std::env::args().skip(1));
+
+    if cli::help_requested(std::env::args().skip(1)) {
+        print!("{}", cli::USAGE);
+        return ExitCode::SUCCESS;
+    }
+
+    match cli::parse(std::env::args().skip(1)) {
+        Ok(Some(args)) => {
+            return match cli::run(&args) {
+                Ok(summary) => {
+                    println!("{summary}");
+                    ExitCode::from(summary.exit_code())
+                }
+                Err(err) => {
+                    eprintln!("ERROR: {err}");
+                    ExitCode::from(err.exit_code())
+                }
+            };
+        }
+        Ok(None) => {}
+        Err(err) => {
+            eprintln!("ERROR: {err}");
+            return ExitCode::from(cli::EXIT_ERROR);
+        }
+    }
+
+    match gui::UadGui::start() {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            error!("{err}");
+            ExitCode::FAILURE
+        }
+    }
 }
 
 /// Sets up logging to a new file in `CACHE_DIR"/uadng.log"`